@@ -598,8 +598,6 @@ const OPK_DIFF_MAX = 0.25;
 const OPK_VIEW_MAX_CELLS = 65536;
 const OPK_PAYLOAD_EVERY_STEPS = 20000;
 const MAINT_EVERY_STEPS = 50000;
-const MAINT_TRIALS = 8;
-const MAINT_ERR_FRAC = 0.5;
 const MAINT_SERIES_CAP = 240;
 /** Threshold for "bad cell" in maintenance overlay: |base - meta0| > tau */
 const MAINT_BAD_CELL_TAU = 3;
@@ -845,80 +843,6 @@ function buildOpkMismatchMap({
   return out;
 }
 
-function quadrantIndex(idx: number, g: number): number {
-  const x = idx % g;
-  const y = Math.floor(idx / g);
-  const qx = x < g / 2 ? 0 : 1;
-  const qy = y < g / 2 ? 0 : 1;
-  return qy * 2 + qx;
-}
-
-function logicalBitsFromField(
-  field: Uint8Array,
-  g: number,
-  lS: number,
-  mask?: Uint8Array
-): number[] {
-  const sums = [0, 0, 0, 0];
-  const counts = [0, 0, 0, 0];
-  for (let i = 0; i < field.length; i += 1) {
-    if (mask && mask[i] === 0) continue;
-    const q = quadrantIndex(i, g);
-    sums[q] += field[i] ?? 0;
-    counts[q] += 1;
-  }
-  const threshold = lS / 2;
-  return sums.map((sum, i) => {
-    const meanVal = counts[i] > 0 ? sum / counts[i] : 0;
-    return meanVal >= threshold ? 1 : 0;
-  });
-}
-
-function errorRate(bitsA: number[], bitsB: number[]): number {
-  let mismatches = 0;
-  const len = Math.min(bitsA.length, bitsB.length);
-  if (len === 0) return 0;
-  for (let i = 0; i < len; i += 1) {
-    if (bitsA[i] !== bitsB[i]) mismatches += 1;
-  }
-  return mismatches / len;
-}
-
-function makeRng(seed: number) {
-  let x = seed >>> 0;
-  if (x === 0) x = 1;
-  return () => {
-    x ^= x << 13;
-    x ^= x >>> 17;
-    x ^= x << 5;
-    return (x >>> 8) / (1 << 24);
-  };
-}
-
-function computeErrF05(
-  baseS: Uint8Array,
-  meta0: Uint8Array,
-  grid: number,
-  lS: number,
-  trials: number,
-  frac: number,
-  seed: number
-): number {
-  if (baseS.length === 0 || meta0.length === 0 || grid <= 0 || trials <= 0) return 0;
-  const baseBits = logicalBitsFromField(baseS, grid, lS);
-  const rng = makeRng(seed);
-  let acc = 0;
-  const mask = new Uint8Array(meta0.length);
-  for (let t = 0; t < trials; t += 1) {
-    for (let i = 0; i < meta0.length; i += 1) {
-      mask[i] = rng() < frac ? 1 : 0;
-    }
-    const bits = logicalBitsFromField(meta0, grid, lS, mask);
-    acc += errorRate(bits, baseBits);
-  }
-  return acc / trials;
-}
-
 function drawSparkline(
   canvas: HTMLCanvasElement,
   series: number[],
@@ -1430,7 +1354,6 @@ export default function App() {
     baselineErr: null as number | null,
     baselineSdiff: null as number | null,
     recoverySteps: null as number | null,
-    seed: 1234567,
     injuryPending: false,
     damageAtInjury: null as number | null,
     damagePeakSinceInjury: null as number | null,
@@ -2263,16 +2186,8 @@ export default function App() {
         let badCellIdxArr: Uint32Array<ArrayBufferLike> = new Uint32Array(0);
         if (paramsApplied.metaLayers >= 1 && cells > 0 && s.metaField.length >= cells) {
           const meta0 = s.metaField.subarray(0, cells);
-          sdiffBase = meanAbsDiff(s.baseSField, meta0, cells);
-          errF0_5 = computeErrF05(
-            s.baseSField,
-            meta0,
-            grid,
-            paramsApplied.lS,
-            MAINT_TRIALS,
-            MAINT_ERR_FRAC,
-            maint.seed++
-          );
+          sdiffBase = s.extras.maintenance?.sdiffBase ?? meanAbsDiff(s.baseSField, meta0, cells);
+          errF0_5 = s.extras.maintenance?.errF05 ?? null;
           const mask = computeBadCellMask(s.baseSField, meta0, cells, MAINT_BAD_CELL_TAU);
           badCellCount = mask.count;
           badCellIdxArr = mask.indices;
@@ -2858,7 +2773,6 @@ export default function App() {
         baselineErr: null,
         baselineSdiff: null,
         recoverySteps: null,
-        seed: 1234567,
         injuryPending: false,
         damageAtInjury: null,
         damagePeakSinceInjury: null,
@@ -1,4 +1,4 @@
-import type { SimMessage, SimRequest, SimSnapshot } from "./workerMessages";
+import type { MaintenanceSnapshotExtras, SimMessage, SimRequest, SimSnapshot } from "./workerMessages";
 import { SNAPSHOT_VERSION } from "./workerMessages";
 
 let wasmMod: any | null = null;
@@ -14,8 +14,14 @@ let totalSteps = 0;
 let lastOpkPayloadSteps = -20000;
 let cachedOpkTokens: Uint8Array | null = null;
 let cachedOpkOffsets: Int8Array | null = null;
+let lastMaintPayloadSteps = -10000;
+let cachedMaint: MaintenanceSnapshotExtras = {};
+let maintSeed = 1;
 
 const OPK_PAYLOAD_EVERY_STEPS = 20000;
+const MAINT_PAYLOAD_EVERY_STEPS = 10000;
+const MAINT_TRIALS = 8;
+const MAINT_ERR_FRAC = 0.5;
 
 function post(message: SimMessage) {
   postMessage(message);
@@ -27,6 +33,26 @@ function debug(message: string) {
 
 debug("Worker bootstrap (WASM).");
 
+// Code-maintenance metrics of meta layer 0 against base S, read from the core's code_metrics.
+// Refreshed every MAINT_PAYLOAD_EVERY_STEPS and on every out-of-band snapshot (steps = 0).
+function buildMaintenance(simRef: any, steps: number): MaintenanceSnapshotExtras {
+  if (steps > 0 && totalSteps - lastMaintPayloadSteps < MAINT_PAYLOAD_EVERY_STEPS) {
+    return cachedMaint;
+  }
+  lastMaintPayloadSteps = totalSteps;
+  if (Number(simRef.meta_layers()) < 1) {
+    cachedMaint = {};
+    return cachedMaint;
+  }
+  const metrics = simRef.code_metrics({ frac: MAINT_ERR_FRAC, trials: MAINT_TRIALS, seed: maintSeed++ });
+  cachedMaint = {
+    errF05: metrics.reconErr[0] ?? 0,
+    sdiffBase: metrics.sdiff[0] ?? 0,
+    computedAtSteps: totalSteps,
+  };
+  return cachedMaint;
+}
+
 function buildSnapshot(
   simRef: any,
   bondLimit: number,
@@ -110,6 +136,7 @@ function buildSnapshot(
         tokens: opkTokens,
         computedAtSteps: opkComputedAt,
       },
+      maintenance: buildMaintenance(simRef, steps),
     },
   };
 }
@@ -148,6 +175,7 @@ self.onmessage = async (ev: MessageEvent<SimRequest>) => {
       cachedBonds = null;
       lastBondsSteps = -Math.max(1, bondsEverySteps);
       lastOpkPayloadSteps = -OPK_PAYLOAD_EVERY_STEPS;
+      lastMaintPayloadSteps = -MAINT_PAYLOAD_EVERY_STEPS;
       cachedOpkTokens = null;
       cachedOpkOffsets = null;
       sim = new mod.Sim(req.n, req.seed);
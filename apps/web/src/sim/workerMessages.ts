@@ -132,7 +132,11 @@ export type OpkSnapshotExtras = {
   computedAtSteps?: number;
 };
 
-export type MaintenanceSnapshotExtras = {};
+export type MaintenanceSnapshotExtras = {
+  errF05?: number;
+  sdiffBase?: number;
+  computedAtSteps?: number;
+};
 
 export type SnapshotExtras = {
   ep?: EpSnapshotExtras;
@@ -0,0 +1,204 @@
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+use crate::kmc::MoveEval;
+use crate::Sim;
+
+// Acceptance rules per move kind (`acceptRule`: one number for every kind, or an array by
+// kind). With x = -beta (ΔE - W) + ln(q_rev / q_fwd):
+//   0 Metropolis:  a = min(1, e^x)
+//   1 Barker (Glauber): a = 1 / (1 + e^-x)
+//   2 heat-bath: the base counters (P4), apparatus (P2) and S cells (P5) are resampled from
+//     their full conditional over every reachable value, weighted by exp(-beta (ΔE - W)) with
+//     ΔE and W summed along the unit steps from the current value. Other kinds, including
+//     the clock (a driven ring has no potential to resample from), use Barker.
+// Each rule satisfies a(x) / a(-x) = e^x, so the exact EP of an accepted move stays x.
+// Under finite reservoirs each unit of a heat-bath jump sees the stock left by the units
+// before it: the effective mu follows the stock and an upward jump stops where it runs out.
+pub(crate) const ACCEPT_METROPOLIS: u8 = 0;
+pub(crate) const ACCEPT_BARKER: u8 = 1;
+pub(crate) const ACCEPT_HEAT_BATH: u8 = 2;
+
+#[wasm_bindgen]
+impl Sim {
+    /// Acceptance rule per move kind (0 Metropolis, 1 Barker, 2 heat-bath).
+    pub fn accept_rules(&self) -> Uint8Array {
+        Uint8Array::from(&self.params.accept_rule[..])
+    }
+}
+
+impl Sim {
+    pub(crate) fn heat_bath_on(&self, move_kind: usize) -> bool {
+        self.params.accept_rule.get(move_kind) == Some(&ACCEPT_HEAT_BATH)
+    }
+
+    // Acceptance probability of a move of `move_kind` with log ratio x.
+    pub(crate) fn acceptance(&self, move_kind: usize, x: f64) -> f64 {
+        match self.params.accept_rule.get(move_kind).copied().unwrap_or(ACCEPT_METROPOLIS) {
+            ACCEPT_BARKER | ACCEPT_HEAT_BATH => 1.0 / (1.0 + (-x).exp()),
+            _ => x.min(0.0).exp(),
+        }
+    }
+
+    // Heat-bath resampling of one discrete variable currently at `current`. `set` writes a
+    // trial value, `eval(up)` evaluates a unit step from the written value (None past its
+    // range or stock), and `commit(up, high, ep_before)` applies one unit step. The reservoir
+    // stocks follow the trial steps and are restored afterwards. Returns the net change.
+    pub(crate) fn heat_bath_resample(
+        &mut self,
+        move_kind: usize,
+        current: i32,
+        set: impl Fn(&mut Sim, i32),
+        eval: impl Fn(&Sim, bool) -> Option<MoveEval>,
+        commit: impl Fn(&mut Sim, bool, bool, f64),
+    ) -> i32 {
+        // (value, ΔE, W, high reservoir) relative to the current value.
+        let mut states = vec![(current, 0.0f32, 0.0f32, false)];
+        let stock = (self.reservoir_stock, self.reservoir_drawn, self.reservoir_spilled);
+        for up in [true, false] {
+            let (mut v, mut d_e, mut work) = (current, 0.0, 0.0);
+            set(self, v);
+            (self.reservoir_stock, self.reservoir_drawn, self.reservoir_spilled) = stock;
+            while let Some(step) = eval(self, up) {
+                d_e += step.d_e;
+                work += step.work;
+                v += if up { 1 } else { -1 };
+                states.push((v, d_e, work, step.high));
+                set(self, v);
+                if self.params.p6_on {
+                    self.draw_resource(step.high, up);
+                }
+            }
+        }
+        set(self, current);
+        (self.reservoir_stock, self.reservoir_drawn, self.reservoir_spilled) = stock;
+        let log_w: Vec<f64> = states.iter().map(|s| -(self.params.beta * (s.1 - s.2)) as f64).collect();
+        let max = log_w.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = log_w.iter().map(|l| (l - max).exp()).collect();
+        let mut pick = self.rand01() as f64 * weights.iter().sum::<f64>();
+        let chosen = weights
+            .iter()
+            .position(|w| {
+                pick -= w;
+                pick < 0.0
+            })
+            .unwrap_or(states.len() - 1);
+        let (target, d_e, work, high) = states[chosen];
+        if target == current {
+            return 0;
+        }
+        let mut ep_before = self.ep_exact_total;
+        self.book_accepted(d_e, work, 0.0, move_kind);
+        let up = target > current;
+        for _ in 0..(target - current).abs() {
+            commit(self, up, high, ep_before);
+            // The accept log carries the whole move's EP on its first unit.
+            ep_before = self.ep_exact_total;
+        }
+        target - current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MOVE_KIND_COUNT, MOVE_P4_BASE, MOVE_P5_BASE};
+
+    fn counter_sim(rule: u8, seed: u32) -> Sim {
+        let mut sim = Sim::new(1, seed);
+        sim.params.p_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_s_write = 0.0;
+        sim.params.p_n_write = 1.0;
+        sim.params.l_n = 3;
+        sim.params.lambda_n = 0.5;
+        sim.params.accept_rule = [rule; MOVE_KIND_COUNT];
+        sim
+    }
+
+    #[test]
+    fn test_rules_sample_boltzmann() {
+        let weights: Vec<(f64, f64)> = (-3..=3).map(|n| ((n * n) as f64, (-0.25 * (n * n) as f64).exp())).collect();
+        let z: f64 = weights.iter().map(|w| w.1).sum();
+        let exact: f64 = weights.iter().map(|w| w.0 * w.1).sum::<f64>() / z;
+        for rule in [ACCEPT_METROPOLIS, ACCEPT_BARKER, ACCEPT_HEAT_BATH] {
+            let mut sim = counter_sim(rule, 7 + rule as u32);
+            let e0 = sim.energy_breakdown_inner().6 as f64;
+            let samples = 40_000;
+            let mut sum = 0.0;
+            for _ in 0..samples {
+                sim.step(1);
+                sum += (sim.n_counter[0] as f64).powi(2);
+            }
+            let observed = sum / samples as f64;
+            assert!((observed - exact).abs() < 0.12, "rule {rule}: <n^2> {observed} vs {exact}");
+            // Undriven: the exact EP is -beta times the energy change, whatever the rule.
+            let d_e = sim.energy_breakdown_inner().6 as f64 - e0;
+            assert!((sim.ep_exact_total + sim.params.beta as f64 * d_e).abs() < 1e-3);
+        }
+        let sim = counter_sim(ACCEPT_BARKER, 1);
+        assert!((sim.acceptance(MOVE_P4_BASE, 0.0) - 0.5).abs() < 1e-12);
+        assert!((sim.acceptance(MOVE_P4_BASE, 2.0) / sim.acceptance(MOVE_P4_BASE, -2.0) - 2f64.exp()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_heat_bath_books_driven_work() {
+        let mut sim = Sim::new(0, 17);
+        let g = 4usize;
+        sim.params.grid_size = g as u16;
+        sim.s_field = vec![0u8; g * g];
+        sim.params.p_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_n_write = 0.0;
+        sim.params.p_s_write = 1.0;
+        sim.params.p6_on = true;
+        sim.params.mu_high = 1.5;
+        sim.params.mu_low = -0.5;
+        sim.params.accept_rule[MOVE_P5_BASE] = ACCEPT_HEAT_BATH;
+        sim.ledger_reset();
+        sim.step(3000);
+        // Multi-unit jumps happen and the ledger still closes with EP = -beta * heat.
+        let heat = sim.ledger.delta_e[MOVE_P5_BASE] - sim.ledger.work[MOVE_P5_BASE];
+        let ep = sim.ep_exact_by_move[MOVE_P5_BASE];
+        assert!((ep + sim.params.beta as f64 * heat).abs() < 1e-3 * (1.0 + heat.abs()));
+        assert!(sim.ledger_residual().abs() < 1e-3);
+        assert_eq!(sim.sum_s, sim.s_field.iter().map(|s| *s as i32).sum::<i32>());
+        assert!(sim.s_field.iter().any(|s| *s > 1));
+    }
+
+    #[test]
+    fn test_heat_bath_respects_finite_stock() {
+        let mut sim = Sim::new(0, 23);
+        let g = 4usize;
+        sim.params.grid_size = g as u16;
+        sim.s_field = vec![0u8; g * g];
+        sim.sum_s = 0;
+        sim.params.l_s = 8;
+        sim.params.p_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_n_write = 0.0;
+        sim.params.p_s_write = 1.0;
+        sim.params.p6_on = true;
+        sim.params.mu_high = 4.0;
+        sim.params.mu_low = 4.0;
+        sim.params.reservoir_mode = 1;
+        sim.params.reservoir_capacity = 3.0;
+        sim.params.reservoir_refill = 0.0;
+        sim.reservoir_stock = [3.0, 3.0];
+        sim.params.accept_rule[MOVE_P5_BASE] = ACCEPT_HEAT_BATH;
+        sim.ledger_reset();
+        for _ in 0..500 {
+            sim.step(1);
+            // A multi-unit jump never draws more than the stock holds.
+            assert!(sim.reservoir_stock[0] >= 0.0);
+        }
+        let stock = sim.reservoir_stock[0];
+        assert_eq!(sim.sum_s as f32, 3.0 - stock);
+        assert!((sim.reservoir_drawn - sim.sum_s as f64).abs() < 1e-6);
+        // The booked work follows the depleted mu, so the ledger still closes.
+        let heat = sim.ledger.delta_e[MOVE_P5_BASE] - sim.ledger.work[MOVE_P5_BASE];
+        let ep = sim.ep_exact_by_move[MOVE_P5_BASE];
+        assert!((ep + sim.params.beta as f64 * heat).abs() < 1e-3 * (1.0 + heat.abs()));
+        assert!(sim.ledger_residual().abs() < 1e-3);
+    }
+}
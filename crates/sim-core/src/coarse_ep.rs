@@ -0,0 +1,277 @@
+use js_sys::{Float64Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::motif::motif_token_states;
+use crate::{get_f32, get_u16, get_u32, Sim};
+
+// Smoothed plug-in EP of a coarse trajectory from its ordered transition counts
+// `counts[i * states + j]` (i -> j). Each unordered pair contributes
+// (c_ij - c_ji) ln((c_ij + alpha) / (c_ji + alpha)), the same per-pair term as the
+// scripts' `coarseEPDecompose`, so `total` estimates EP over the whole observation
+// window (twice `coarseEPSmoothed`, which reports the half-sum).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CoarseEp {
+    pub(crate) total: f64,
+    pub(crate) transitions: f64,
+    pub(crate) per_edge: Vec<f64>,
+    pub(crate) per_state: Vec<f64>,
+}
+
+pub(crate) fn coarse_ep(counts: &[f64], states: usize, alpha: f64) -> CoarseEp {
+    let mut out = CoarseEp {
+        per_edge: vec![0.0; states * states],
+        per_state: vec![0.0; states],
+        ..CoarseEp::default()
+    };
+    if counts.len() < states * states {
+        return out;
+    }
+    for i in 0..states {
+        for j in (i + 1)..states {
+            let fwd = counts[i * states + j];
+            let rev = counts[j * states + i];
+            if fwd == 0.0 && rev == 0.0 {
+                continue;
+            }
+            let ln_ratio = ((fwd + alpha) / (rev + alpha)).ln();
+            let pair = (fwd - rev) * ln_ratio;
+            out.total += pair;
+            out.transitions += fwd + rev;
+            out.per_edge[i * states + j] += fwd * ln_ratio;
+            out.per_edge[j * states + i] -= rev * ln_ratio;
+            out.per_state[i] += 0.5 * pair;
+            out.per_state[j] += 0.5 * pair;
+        }
+    }
+    out
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BootstrapOpts {
+    pub(crate) trials: u32,
+    pub(crate) seed: u32,
+    pub(crate) level: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct BootstrapCi {
+    pub(crate) low: f64,
+    pub(crate) high: f64,
+    pub(crate) mean: f64,
+    pub(crate) std: f64,
+}
+
+pub(crate) struct Xorshift(pub(crate) u32);
+
+impl Xorshift {
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    // Open interval (0,1) so it is safe under ln.
+    fn next_open01(&mut self) -> f64 {
+        ((self.next_u32() >> 8) as f64 + 0.5) / ((1u32 << 24) as f64)
+    }
+
+    fn poisson(&mut self, mean: f64) -> f64 {
+        if mean <= 0.0 {
+            return 0.0;
+        }
+        if mean < 30.0 {
+            let limit = (-mean).exp();
+            let mut k = 0.0;
+            let mut p = self.next_open01();
+            while p > limit {
+                k += 1.0;
+                p *= self.next_open01();
+            }
+            return k;
+        }
+        // Normal approximation for large counts.
+        let u1 = self.next_open01();
+        let u2 = self.next_open01();
+        let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+        (mean + mean.sqrt() * z).round().max(0.0)
+    }
+}
+
+// Poisson bootstrap: every ordered edge count is resampled independently as Poisson(c),
+// which keeps the cost independent of the number of observed transitions.
+pub(crate) fn coarse_ep_bootstrap(
+    counts: &[f64],
+    states: usize,
+    alpha: f64,
+    opts: BootstrapOpts,
+) -> BootstrapCi {
+    if opts.trials == 0 || counts.len() < states * states {
+        return BootstrapCi::default();
+    }
+    let mut rng = Xorshift(opts.seed.max(1));
+    let mut sample = vec![0.0; states * states];
+    let mut totals = Vec::with_capacity(opts.trials as usize);
+    for _ in 0..opts.trials {
+        for (s, c) in sample.iter_mut().zip(counts) {
+            *s = rng.poisson(*c);
+        }
+        totals.push(coarse_ep(&sample, states, alpha).total);
+    }
+    totals.sort_by(|a, b| a.total_cmp(b));
+    let n = totals.len() as f64;
+    let mean = totals.iter().sum::<f64>() / n;
+    let var = totals.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / n;
+    let tail = 0.5 * (1.0 - opts.level.clamp(0.0, 1.0));
+    let pick = |q: f64| totals[((q * (n - 1.0)).round() as usize).min(totals.len() - 1)];
+    BootstrapCi {
+        low: pick(tail),
+        high: pick(1.0 - tail),
+        mean,
+        std: var.sqrt(),
+    }
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Coarse EP of an arbitrary labelling from row-major `states x states` transition counts.
+    ///
+    /// Options: `alpha` (pseudocount, default 0.5), `bootstrap` (trials), `seed`, `ci` (level),
+    /// `epBaseline` (subtracted from `ep_exact_total` for windowed counts).
+    pub fn coarse_ep(&self, counts: &[f64], states: u32, opts: JsValue) -> Object {
+        self.coarse_ep_object(counts, states as usize, &opts)
+    }
+
+    /// Coarse EP of a built-in lens: `"motif"` (op-K token motifs of `opts.interface`,
+    /// requires opMotifOn) or `"clock"` (clock moves lumped into forward/backward).
+    pub fn coarse_ep_source(&self, source: String, opts: JsValue) -> Object {
+        match source.as_str() {
+            "motif" => {
+                let states = motif_token_states(self.params.op_motif_mode);
+                let interface = if opts.is_object() {
+                    get_u16(&opts, "interface").unwrap_or(0) as usize
+                } else {
+                    0
+                };
+                let start = interface * states * states;
+                let counts: Vec<f64> = match self.op_motif_trans.get(start..start + states * states) {
+                    Some(c) => c.iter().map(|v| *v as f64).collect(),
+                    None => Vec::new(),
+                };
+                self.coarse_ep_object(&counts, states, &opts)
+            }
+            "clock" => {
+                let counts = [0.0, self.clock_fwd as f64, self.clock_bwd as f64, 0.0];
+                self.coarse_ep_object(&counts, 2, &opts)
+            }
+            _ => self.coarse_ep_object(&[], 0, &opts),
+        }
+    }
+}
+
+impl Sim {
+    fn coarse_ep_object(&self, counts: &[f64], states: usize, opts: &JsValue) -> Object {
+        let mut alpha = 0.5;
+        let mut boot = BootstrapOpts {
+            trials: 0,
+            seed: 1,
+            level: 0.95,
+        };
+        let mut ep_baseline = 0.0;
+        if opts.is_object() {
+            if let Some(v) = get_f32(opts, "alpha") {
+                if v.is_finite() && v > 0.0 {
+                    alpha = v as f64;
+                }
+            }
+            if let Some(v) = get_u32(opts, "bootstrap") {
+                boot.trials = v;
+            }
+            if let Some(v) = get_u32(opts, "seed") {
+                boot.seed = v;
+            }
+            if let Some(v) = get_f32(opts, "ci") {
+                if v.is_finite() {
+                    boot.level = v.clamp(0.0, 1.0) as f64;
+                }
+            }
+            // Read as f64: EP totals outgrow f32 precision on long runs.
+            if let Some(v) = Reflect::get(opts, &JsValue::from_str("epBaseline"))
+                .ok()
+                .and_then(|v| v.as_f64())
+            {
+                ep_baseline = v;
+            }
+        }
+        let est = coarse_ep(counts, states, alpha);
+        let ci = coarse_ep_bootstrap(counts, states, alpha, boot);
+        let ep_exact = self.ep_exact_total - ep_baseline;
+        let captured = if ep_exact > 0.0 { est.total / ep_exact } else { f64::NAN };
+
+        let o = Object::new();
+        let _ = Reflect::set(&o, &JsValue::from_str("states"), &JsValue::from_f64(states as f64));
+        let _ = Reflect::set(&o, &JsValue::from_str("total"), &JsValue::from_f64(est.total));
+        let _ = Reflect::set(&o, &JsValue::from_str("transitions"), &JsValue::from_f64(est.transitions));
+        let per_transition = if est.transitions > 0.0 {
+            est.total / est.transitions
+        } else {
+            0.0
+        };
+        let _ = Reflect::set(&o, &JsValue::from_str("perTransition"), &JsValue::from_f64(per_transition));
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("perEdge"),
+            &Float64Array::from(est.per_edge.as_slice()),
+        );
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("perState"),
+            &Float64Array::from(est.per_state.as_slice()),
+        );
+        if boot.trials > 0 {
+            let _ = Reflect::set(&o, &JsValue::from_str("ciLow"), &JsValue::from_f64(ci.low));
+            let _ = Reflect::set(&o, &JsValue::from_str("ciHigh"), &JsValue::from_f64(ci.high));
+            let _ = Reflect::set(&o, &JsValue::from_str("bootstrapMean"), &JsValue::from_f64(ci.mean));
+            let _ = Reflect::set(&o, &JsValue::from_str("bootstrapStd"), &JsValue::from_f64(ci.std));
+        }
+        let _ = Reflect::set(&o, &JsValue::from_str("epExact"), &JsValue::from_f64(ep_exact));
+        let _ = Reflect::set(&o, &JsValue::from_str("captured"), &JsValue::from_f64(captured));
+        o
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coarse_ep_decomposition_and_bootstrap() {
+        // Three-state cycle driven 0 -> 1 -> 2 -> 0 with some reverse traffic.
+        let states = 3;
+        let mut counts = vec![0.0; 9];
+        for (i, j, c) in [(0, 1, 90.0), (1, 2, 90.0), (2, 0, 90.0), (1, 0, 30.0), (2, 1, 30.0), (0, 2, 30.0)] {
+            counts[i * states + j] = c;
+        }
+        let est = coarse_ep(&counts, states, 0.5);
+        let pair = 60.0 * (90.5f64 / 30.5).ln();
+        assert!((est.total - 3.0 * pair).abs() < 1e-9);
+        assert_eq!(est.transitions, 360.0);
+        assert!((est.per_edge.iter().sum::<f64>() - est.total).abs() < 1e-9);
+        assert!((est.per_state.iter().sum::<f64>() - est.total).abs() < 1e-9);
+
+        // Detailed balance gives zero EP regardless of the smoothing.
+        let balanced = vec![0.0, 5.0, 0.0, 5.0, 0.0, 7.0, 0.0, 7.0, 0.0];
+        assert_eq!(coarse_ep(&balanced, states, 0.5).total, 0.0);
+
+        let opts = BootstrapOpts {
+            trials: 200,
+            seed: 7,
+            level: 0.9,
+        };
+        let ci = coarse_ep_bootstrap(&counts, states, 0.5, opts);
+        assert!(ci.low <= est.total && est.total <= ci.high);
+        assert!(ci.low > 0.0 && ci.std > 0.0);
+    }
+}
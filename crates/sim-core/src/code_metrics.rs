@@ -0,0 +1,342 @@
+use js_sys::{Array, Float64Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::region::{Axis, RegionMask};
+use crate::{get_f32, get_string, get_u16, get_u32, get_u8, Sim};
+
+// Logical-bit encoding of an S field: one bit per region, set when the region mean
+// reaches `threshold * l_s`. The default is the quadrant-majority code used by the
+// maintenance and deadline experiments (four quadrants, threshold l_s/2).
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct LogicalEncoding {
+    pub(crate) regions: Vec<RegionMask>,
+    pub(crate) threshold: f32,
+}
+
+impl Default for LogicalEncoding {
+    fn default() -> Self {
+        LogicalEncoding {
+            regions: (0..4).map(RegionMask::Quadrant).collect(),
+            threshold: 0.5,
+        }
+    }
+}
+
+impl LogicalEncoding {
+    pub(crate) fn from_js(v: &JsValue, default_bins: u8) -> Option<LogicalEncoding> {
+        if !v.is_object() {
+            return None;
+        }
+        let kind = get_string(v, "type").unwrap_or_else(|| "quadrant".to_string());
+        let regions = match kind.as_str() {
+            "quadrant" => (0..4).map(RegionMask::Quadrant).collect(),
+            "stripe" => {
+                let bins = get_u8(v, "bins").unwrap_or(default_bins).max(1);
+                let axis = if get_string(v, "axis").as_deref() == Some("y") {
+                    Axis::Y
+                } else {
+                    Axis::X
+                };
+                (0..bins)
+                    .map(|bin| RegionMask::Stripe {
+                        axis,
+                        bins,
+                        bin,
+                        span: 1,
+                    })
+                    .collect()
+            }
+            "regions" => {
+                let list = Reflect::get(v, &JsValue::from_str("regions")).ok()?;
+                if !Array::is_array(&list) {
+                    return None;
+                }
+                let mut regions = Vec::new();
+                for item in Array::from(&list).iter() {
+                    regions.push(RegionMask::from_js(&item, default_bins)?);
+                }
+                regions
+            }
+            _ => return None,
+        };
+        if regions.is_empty() {
+            return None;
+        }
+        let threshold = get_f32(v, "threshold")
+            .filter(|t| t.is_finite())
+            .unwrap_or(0.5)
+            .clamp(0.0, 1.0);
+        Some(LogicalEncoding { regions, threshold })
+    }
+
+    pub(crate) fn bit_count(&self) -> usize {
+        self.regions.len()
+    }
+}
+
+// Mean of `field` over each encoding region, restricted to cells where `mask` is set.
+pub(crate) fn region_means(field: &[u8], g: usize, enc: &LogicalEncoding, mask: Option<&[u8]>) -> Vec<f64> {
+    let mut sums = vec![0u64; enc.regions.len()];
+    let mut counts = vec![0u64; enc.regions.len()];
+    for (idx, v) in field.iter().enumerate().take(g * g) {
+        if mask.is_some_and(|m| m.get(idx).is_none_or(|b| *b == 0)) {
+            continue;
+        }
+        for (r, region) in enc.regions.iter().enumerate() {
+            if region.contains_cell(idx, g) {
+                sums[r] += *v as u64;
+                counts[r] += 1;
+            }
+        }
+    }
+    sums.iter()
+        .zip(&counts)
+        .map(|(s, c)| if *c > 0 { *s as f64 / *c as f64 } else { 0.0 })
+        .collect()
+}
+
+pub(crate) fn logical_bits(
+    field: &[u8],
+    g: usize,
+    l_s: u8,
+    enc: &LogicalEncoding,
+    mask: Option<&[u8]>,
+) -> Vec<u8> {
+    let threshold = (enc.threshold as f64) * (l_s as f64);
+    region_means(field, g, enc, mask)
+        .iter()
+        .map(|m| (*m >= threshold) as u8)
+        .collect()
+}
+
+pub(crate) fn error_rate(bits_a: &[u8], bits_b: &[u8]) -> f64 {
+    if bits_a.is_empty() {
+        return 0.0;
+    }
+    let mismatches = bits_a.iter().zip(bits_b).filter(|(a, b)| a != b).count();
+    mismatches as f64 / bits_a.len() as f64
+}
+
+// Per-region |mean_a - mean_b| / l_s; the average over regions is the quadrant-mean error.
+pub(crate) fn region_mean_errors(
+    a: &[u8],
+    b: &[u8],
+    g: usize,
+    l_s: u8,
+    enc: &LogicalEncoding,
+    mask: Option<&[u8]>,
+) -> Vec<f64> {
+    let denom = l_s.max(1) as f64;
+    let means_a = region_means(a, g, enc, mask);
+    let means_b = region_means(b, g, enc, mask);
+    means_a
+        .iter()
+        .zip(&means_b)
+        .map(|(x, y)| (x - y).abs() / denom)
+        .collect()
+}
+
+pub(crate) fn mean_abs_diff_region(a: &[u8], b: &[u8], g: usize, region: &RegionMask) -> f64 {
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for idx in 0..(g * g).min(a.len()).min(b.len()) {
+        if region.contains_cell(idx, g) {
+            sum += (a[idx] as i32 - b[idx] as i32).unsigned_abs() as u64;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        0.0
+    } else {
+        sum as f64 / count as f64
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct ReconSampling {
+    pub(crate) frac: f32,
+    pub(crate) trials: u32,
+    pub(crate) seed: u32,
+}
+
+// Reconstructibility: bit error of `field` read through random cell subsets keeping
+// each cell with probability `frac`. Masks follow the scripts' xorshift `makeMask`
+// (seed + 101 * trial). Unrestricted, `field` is compared with the given reference bits
+// (JS `errF05`); restricted to a region, the reference bits are re-read from the
+// reference field through each trial mask (JS `errF05Region`).
+pub(crate) fn reconstruction_error(
+    (reference_bits, reference): (&[u8], &[u8]),
+    field: &[u8],
+    g: usize,
+    l_s: u8,
+    enc: &LogicalEncoding,
+    region: &RegionMask,
+    sampling: ReconSampling,
+) -> f64 {
+    let ReconSampling { frac, trials, seed } = sampling;
+    if trials == 0 {
+        return 0.0;
+    }
+    let cells = g * g;
+    let mut mask = vec![0u8; cells];
+    let mut acc = 0.0;
+    for t in 0..trials {
+        let mut x = seed.wrapping_add(t.wrapping_mul(101));
+        if x == 0 {
+            x = 1;
+        }
+        for (idx, m) in mask.iter_mut().enumerate() {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            let r = ((x >> 8) as f32) / ((1u32 << 24) as f32);
+            *m = (r < frac && region.contains_cell(idx, g)) as u8;
+        }
+        let bits = logical_bits(field, g, l_s, enc, Some(&mask));
+        if *region == RegionMask::All {
+            acc += error_rate(&bits, reference_bits);
+        } else {
+            acc += error_rate(&bits, &logical_bits(reference, g, l_s, enc, Some(&mask)));
+        }
+    }
+    acc / trials as f64
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Code-fidelity metrics of every meta layer against base S (or the layer below).
+    /// Downsampled layers are read on the base grid, each base cell taking its parent's value.
+    ///
+    /// Options: `region` (RegionMask spec), `reference` ("base" | "below"), `frac`,
+    /// `trials` and `seed` for reconstructibility. Per-region arrays are layer-major.
+    pub fn code_metrics(&self, opts: JsValue) -> Object {
+        let g = self.params.grid_size as usize;
+        let l_s = self.params.l_s;
+        let layers = self.params.meta_layers as usize;
+        let enc = &self.code_encoding;
+        let bits = enc.bit_count();
+        let mut region = RegionMask::All;
+        let mut below = false;
+        let mut sampling = ReconSampling {
+            frac: 0.5,
+            trials: 20,
+            seed: 1,
+        };
+        if opts.is_object() {
+            if let Some(r) = RegionMask::from_params(&opts, "region", self.params.clock_k) {
+                region = r;
+            }
+            below = get_string(&opts, "reference").as_deref() == Some("below");
+            if let Some(v) = get_f32(&opts, "frac") {
+                sampling.frac = v.clamp(0.0, 1.0);
+            }
+            if let Some(v) = get_u16(&opts, "trials") {
+                sampling.trials = v as u32;
+            }
+            if let Some(v) = get_u32(&opts, "seed") {
+                sampling.seed = v;
+            }
+        }
+        let mask = region.to_mask(g);
+        let base_bits = logical_bits(self.s_level(0), g, l_s, enc, Some(&mask));
+
+        let mut bit_err = Vec::with_capacity(layers);
+        let mut recon_err = Vec::with_capacity(layers);
+        let mut sdiff = Vec::with_capacity(layers);
+        let mut region_err = Vec::with_capacity(layers * bits);
+        let mut region_mismatch: Vec<u8> = Vec::with_capacity(layers * bits);
+        let mut meta_bits: Vec<u8> = Vec::with_capacity(layers * bits);
+        for level in 1..=layers {
+            let field = &*self.s_level_lifted(level);
+            let reference = &*self.s_level_lifted(if below { level - 1 } else { 0 });
+            let ref_bits = logical_bits(reference, g, l_s, enc, Some(&mask));
+            let layer_bits = logical_bits(field, g, l_s, enc, Some(&mask));
+            bit_err.push(error_rate(&layer_bits, &ref_bits));
+            recon_err.push(reconstruction_error((&ref_bits, reference), field, g, l_s, enc, &region, sampling));
+            sdiff.push(mean_abs_diff_region(reference, field, g, &region));
+            region_err.extend(region_mean_errors(reference, field, g, l_s, enc, Some(&mask)));
+            region_mismatch.extend(layer_bits.iter().zip(&ref_bits).map(|(a, b)| (a != b) as u8));
+            meta_bits.extend_from_slice(&layer_bits);
+        }
+
+        let o = Object::new();
+        let _ = Reflect::set(&o, &JsValue::from_str("bits"), &JsValue::from_f64(bits as f64));
+        let _ = Reflect::set(&o, &JsValue::from_str("baseBits"), &Uint8Array::from(base_bits.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("metaBits"), &Uint8Array::from(meta_bits.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("bitErr"), &Float64Array::from(bit_err.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("reconErr"), &Float64Array::from(recon_err.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("sdiff"), &Float64Array::from(sdiff.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("regionErr"), &Float64Array::from(region_err.as_slice()));
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("regionMismatch"),
+            &Uint8Array::from(region_mismatch.as_slice()),
+        );
+        o
+    }
+
+    /// Logical bits of one S level (0 = base) under the configured encoding.
+    pub fn logical_bits(&self, level: u16) -> Uint8Array {
+        let level = level as usize;
+        if level > self.params.meta_layers as usize {
+            return Uint8Array::new_with_length(0);
+        }
+        let g = self.params.grid_size as usize;
+        let bits = logical_bits(&self.s_level_lifted(level), g, self.params.l_s, &self.code_encoding, None);
+        Uint8Array::from(bits.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quadrant_encoding_and_errors() {
+        let g = 4usize;
+        let enc = LogicalEncoding::default();
+        // Quadrants 0 and 3 high, 1 and 2 low.
+        let mut a = vec![0u8; g * g];
+        for (idx, v) in a.iter_mut().enumerate() {
+            let (x, y) = (idx % g, idx / g);
+            if (x < 2) == (y < 2) {
+                *v = 6;
+            }
+        }
+        assert_eq!(logical_bits(&a, g, 6, &enc, None), vec![1, 0, 0, 1]);
+
+        let mut b = a.clone();
+        b[0] = 0;
+        b[1] = 0;
+        b[4] = 0;
+        // Quadrant 0 mean drops to 1.5 < 3, flipping one of four bits.
+        assert_eq!(error_rate(&logical_bits(&b, g, 6, &enc, None), &[1, 0, 0, 1]), 0.25);
+        let errs = region_mean_errors(&a, &b, g, 6, &enc, None);
+        assert!((errs[0] - 4.5 / 6.0).abs() < 1e-12);
+        assert_eq!(&errs[1..], &[0.0, 0.0, 0.0]);
+        assert!((mean_abs_diff_region(&a, &b, g, &RegionMask::Quadrant(0)) - 4.5).abs() < 1e-12);
+
+        // A field that matches the reference has zero reconstruction error at any frac.
+        let bits = logical_bits(&a, g, 6, &enc, None);
+        let sampling = ReconSampling {
+            frac: 1.0,
+            trials: 5,
+            seed: 9,
+        };
+        let err = reconstruction_error((&bits, &a), &a, g, 6, &enc, &RegionMask::All, sampling);
+        assert_eq!(err, 0.0);
+
+        // Restricted to quadrant 0 (cells 6, 6, 0, 0: subset means straddle the threshold),
+        // both fields are read through the same trial masks, so a field that differs from
+        // the reference only outside the region reconstructs exactly.
+        let mut d = a.clone();
+        d[4] = 0;
+        d[5] = 0;
+        let mut c = d.clone();
+        c[15] = 0;
+        let region = RegionMask::Quadrant(0);
+        let bits = logical_bits(&d, g, 6, &enc, Some(&region.to_mask(g)));
+        let sampling = ReconSampling { frac: 0.5, trials: 20, seed: 9 };
+        assert_eq!(reconstruction_error((&bits, &d), &c, g, 6, &enc, &region, sampling), 0.0);
+    }
+}
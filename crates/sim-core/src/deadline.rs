@@ -0,0 +1,284 @@
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::code_metrics::{error_rate, logical_bits, mean_abs_diff_region, region_mean_errors};
+use crate::region::RegionMask;
+use crate::{get_f32, get_string, get_u16, get_u32, Sim};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DeadlineMetric {
+    // Mean |S_level - S_ref| over the region.
+    Sdiff,
+    // Logical-bit error rate under the configured code encoding.
+    BitErr,
+    // Mean per-region |mean_level - mean_ref| / l_s.
+    RegionErr,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DeadlineStatus {
+    Pending,
+    Success,
+    Failure,
+}
+
+// "Level `level` reproduces level `reference` in `region` to within `threshold`
+// before `deadline` steps have elapsed", checked every `check_every` steps (a kMC event
+// counts as a step).
+#[derive(Clone, Debug)]
+pub(crate) struct DeadlineTask {
+    pub(crate) id: u32,
+    pub(crate) metric: DeadlineMetric,
+    pub(crate) level: usize,
+    pub(crate) reference: usize,
+    pub(crate) region: RegionMask,
+    pub(crate) threshold: f64,
+    pub(crate) deadline: u32,
+    pub(crate) check_every: u32,
+    pub(crate) start_step: u32,
+    // `ep_exact_total` at registration, shifted by `rebase_deadline_ep` when the total resets.
+    pub(crate) ep_start: f64,
+    pub(crate) status: DeadlineStatus,
+    pub(crate) hit_elapsed: Option<u32>,
+    pub(crate) ep_spent: f64,
+    pub(crate) last_value: f64,
+    pub(crate) checks: u32,
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Registers a deadline task starting at the current step and returns its id.
+    ///
+    /// Spec fields: `metric` ("sdiff" | "bitErr" | "regionErr"), `level` (S level, default 1),
+    /// `reference` (S level, default 0), `region`, `threshold`, `deadline`, `checkEvery`.
+    pub fn add_deadline_task(&mut self, spec: JsValue) -> u32 {
+        if !spec.is_object() {
+            return 0;
+        }
+        let metric = match get_string(&spec, "metric").as_deref() {
+            Some("bitErr") => DeadlineMetric::BitErr,
+            Some("regionErr") => DeadlineMetric::RegionErr,
+            _ => DeadlineMetric::Sdiff,
+        };
+        let region = RegionMask::from_params(&spec, "region", self.params.clock_k).unwrap_or(RegionMask::All);
+        let task = self.new_deadline_task(
+            metric,
+            get_u16(&spec, "level").unwrap_or(1) as usize,
+            get_u16(&spec, "reference").unwrap_or(0) as usize,
+            region,
+            get_f32(&spec, "threshold").unwrap_or(0.0) as f64,
+            get_u32(&spec, "deadline").unwrap_or(10_000),
+            get_u32(&spec, "checkEvery").unwrap_or(1),
+        );
+        let id = task.id;
+        self.deadline_tasks.push(task);
+        id
+    }
+
+    /// Outcome of every registered task, in registration order.
+    pub fn deadline_task_outcomes(&self) -> Array {
+        let out = Array::new();
+        for task in &self.deadline_tasks {
+            let o = Object::new();
+            let status = match task.status {
+                DeadlineStatus::Pending => "pending",
+                DeadlineStatus::Success => "success",
+                DeadlineStatus::Failure => "failure",
+            };
+            let _ = Reflect::set(&o, &JsValue::from_str("id"), &JsValue::from_f64(task.id as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("status"), &JsValue::from_str(status));
+            let _ = Reflect::set(&o, &JsValue::from_str("startStep"), &JsValue::from_f64(task.start_step as f64));
+            let hit = match task.hit_elapsed {
+                Some(t) => JsValue::from_f64(t as f64),
+                None => JsValue::NULL,
+            };
+            let _ = Reflect::set(&o, &JsValue::from_str("hitTime"), &hit);
+            let _ = Reflect::set(&o, &JsValue::from_str("epSpent"), &JsValue::from_f64(task.ep_spent));
+            let _ = Reflect::set(&o, &JsValue::from_str("lastValue"), &JsValue::from_f64(task.last_value));
+            let _ = Reflect::set(&o, &JsValue::from_str("checks"), &JsValue::from_f64(task.checks as f64));
+            out.push(&o);
+        }
+        out
+    }
+
+    pub fn deadline_tasks_pending(&self) -> u32 {
+        self.deadline_tasks
+            .iter()
+            .filter(|t| t.status == DeadlineStatus::Pending)
+            .count() as u32
+    }
+
+    pub fn clear_deadline_tasks(&mut self) {
+        self.deadline_tasks.clear();
+    }
+}
+
+impl Sim {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new_deadline_task(
+        &mut self,
+        metric: DeadlineMetric,
+        level: usize,
+        reference: usize,
+        region: RegionMask,
+        threshold: f64,
+        deadline: u32,
+        check_every: u32,
+    ) -> DeadlineTask {
+        self.deadline_next_id = self.deadline_next_id.wrapping_add(1).max(1);
+        DeadlineTask {
+            id: self.deadline_next_id,
+            metric,
+            level,
+            reference,
+            region,
+            threshold,
+            deadline: deadline.max(1),
+            check_every: check_every.max(1),
+            start_step: self.step_count,
+            ep_start: self.ep_exact_total,
+            status: DeadlineStatus::Pending,
+            hit_elapsed: None,
+            ep_spent: 0.0,
+            last_value: f64::NAN,
+            checks: 0,
+        }
+    }
+
+    pub(crate) fn deadline_metric(&self, task: &DeadlineTask) -> Option<f64> {
+        let layers = self.params.meta_layers as usize;
+        if task.level > layers || task.reference > layers {
+            return None;
+        }
+        let g = self.params.grid_size as usize;
+        let l_s = self.params.l_s;
+        let field = &*self.s_level_lifted(task.level);
+        let reference = &*self.s_level_lifted(task.reference);
+        let value = match task.metric {
+            DeadlineMetric::Sdiff => mean_abs_diff_region(reference, field, g, &task.region),
+            DeadlineMetric::BitErr => {
+                let mask = task.region.to_mask(g);
+                let enc = &self.code_encoding;
+                let a = logical_bits(field, g, l_s, enc, Some(&mask));
+                let b = logical_bits(reference, g, l_s, enc, Some(&mask));
+                error_rate(&a, &b)
+            }
+            DeadlineMetric::RegionErr => {
+                let mask = task.region.to_mask(g);
+                let errs = region_mean_errors(reference, field, g, l_s, &self.code_encoding, Some(&mask));
+                if errs.is_empty() {
+                    0.0
+                } else {
+                    errs.iter().sum::<f64>() / errs.len() as f64
+                }
+            }
+        };
+        Some(value)
+    }
+
+    // Called just before `ep_exact_total` is zeroed: shifts the baseline of open tasks so the
+    // EP they have already spent carries over the reset.
+    pub(crate) fn rebase_deadline_ep(&mut self) {
+        let total = self.ep_exact_total;
+        for task in &mut self.deadline_tasks {
+            if task.status == DeadlineStatus::Pending {
+                task.ep_start -= total;
+            }
+        }
+    }
+
+    pub(crate) fn check_deadline_tasks(&mut self) {
+        for i in 0..self.deadline_tasks.len() {
+            let task = &self.deadline_tasks[i];
+            if task.status != DeadlineStatus::Pending {
+                continue;
+            }
+            let elapsed = self.step_count.wrapping_sub(task.start_step);
+            let due = elapsed >= task.deadline;
+            if !due && !elapsed.is_multiple_of(task.check_every) {
+                continue;
+            }
+            let value = self.deadline_metric(task);
+            let ep_spent = self.ep_exact_total - task.ep_start;
+            let task = &mut self.deadline_tasks[i];
+            task.checks += 1;
+            match value {
+                Some(v) => {
+                    task.last_value = v;
+                    if v <= task.threshold {
+                        task.status = DeadlineStatus::Success;
+                        task.hit_elapsed = Some(elapsed);
+                        task.ep_spent = ep_spent;
+                        continue;
+                    }
+                }
+                None => task.last_value = f64::NAN,
+            }
+            if due {
+                task.status = DeadlineStatus::Failure;
+                task.ep_spent = ep_spent;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_tasks_record_hit_and_failure() {
+        let mut sim = Sim::new(0, 9);
+        let g = 4usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 1;
+        sim.params.p_s_write = 0.0;
+        sim.s_field = vec![0u8; g * g];
+        sim.resize_meta_arrays();
+
+        let hit = sim.new_deadline_task(DeadlineMetric::Sdiff, 1, 0, RegionMask::All, 0.0, 10, 1);
+        sim.deadline_tasks.push(hit);
+        sim.s_field = vec![3u8; g * g];
+        let miss = sim.new_deadline_task(DeadlineMetric::BitErr, 1, 0, RegionMask::Quadrant(2), 0.0, 5, 2);
+        sim.deadline_tasks.push(miss);
+        // The first task only sees the mismatch once it is checked after the field changed.
+        sim.s_field = vec![0u8; g * g];
+        sim.step(1);
+        sim.s_field = vec![3u8; g * g];
+        sim.step(9);
+
+        let hit = &sim.deadline_tasks[0];
+        assert_eq!(hit.status, DeadlineStatus::Success);
+        assert_eq!(hit.hit_elapsed, Some(1));
+        assert_eq!(hit.checks, 1);
+
+        // Checked at elapsed 2 and 4, then once more at the deadline.
+        let miss = &sim.deadline_tasks[1];
+        assert_eq!(miss.status, DeadlineStatus::Failure);
+        assert_eq!(miss.hit_elapsed, None);
+        assert_eq!(miss.checks, 3);
+        // Only the masked quadrant's bit can disagree.
+        assert_eq!(miss.last_value, 0.25);
+        assert_eq!(sim.deadline_tasks_pending(), 0);
+    }
+
+    #[test]
+    fn test_deadline_ep_survives_ep_reset() {
+        let mut sim = Sim::new(0, 9);
+        sim.params.grid_size = 4;
+        sim.params.meta_layers = 1;
+        sim.s_field = vec![0u8; 16];
+        sim.resize_meta_arrays();
+        sim.ep_exact_total = 5.0;
+        let task = sim.new_deadline_task(DeadlineMetric::Sdiff, 1, 0, RegionMask::All, -1.0, 2, 1);
+        sim.deadline_tasks.push(task);
+        sim.ep_exact_total = 7.0;
+        sim.rebase_deadline_ep();
+        sim.ep_exact_total = 1.5;
+        sim.step_count += 2;
+        sim.check_deadline_tasks();
+        let task = &sim.deadline_tasks[0];
+        assert_eq!(task.status, DeadlineStatus::Failure);
+        assert!((task.ep_spent - 3.5).abs() < 1e-12);
+    }
+}
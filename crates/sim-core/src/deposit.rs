@@ -0,0 +1,377 @@
+use crate::{edge_index, torus_delta, torus_midpoint, Sim};
+
+// Particle quantities deposited onto the grid of a meta layer, for interfaces whose lower end
+// is the base: counters are binned by particle position, bonds with w > 0 by midpoint and
+// orientation (horizontal plane if |dx| >= |dy|, as laid out in `meta_w_edges`). Each meta
+// slot holding at least one item is pulled toward the mean of its items. A proposal only
+// touches the slots of the moved particle or of its bonds: counter slots are re-summed from
+// the particles, bond slots come from `deposit_w_bins`, kept in step on every commit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DepositChannel {
+    A,
+    N,
+    W,
+}
+
+// One proposed base change, applied on the fly while binning.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum DepositEdit {
+    Move(usize, f32, f32),
+    Bond(usize, usize, u8),
+    A(usize, u16),
+    N(usize, i16),
+}
+
+impl DepositEdit {
+    fn channels(self) -> &'static [DepositChannel] {
+        match self {
+            DepositEdit::Move(..) => &[DepositChannel::A, DepositChannel::N, DepositChannel::W],
+            DepositEdit::Bond(..) => &[DepositChannel::W],
+            DepositEdit::A(..) => &[DepositChannel::A],
+            DepositEdit::N(..) => &[DepositChannel::N],
+        }
+    }
+}
+
+impl Sim {
+    // Cell of `level` containing the point (x, y) of the unit torus.
+    pub(crate) fn level_cell_at(&self, level: usize, x: f32, y: f32) -> usize {
+        let g = self.level_grid(level);
+        let cx = ((x * g as f32) as usize).min(g - 1);
+        let cy = ((y * g as f32) as usize).min(g - 1);
+        cy * g + cx
+    }
+
+    // Rebuilds the cached bond bins of every meta layer (empty while deposit coupling is off).
+    pub(crate) fn recompute_deposit_bins(&mut self) {
+        self.deposit_w_bins = if self.params.deposit_coupling_on {
+            let layers = self.params.meta_layers as usize;
+            (0..layers).map(|layer| self.deposit_bins(DepositChannel::W, layer, None)).collect()
+        } else {
+            Vec::new()
+        };
+    }
+
+    // Updates the cached bond bins for `edit`; call before the edit is written.
+    pub(crate) fn deposit_commit(&mut self, edit: DepositEdit) {
+        if !matches!(edit, DepositEdit::Move(..) | DepositEdit::Bond(..)) {
+            return;
+        }
+        for layer in 0..self.deposit_w_bins.len() {
+            for (slot, sum, count) in self.deposit_changes(DepositChannel::W, layer, edit) {
+                let bin = &mut self.deposit_w_bins[layer][slot];
+                bin.0 += sum;
+                bin.1 = bin.1.wrapping_add_signed(count);
+            }
+        }
+    }
+
+    fn deposit_position(&self, k: usize, edit: Option<DepositEdit>) -> (f32, f32) {
+        match edit {
+            Some(DepositEdit::Move(i, x, y)) if i == k => (x, y),
+            _ => (self.positions[2 * k], self.positions[2 * k + 1]),
+        }
+    }
+
+    fn deposit_denom(&self, channel: DepositChannel) -> f32 {
+        match channel {
+            DepositChannel::A => self.params.l_a.max(1) as f32,
+            DepositChannel::N => self.params.l_n.max(1) as f32,
+            DepositChannel::W => self.params.l_w.max(1) as f32,
+        }
+    }
+
+    fn deposit_meta_value(&self, channel: DepositChannel, layer: usize, slot: usize) -> i32 {
+        match channel {
+            DepositChannel::A => self.meta_a_field[self.meta_offset(layer) + slot] as i32,
+            DepositChannel::N => self.meta_n_field[self.meta_offset(layer) + slot] as i32,
+            DepositChannel::W => self.meta_w_edges[self.meta_edge_offset(layer) + slot] as i32,
+        }
+    }
+
+    // (sum, count) of deposited items per slot of `channel` on the grid of meta `layer`.
+    pub(crate) fn deposit_bins(
+        &self,
+        channel: DepositChannel,
+        layer: usize,
+        edit: Option<DepositEdit>,
+    ) -> Vec<(i32, u32)> {
+        let level = layer + 1;
+        let cells = self.level_cells(level);
+        if channel != DepositChannel::W {
+            let mut bins = vec![(0i32, 0u32); cells];
+            for k in 0..self.n {
+                let (x, y) = self.deposit_position(k, edit);
+                let value = match (channel, edit) {
+                    (DepositChannel::A, Some(DepositEdit::A(i, a))) if i == k => a as i32,
+                    (DepositChannel::A, _) => self.a_counter[k] as i32,
+                    (_, Some(DepositEdit::N(i, n))) if i == k => n as i32,
+                    _ => self.n_counter[k] as i32,
+                };
+                let bin = &mut bins[self.level_cell_at(level, x, y)];
+                bin.0 += value;
+                bin.1 += 1;
+            }
+            return bins;
+        }
+        let mut bins = vec![(0i32, 0u32); 2 * cells];
+        for i in 0..self.n {
+            let (xi, yi) = self.deposit_position(i, edit);
+            for j in (i + 1)..self.n {
+                let idx = edge_index(self.n, i, j);
+                let w = match edit {
+                    Some(DepositEdit::Bond(a, b, w)) if (a, b) == (i, j) => w,
+                    _ => self.w[idx],
+                };
+                if w == 0 {
+                    continue;
+                }
+                let (xj, yj) = self.deposit_position(j, edit);
+                let bin = &mut bins[self.bond_slot(level, (xi, yi), (xj, yj))];
+                bin.0 += w as i32;
+                bin.1 += 1;
+            }
+        }
+        bins
+    }
+
+    // Slot of `meta_w_edges` on `level` receiving a bond between two positions.
+    pub(crate) fn bond_slot(&self, level: usize, (xi, yi): (f32, f32), (xj, yj): (f32, f32)) -> usize {
+        let (dx, dy) = torus_delta(xi, yi, xj, yj);
+        let dir = if dx.abs() >= dy.abs() { 0 } else { 1 };
+        let (mx, my) = torus_midpoint(xi, yi, xj, yj);
+        dir * self.level_cells(level) + self.level_cell_at(level, mx, my)
+    }
+
+    fn counter_value(&self, channel: DepositChannel, k: usize) -> i32 {
+        match channel {
+            DepositChannel::A => self.a_counter[k] as i32,
+            _ => self.n_counter[k] as i32,
+        }
+    }
+
+    // (slot, Δsum, Δcount) of the items `edit` moves in or out of `channel` on meta `layer`.
+    fn deposit_changes(&self, channel: DepositChannel, layer: usize, edit: DepositEdit) -> Vec<(usize, i32, i32)> {
+        let level = layer + 1;
+        let pos = |k: usize| (self.positions[2 * k], self.positions[2 * k + 1]);
+        let mut changes = Vec::new();
+        match (channel, edit) {
+            (DepositChannel::W, DepositEdit::Move(i, x, y)) => {
+                for j in (0..self.n).filter(|j| *j != i) {
+                    let w = self.w[edge_index(self.n, i.min(j), i.max(j))] as i32;
+                    if w > 0 {
+                        changes.push((self.bond_slot(level, pos(i), pos(j)), -w, -1));
+                        changes.push((self.bond_slot(level, (x, y), pos(j)), w, 1));
+                    }
+                }
+            }
+            (DepositChannel::W, DepositEdit::Bond(i, j, w1)) => {
+                let w0 = self.w[edge_index(self.n, i, j)] as i32;
+                let slot = self.bond_slot(level, pos(i), pos(j));
+                let w1 = w1 as i32;
+                changes.push((slot, w1 - w0, (w1 > 0) as i32 - (w0 > 0) as i32));
+            }
+            (DepositChannel::W, _) => {}
+            (_, DepositEdit::Move(i, x, y)) => {
+                let v = self.counter_value(channel, i);
+                changes.push((self.level_cell_at(level, pos(i).0, pos(i).1), -v, -1));
+                changes.push((self.level_cell_at(level, x, y), v, 1));
+            }
+            (DepositChannel::A, DepositEdit::A(i, a)) => {
+                let v = self.counter_value(channel, i);
+                changes.push((self.level_cell_at(level, pos(i).0, pos(i).1), a as i32 - v, 0));
+            }
+            (DepositChannel::N, DepositEdit::N(i, n)) => {
+                let v = self.counter_value(channel, i);
+                changes.push((self.level_cell_at(level, pos(i).0, pos(i).1), n as i32 - v, 0));
+            }
+            _ => {}
+        }
+        changes
+    }
+
+    // Current (sum, count) of `channel`'s `slot` on meta `layer`.
+    fn deposit_bin(&self, channel: DepositChannel, layer: usize, slot: usize) -> (i32, u32) {
+        let level = layer + 1;
+        if channel == DepositChannel::W {
+            return match self.deposit_w_bins.get(layer) {
+                Some(bins) if bins.len() == 2 * self.level_cells(level) => bins[slot],
+                _ => self.deposit_bins(channel, layer, None)[slot],
+            };
+        }
+        let mut bin = (0i32, 0u32);
+        for k in 0..self.n {
+            if self.level_cell_at(level, self.positions[2 * k], self.positions[2 * k + 1]) == slot {
+                bin.0 += self.counter_value(channel, k);
+                bin.1 += 1;
+            }
+        }
+        bin
+    }
+
+    fn deposit_term(&self, channel: DepositChannel, meta: i32, (sum, count): (i32, u32)) -> f32 {
+        if count == 0 {
+            return 0.0;
+        }
+        let denom = self.deposit_denom(channel);
+        let mean = (sum as f32) / (count as f32) / denom;
+        0.5 * ((meta as f32) / denom - mean).powi(2)
+    }
+
+    // Unweighted deposit energy of `channel` between the particles and meta `layer`.
+    pub(crate) fn deposit_energy(&self, channel: DepositChannel, layer: usize) -> f64 {
+        self.deposit_bins(channel, layer, None)
+            .iter()
+            .enumerate()
+            .map(|(slot, bin)| {
+                let meta = self.deposit_meta_value(channel, layer, slot);
+                self.deposit_term(channel, meta, *bin) as f64
+            })
+            .sum()
+    }
+
+    // Weighted ΔE of a base move over every interface from the base to a meta layer.
+    pub(crate) fn delta_e_deposit(&self, edit: DepositEdit) -> f32 {
+        if !self.params.deposit_coupling_on || self.params.meta_layers == 0 {
+            return 0.0;
+        }
+        let mut delta = 0.0;
+        for interface in self.interfaces_at(0) {
+            let layer = self.interface_levels(interface).1 - 1;
+            let eta = self.eta_at(interface);
+            for channel in edit.channels() {
+                // Merge the changes per touched slot, then re-score only those slots.
+                let mut touched: Vec<(usize, i32, i32)> = Vec::new();
+                for (slot, sum, count) in self.deposit_changes(*channel, layer, edit) {
+                    match touched.iter_mut().find(|t| t.0 == slot) {
+                        Some(t) => {
+                            t.1 += sum;
+                            t.2 += count;
+                        }
+                        None => touched.push((slot, sum, count)),
+                    }
+                }
+                for (slot, sum, count) in touched {
+                    if (sum, count) == (0, 0) {
+                        continue;
+                    }
+                    let before = self.deposit_bin(*channel, layer, slot);
+                    let after = (before.0 + sum, before.1.wrapping_add_signed(count));
+                    let meta = self.deposit_meta_value(*channel, layer, slot);
+                    let change = self.deposit_term(*channel, meta, after) - self.deposit_term(*channel, meta, before);
+                    delta += eta * change;
+                }
+            }
+        }
+        delta
+    }
+
+    // Weighted ΔE of meta `layer`'s `slot` moving from v0 to v1, against the particle deposit.
+    pub(crate) fn delta_e_deposit_meta(
+        &self,
+        channel: DepositChannel,
+        (layer, slot): (usize, usize),
+        v0: i32,
+        v1: i32,
+    ) -> f32 {
+        if !self.params.deposit_coupling_on {
+            return 0.0;
+        }
+        let mut from_base = self
+            .interfaces_at(layer + 1)
+            .filter(|i| self.interface_levels(*i).0 == 0)
+            .peekable();
+        if from_base.peek().is_none() {
+            return 0.0;
+        }
+        let bin = self.deposit_bin(channel, layer, slot);
+        let change = self.deposit_term(channel, v1, bin) - self.deposit_term(channel, v0, bin);
+        let mut delta = 0.0;
+        for interface in from_base {
+            delta += self.eta_at(interface) * change;
+        }
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assert_delta, Lcg};
+
+    fn coupling_total(sim: &Sim) -> f64 {
+        sim.coupling_energy_by_interface_inner().iter().sum()
+    }
+
+    #[test]
+    fn test_deposit_deltas_match_coupling_energy() {
+        let mut sim = Sim::new(12, 9);
+        let g = 6usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 2;
+        sim.params.meta_downsample = 2;
+        sim.params.eta = 0.7;
+        sim.params.deposit_coupling_on = true;
+        sim.s_field = vec![0u8; g * g];
+        sim.resize_meta_arrays();
+        let mut rng = Lcg::new(77);
+        let mut next = move |max: u32| rng.next_u32() % (max + 1);
+        for k in 0..sim.n {
+            sim.a_counter[k] = next(sim.params.l_a as u32) as u16;
+            sim.n_counter[k] = next(2 * sim.params.l_n as u32) as i16 - sim.params.l_n;
+        }
+        for w in sim.w.iter_mut() {
+            *w = if next(2) == 0 { next(5) as u8 } else { 0 };
+        }
+        for a in sim.meta_a_field.iter_mut() {
+            *a = next(6) as u16;
+        }
+        for w in sim.meta_w_edges.iter_mut() {
+            *w = next(5) as u8;
+        }
+        sim.recompute_deposit_bins();
+        for _ in 0..40 {
+            let k = next(sim.n as u32 - 1) as usize;
+            let j = (k + 1 + next(sim.n as u32 - 2) as usize) % sim.n;
+            let edits = [
+                DepositEdit::Move(k, next(999) as f32 / 1000.0, next(999) as f32 / 1000.0),
+                DepositEdit::Bond(k.min(j), k.max(j), next(5) as u8),
+                DepositEdit::A(k, next(6) as u16),
+                DepositEdit::N(k, next(12) as i16 - 6),
+            ];
+            for edit in edits {
+                let delta = sim.delta_e_deposit(edit) as f64;
+                let apply = |sim: &mut Sim| {
+                    sim.deposit_commit(edit);
+                    match edit {
+                        DepositEdit::Move(i, x, y) => {
+                            sim.positions[2 * i] = x;
+                            sim.positions[2 * i + 1] = y;
+                        }
+                        DepositEdit::Bond(i, j, w) => sim.w[edge_index(sim.n, i, j)] = w,
+                        DepositEdit::A(i, a) => sim.a_counter[i] = a,
+                        DepositEdit::N(i, n) => sim.n_counter[i] = n,
+                    }
+                };
+                assert_delta(&mut sim, coupling_total, delta, apply, 1e-4);
+            }
+            // The cached bond bins track every committed edit.
+            for layer in 0..2 {
+                assert_eq!(sim.deposit_w_bins[layer], sim.deposit_bins(DepositChannel::W, layer, None));
+            }
+            let layer = next(1) as usize;
+            let cells = sim.level_cells(layer + 1);
+            let q = next(cells as u32 - 1) as usize;
+            let at = sim.meta_offset(layer) + q;
+            let (a0, a1) = (sim.meta_a_field[at], next(6) as u16);
+            let delta = sim.delta_e_meta_a_couple(layer, q, a0, a1) as f64;
+            assert_delta(&mut sim, coupling_total, delta, |sim| sim.meta_a_field[at] = a1, 1e-4);
+            let edge = next(2 * cells as u32 - 1) as usize;
+            let at = sim.meta_edge_offset(layer) + edge;
+            let (w0, w1) = (sim.meta_w_edges[at], next(5) as u8);
+            let delta = sim.delta_e_meta_w_couple(layer, edge, w0, w1) as f64;
+            assert_delta(&mut sim, coupling_total, delta, |sim| sim.meta_w_edges[at] = w1, 1e-4);
+        }
+        assert!(sim.deposit_energy(DepositChannel::W, 0) > 0.0);
+    }
+}
@@ -0,0 +1,322 @@
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::deposit::DepositChannel;
+use crate::{edge_index, torus_dist, Sim, MOVE_GC};
+
+// Grand-canonical exchange with a particle reservoir at chemical potential muParticle (box
+// area V = 1). An insertion places a particle uniformly on the torus with a uniform species
+// and uniform counters (a in 0..=lA, n in -lN..=lN of that species), and draws a uniform w
+// in 0..=lW for each of its m bondable partners (the pairs P1 proposes: within rPropose and
+// allowed by the species table); a deletion picks a particle uniformly and removes it with
+// its bonds. A particle bonded to a partner outside that set cannot be deleted, since no
+// insertion could restore the bond. The MH ratio is exp(-beta (dE -+ mu)) * q_rev / q_fwd, with
+//   insert: q_rev / q_fwd = V * K / (N + 1),  delete: q_rev / q_fwd = N / (V * K),
+// where K = species count * (lA(s) + 1) * (2 lN(s) + 1) * (lW + 1)^m counts the internal and
+// bond states of the particle; dE includes the energy of the inserted or removed bonds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ParticleState {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) a: u16,
+    pub(crate) n: i16,
+    pub(crate) species: u8,
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Accepted grand-canonical moves as `{ inserted, deleted }`.
+    pub fn gc_stats(&self) -> Object {
+        let o = Object::new();
+        let _ = Reflect::set(&o, &JsValue::from_str("inserted"), &JsValue::from_f64(self.gc_inserted as f64));
+        let _ = Reflect::set(&o, &JsValue::from_str("deleted"), &JsValue::from_f64(self.gc_deleted as f64));
+        o
+    }
+}
+
+impl Sim {
+    // Rebuilds the bond array (and the deposited bond bins) for `new_n` particles; `old_of(new
+    // index)` gives the old index, or None for a fresh particle (all its bonds 0).
+    fn remap_bonds(&mut self, new_n: usize, old_of: impl Fn(usize) -> Option<usize>) {
+        let mut w = vec![0u8; new_n.saturating_mul(new_n.saturating_sub(1)) / 2];
+        for i in 0..new_n {
+            for j in (i + 1)..new_n {
+                if let (Some(a), Some(b)) = (old_of(i), old_of(j)) {
+                    w[edge_index(new_n, i, j)] = self.w[edge_index(self.n, a.min(b), a.max(b))];
+                }
+            }
+        }
+        self.w = w;
+        self.n = new_n;
+        self.recompute_deposit_bins();
+    }
+
+    pub(crate) fn insert_particle(&mut self, k: usize, p: ParticleState) {
+        self.positions.splice(2 * k..2 * k, [p.x, p.y]);
+        self.a_counter.insert(k, p.a);
+        self.n_counter.insert(k, p.n);
+        self.species.insert(k, p.species);
+        self.remap_bonds(self.n + 1, |i| match i.cmp(&k) {
+            std::cmp::Ordering::Less => Some(i),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(i - 1),
+        });
+        self.recompute_field_counts();
+    }
+
+    // Removes particle k; its bonds are dropped (sum_w is recomputed).
+    pub(crate) fn remove_particle(&mut self, k: usize) -> ParticleState {
+        let p = ParticleState {
+            x: self.positions[2 * k],
+            y: self.positions[2 * k + 1],
+            a: self.a_counter.remove(k),
+            n: self.n_counter.remove(k),
+            species: self.species.remove(k),
+        };
+        self.positions.drain(2 * k..2 * k + 2);
+        self.remap_bonds(self.n - 1, |i| Some(if i < k { i } else { i + 1 }));
+        self.recompute_sum_w();
+        self.recompute_field_counts();
+        p
+    }
+
+    // Particles that P1 could bond to particle k: within rPropose and allowed by the species.
+    fn gc_bond_partners(&self, k: usize) -> Vec<usize> {
+        let (xk, yk) = (self.positions[2 * k], self.positions[2 * k + 1]);
+        (0..self.n)
+            .filter(|j| *j != k && self.bond_allowed(k, *j))
+            .filter(|j| torus_dist(xk, yk, self.positions[2 * j], self.positions[2 * j + 1]) <= self.params.r_propose)
+            .collect()
+    }
+
+    // Writes the bonds of particle k to (partner, w) pairs and refreshes the bond totals.
+    fn gc_set_bonds(&mut self, k: usize, bonds: &[(usize, u8)]) {
+        for (j, w) in bonds {
+            self.w[edge_index(self.n, k.min(*j), k.max(*j))] = *w;
+        }
+        self.recompute_sum_w();
+        self.recompute_deposit_bins();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn has_bonds(&self, k: usize) -> bool {
+        (0..self.n).any(|j| j != k && self.w[edge_index(self.n, k.min(j), k.max(j))] > 0)
+    }
+
+    // Energy particle k carries: pair and bond terms with every other particle, its counters,
+    // its species energy and its particle-field term (the deposit coupling is handled separately).
+    pub(crate) fn particle_energy(&self, k: usize) -> f32 {
+        let (xk, yk) = (self.positions[2 * k], self.positions[2 * k + 1]);
+        let mut e = self.delta_e_counter(0, self.n_counter[k]) + self.delta_e_apparatus(0, self.a_counter[k]);
+        e += self.particle_field_at(xk, yk) + self.species_energy(self.species[k]);
+        for j in 0..self.n {
+            if j == k {
+                continue;
+            }
+            let r = torus_dist(xk, yk, self.positions[2 * j], self.positions[2 * j + 1]);
+            e += self.pair_energy(k, j, r);
+            let w = self.w[edge_index(self.n, k.min(j), k.max(j))];
+            if w > 0 {
+                e += self.delta_e_write(0, w, (k.min(j), k.max(j)), r);
+            }
+        }
+        e
+    }
+
+    // Weighted deposit energy over every interface from the base to a meta layer.
+    fn deposit_energy_total(&self) -> f32 {
+        if !self.params.deposit_coupling_on || self.params.meta_layers == 0 {
+            return 0.0;
+        }
+        let mut e = 0.0;
+        for interface in self.interfaces_at(0) {
+            let layer = self.interface_levels(interface).1 - 1;
+            let channels = [DepositChannel::A, DepositChannel::N, DepositChannel::W];
+            let sum: f64 = channels.iter().map(|c| self.deposit_energy(*c, layer)).sum();
+            e += self.eta_at(interface) * sum as f32;
+        }
+        e
+    }
+
+    // ln K: internal states of a particle of species s with m bondable partners.
+    fn gc_log_states(&self, species: u8, partners: usize) -> f32 {
+        let l_a = self.l_a_for(species) as f32;
+        let l_n = self.l_n_for(species) as f32;
+        let bonds = partners as f32 * (self.params.l_w as f32 + 1.0).ln();
+        (self.species_count() as f32 * (l_a + 1.0) * (2.0 * l_n + 1.0)).ln() + bonds
+    }
+
+    pub(crate) fn gc_exchange_step(&mut self) {
+        let mu = self.params.mu_particle;
+        if self.rand01() < 0.5 {
+            if self.n >= self.params.n_max as usize {
+                return;
+            }
+            let species = ((self.rand_u32() as usize) % self.species_count()) as u8;
+            let l_a = self.l_a_for(species) as u32;
+            let l_n = self.l_n_for(species) as u32;
+            let p = ParticleState {
+                x: self.rand01(),
+                y: self.rand01(),
+                a: (self.rand_u32() % (l_a + 1)) as u16,
+                n: (self.rand_u32() % (2 * l_n + 1)) as i16 - l_n as i16,
+                species,
+            };
+            let deposit_before = self.deposit_energy_total();
+            let k = self.n;
+            self.insert_particle(k, p);
+            let partners = self.gc_bond_partners(k);
+            let l_w = self.params.l_w as u32;
+            let bonds: Vec<(usize, u8)> = partners.iter().map(|j| (*j, (self.rand_u32() % (l_w + 1)) as u8)).collect();
+            self.gc_set_bonds(k, &bonds);
+            let log_q = self.gc_log_states(species, partners.len()) - (self.n as f32).ln();
+            let d_e = self.particle_energy(k) + self.deposit_energy_total() - deposit_before;
+            if self.accept_move(d_e, mu, log_q, MOVE_GC) {
+                self.gc_inserted = self.gc_inserted.saturating_add(1);
+            } else {
+                self.remove_particle(k);
+            }
+        } else {
+            if self.n == 0 {
+                return;
+            }
+            let k = (self.rand_u32() as usize) % self.n;
+            let partners = self.gc_bond_partners(k);
+            let bond = |j: usize| self.w[edge_index(self.n, k.min(j), k.max(j))];
+            if (0..self.n).any(|j| j != k && bond(j) > 0 && !partners.contains(&j)) {
+                return;
+            }
+            let bonds: Vec<(usize, u8)> = partners.iter().map(|j| (*j, bond(*j))).collect();
+            let log_q = (self.n as f32).ln() - self.gc_log_states(self.species[k], partners.len());
+            let d_e_particle = -self.particle_energy(k);
+            let deposit_before = self.deposit_energy_total();
+            let p = self.remove_particle(k);
+            let d_e = d_e_particle + self.deposit_energy_total() - deposit_before;
+            if self.accept_move(d_e, -mu, log_q, MOVE_GC) {
+                self.gc_deleted = self.gc_deleted.saturating_add(1);
+            } else {
+                self.insert_particle(k, p);
+                self.gc_set_bonds(k, &bonds);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_energy(sim: &Sim) -> f64 {
+        sim.energy_breakdown_inner().6 as f64
+    }
+
+    #[test]
+    fn test_insert_remove_match_energy_and_keep_bonds() {
+        let mut sim = Sim::new(6, 31);
+        sim.params.kappa_field = 0.8;
+        sim.s_field = (0..sim.s_field.len()).map(|i| (i % 5) as u8).collect();
+        for (e, w) in sim.w.iter_mut().enumerate() {
+            *w = (e % 3) as u8;
+        }
+        let bond = |sim: &Sim, i: usize, j: usize| sim.w[edge_index(sim.n, i, j)];
+        let (w01, w45) = (bond(&sim, 0, 1), bond(&sim, 4, 5));
+        let before = total_energy(&sim);
+        let p = ParticleState { x: 0.31, y: 0.77, a: 3, n: -2, species: 0 };
+        sim.insert_particle(2, p);
+        assert_eq!(sim.n, 7);
+        assert!(!sim.has_bonds(2));
+        assert_eq!((bond(&sim, 0, 1), bond(&sim, 5, 6)), (w01, w45));
+        let e_new = sim.particle_energy(2) as f64;
+        assert!((total_energy(&sim) - before - e_new).abs() < 1e-3 * (1.0 + before.abs()));
+
+        let e_old = sim.particle_energy(4) as f64;
+        let before = total_energy(&sim);
+        sim.remove_particle(4);
+        assert_eq!(bond(&sim, 0, 1), w01);
+        assert_eq!(sim.sum_w, sim.w.iter().map(|w| *w as i32).sum::<i32>());
+        assert!((before - total_energy(&sim) - e_old).abs() < 1e-3 * (1.0 + before.abs()));
+        assert_eq!(sim.remove_particle(2), p);
+    }
+
+    #[test]
+    fn test_ideal_gas_particle_number_is_poisson() {
+        // Non-interacting particles with flat counters: N is Poisson with mean K exp(beta mu).
+        let mut sim = Sim::new(0, 11);
+        sim.params.p_write = 0.0;
+        sim.params.p_n_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_s_write = 0.0;
+        sim.params.kappa_rep = 0.0;
+        sim.params.lambda_a = 0.0;
+        sim.params.lambda_n = 0.0;
+        sim.params.l_a = 1;
+        sim.params.l_n = 1;
+        sim.params.r_propose = 0.0;
+        sim.params.gc_on = true;
+        sim.params.gc_frac = 1.0;
+        let mean = 8.0f64;
+        sim.params.mu_particle = (mean / 6.0).ln() as f32;
+        sim.step(5_000);
+        let mut sum = 0.0;
+        let samples = 200_000;
+        for _ in 0..samples {
+            sim.step(1);
+            sum += sim.n as f64;
+        }
+        let observed = sum / samples as f64;
+        assert!((observed - mean).abs() < 0.4, "mean N {observed}");
+        assert!(sim.gc_inserted > 0 && sim.gc_deleted > 0);
+        // Reversible exchange: the exact EP telescopes to ln pi(N_end) - ln pi(0), which stays O(N).
+        assert!(sim.ep_exact_by_move[MOVE_GC].abs() < 50.0);
+    }
+
+    #[test]
+    fn test_bonded_exchange_samples_bond_states() {
+        // Every pair is bondable with flat-shape bonds in 0..=1: pi(N) is proportional to
+        // (6 e^{beta mu})^N / N! * z^(N (N - 1) / 2) with z = 1 + e^{-beta lambdaW / 2}. Without
+        // the (lW + 1)^m bond states in q the mean would be off by far more than the tolerance.
+        let mut sim = Sim::new(0, 13);
+        sim.params.p_write = 0.0;
+        sim.params.p_n_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_s_write = 0.0;
+        sim.params.kappa_rep = 0.0;
+        sim.params.kappa_bond = 0.0;
+        sim.params.lambda_a = 0.0;
+        sim.params.lambda_n = 0.0;
+        sim.params.lambda_w = 6.0;
+        sim.params.l_a = 1;
+        sim.params.l_n = 1;
+        sim.params.l_w = 1;
+        sim.params.r_propose = 1.0;
+        sim.params.gc_on = true;
+        sim.params.gc_frac = 1.0;
+        sim.params.n_max = 24;
+        sim.params.mu_particle = (3.0f64 / 6.0).ln() as f32;
+        let beta = sim.params.beta as f64;
+        let z = 1.0 + (-3.0 * beta).exp();
+        let c = 6.0 * (beta * sim.params.mu_particle as f64).exp();
+        let mut weights = vec![1.0f64];
+        for n in 1..=24usize {
+            let prev = weights[n - 1];
+            weights.push(prev * c / n as f64 * z.powi(n as i32 - 1));
+        }
+        let total: f64 = weights.iter().sum();
+        let exact: f64 = weights.iter().enumerate().map(|(n, w)| n as f64 * w).sum::<f64>() / total;
+
+        sim.ledger_reset();
+        sim.step(5_000);
+        let (mut sum, mut bonded) = (0.0, 0);
+        let samples = 200_000;
+        for _ in 0..samples {
+            sim.step(1);
+            sum += sim.n as f64;
+            bonded += (sim.sum_w > 0) as u32;
+        }
+        let observed = sum / samples as f64;
+        assert!((observed - exact).abs() < 0.3, "mean N {observed} vs {exact}");
+        assert!(bonded > 0 && sim.gc_deleted > 0);
+        assert_eq!(sim.sum_w, sim.w.iter().map(|w| *w as i32).sum::<i32>());
+        assert!(sim.ledger_residual().abs() < 1e-2);
+    }
+}
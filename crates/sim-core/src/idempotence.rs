@@ -0,0 +1,364 @@
+use js_sys::{Array, Float64Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::region::RegionMask;
+use crate::{get_string, get_u16, get_u32, Sim};
+
+// Packaging maps P acting on a normalised lower-layer field x in [0,1] per cell. P(x) lives
+// on the lower layer's grid; a downsampled op-K prediction is read back through `coarse_cell`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum PackagingMap {
+    // Meta-layer projection: block-average over `block` x `block` tiles, then quantise
+    // to the l_s + 1 integer levels the upper layer can hold.
+    Projection { block: u16 },
+    // Linear op-K predictor, P(x)_q = sum_r (k_{q,r} / K) x_{q + o_r} (`op_pred_norm`).
+    OpK,
+    // op-K predictor quantised to l_s + 1 levels, i.e. what an integer meta layer can store.
+    OpKQuantized,
+}
+
+// Defects of one map on one interface restricted to one region (means are over region cells).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct IdempotenceDefect {
+    // mean |P(P(x)) - P(x)|
+    pub(crate) defect_l1: f64,
+    // max |P(P(x)) - P(x)|
+    pub(crate) defect_max: f64,
+    // mean |P(x) - x|: distance of the current lower field from a fixed point.
+    pub(crate) fixed_gap: f64,
+    // mean |upper - P(x)|: how well the upper layer packages the lower one.
+    pub(crate) packaging_err: f64,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct IdempotenceSeries {
+    pub(crate) map: PackagingMap,
+    pub(crate) region: RegionMask,
+    pub(crate) every: u32,
+    pub(crate) start_step: u32,
+    pub(crate) columns: usize,
+    pub(crate) rows: Vec<f64>,
+}
+
+// Rows retained by the time series before the oldest half is dropped.
+const IDEMPOTENCE_SERIES_MAX_ROWS: usize = 100_000;
+
+fn quantize(v: f32, l_s: u8) -> f32 {
+    let levels = l_s.max(1) as f32;
+    (v.clamp(0.0, 1.0) * levels).round() / levels
+}
+
+impl PackagingMap {
+    fn from_js(opts: &JsValue, op_available: bool) -> PackagingMap {
+        let block = || get_u16(opts, "block").unwrap_or(1).max(1);
+        match get_string(opts, "map").as_deref() {
+            Some("projection") => PackagingMap::Projection { block: block() },
+            Some("opk") => PackagingMap::OpK,
+            Some("opkQuantized") => PackagingMap::OpKQuantized,
+            _ if op_available => PackagingMap::OpK,
+            _ => PackagingMap::Projection { block: block() },
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Idempotence defects of the packaging map on the current state.
+    ///
+    /// Options: `map` ("projection" | "opk" | "opkQuantized"; defaults to "opk" when op-K
+    /// coupling is on), `block` (projection tile size), and `region` or `regions` (array).
+    /// Arrays are interface-major with one entry per region.
+    pub fn idempotence_defect(&self, opts: JsValue) -> Object {
+        let map = PackagingMap::from_js(&opts, !self.op_k.is_empty());
+        let mut regions = Vec::new();
+        if opts.is_object() {
+            let list = Reflect::get(&opts, &JsValue::from_str("regions")).unwrap_or(JsValue::UNDEFINED);
+            if Array::is_array(&list) {
+                for item in Array::from(&list).iter() {
+                    if let Some(r) = RegionMask::from_js(&item, self.params.clock_k) {
+                        regions.push(r);
+                    }
+                }
+            } else if let Some(r) = RegionMask::from_params(&opts, "region", self.params.clock_k) {
+                regions.push(r);
+            }
+        }
+        if regions.is_empty() {
+            regions.push(RegionMask::All);
+        }
+        let interfaces = self.interface_count();
+        let mut defect_l1 = Vec::with_capacity(interfaces * regions.len());
+        let mut defect_max = Vec::with_capacity(interfaces * regions.len());
+        let mut fixed_gap = Vec::with_capacity(interfaces * regions.len());
+        let mut packaging_err = Vec::with_capacity(interfaces * regions.len());
+        for interface in 0..interfaces {
+            for region in &regions {
+                let d = self.idempotence_defect_internal(interface, map, region);
+                defect_l1.push(d.defect_l1);
+                defect_max.push(d.defect_max);
+                fixed_gap.push(d.fixed_gap);
+                packaging_err.push(d.packaging_err);
+            }
+        }
+        let o = Object::new();
+        let _ = Reflect::set(&o, &JsValue::from_str("regions"), &JsValue::from_f64(regions.len() as f64));
+        let _ = Reflect::set(&o, &JsValue::from_str("defectL1"), &Float64Array::from(defect_l1.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("defectMax"), &Float64Array::from(defect_max.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("fixedGap"), &Float64Array::from(fixed_gap.as_slice()));
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("packagingErr"),
+            &Float64Array::from(packaging_err.as_slice()),
+        );
+        o
+    }
+
+    /// Starts recording `[step, defectL1_0, packagingErr_0, defectL1_1, ...]` rows every
+    /// `every` steps (same options as `idempotence_defect`, single `region`).
+    pub fn start_idempotence_series(&mut self, opts: JsValue) {
+        let map = PackagingMap::from_js(&opts, !self.op_k.is_empty());
+        let mut region = RegionMask::All;
+        let mut every = 100;
+        if opts.is_object() {
+            if let Some(r) = RegionMask::from_params(&opts, "region", self.params.clock_k) {
+                region = r;
+            }
+            if let Some(v) = get_u32(&opts, "every") {
+                every = v.max(1);
+            }
+        }
+        self.idempotence_series = Some(IdempotenceSeries {
+            map,
+            region,
+            every,
+            start_step: self.step_count,
+            columns: 1 + 2 * self.interface_count(),
+            rows: Vec::new(),
+        });
+    }
+
+    /// Recorded rows as `{ columns, data }`, with `data` flattened row-major.
+    pub fn idempotence_series(&self) -> Object {
+        let o = Object::new();
+        let (columns, rows): (usize, &[f64]) = match &self.idempotence_series {
+            Some(series) => (series.columns, &series.rows),
+            None => (0, &[]),
+        };
+        let _ = Reflect::set(&o, &JsValue::from_str("columns"), &JsValue::from_f64(columns as f64));
+        let _ = Reflect::set(&o, &JsValue::from_str("data"), &Float64Array::from(rows));
+        o
+    }
+
+    pub fn stop_idempotence_series(&mut self) {
+        self.idempotence_series = None;
+    }
+}
+
+impl Sim {
+    fn lower_norm_field(&self, interface: usize) -> Vec<f32> {
+        let denom = self.params.l_s.max(1) as f32;
+        let lower = self.interface_levels(interface).0;
+        self.s_level(lower).iter().map(|s| *s as f32 / denom).collect()
+    }
+
+    // Applies the op-K predictor of `interface` to a normalised field on the lower grid; the
+    // result is on the upper grid, reading block means of `x` like `op_pred_norm`.
+    pub(crate) fn op_pred_apply(&self, interface: usize, x: &[f32]) -> Vec<f32> {
+        let (lower, upper) = self.interface_levels(interface);
+        let (fine, g) = (self.level_grid(lower), self.level_grid(upper));
+        let cells = g * g;
+        let budget = self.op_budget_at(interface) as f32;
+        if self.op_k.is_empty() || !self.interface_has_op(interface) || budget <= 0.0 {
+            return vec![0.0; cells];
+        }
+        let offsets = self.op_offsets_at(interface);
+        let lower: Vec<f32> = if fine == g {
+            x.to_vec()
+        } else {
+            (0..cells)
+                .map(|q| {
+                    let (xs, ys) = self.level_block(lower, upper, q);
+                    let n = (xs.len() * ys.len()) as f32;
+                    let sum: f32 = ys.flat_map(|y| xs.clone().map(move |xx| x[y * fine + xx])).sum();
+                    sum / n
+                })
+                .collect()
+        };
+        (0..cells)
+            .map(|q| {
+                offsets
+                    .iter()
+                    .enumerate()
+                    .map(|(r_idx, (dx, dy))| {
+                        let weight = self.op_k[self.op_k_index(interface, q, r_idx)] as f32 / budget;
+                        weight * lower[Self::offset_index(q, *dx, *dy, g)]
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    pub(crate) fn packaging_apply(&self, interface: usize, map: PackagingMap, x: &[f32]) -> Vec<f32> {
+        let g = self.level_grid(self.interface_levels(interface).0);
+        let l_s = self.params.l_s;
+        match map {
+            PackagingMap::Projection { block } => {
+                let b = (block as usize).clamp(1, g.max(1));
+                let mut out = vec![0.0; g * g];
+                for by in (0..g).step_by(b) {
+                    for bx in (0..g).step_by(b) {
+                        let ys = by..(by + b).min(g);
+                        let xs = bx..(bx + b).min(g);
+                        let n = (ys.len() * xs.len()) as f32;
+                        let mut sum = 0.0;
+                        for y in ys.clone() {
+                            for xx in xs.clone() {
+                                sum += x[y * g + xx];
+                            }
+                        }
+                        let v = quantize(sum / n, l_s);
+                        for y in ys.clone() {
+                            for xx in xs.clone() {
+                                out[y * g + xx] = v;
+                            }
+                        }
+                    }
+                }
+                out
+            }
+            PackagingMap::OpK => self.op_pred_lifted(interface, x),
+            PackagingMap::OpKQuantized => self
+                .op_pred_lifted(interface, x)
+                .into_iter()
+                .map(|v| quantize(v, l_s))
+                .collect(),
+        }
+    }
+
+    fn op_pred_lifted(&self, interface: usize, x: &[f32]) -> Vec<f32> {
+        let pred = self.op_pred_apply(interface, x);
+        let (lower, upper) = self.interface_levels(interface);
+        if self.level_grid(lower) == self.level_grid(upper) {
+            return pred;
+        }
+        (0..self.level_cells(lower)).map(|c| pred[self.ancestor_cell(c, lower, upper)]).collect()
+    }
+
+    pub(crate) fn idempotence_defect_internal(
+        &self,
+        interface: usize,
+        map: PackagingMap,
+        region: &RegionMask,
+    ) -> IdempotenceDefect {
+        if interface >= self.interface_count() || self.params.grid_size == 0 {
+            return IdempotenceDefect::default();
+        }
+        let (lower, upper_level) = self.interface_levels(interface);
+        let g = self.level_grid(lower);
+        let x = self.lower_norm_field(interface);
+        let px = self.packaging_apply(interface, map, &x);
+        let ppx = self.packaging_apply(interface, map, &px);
+        let denom = self.params.l_s.max(1) as f32;
+        let upper = self.s_level(upper_level);
+        let mut out = IdempotenceDefect::default();
+        let mut count = 0usize;
+        for q in 0..g * g {
+            if !region.contains_cell(q, g) {
+                continue;
+            }
+            let d = (ppx[q] - px[q]).abs() as f64;
+            out.defect_l1 += d;
+            out.defect_max = out.defect_max.max(d);
+            out.fixed_gap += (px[q] - x[q]).abs() as f64;
+            let up = upper[self.ancestor_cell(q, lower, upper_level)];
+            out.packaging_err += (up as f32 / denom - px[q]).abs() as f64;
+            count += 1;
+        }
+        if count > 0 {
+            let n = count as f64;
+            out.defect_l1 /= n;
+            out.fixed_gap /= n;
+            out.packaging_err /= n;
+        }
+        out
+    }
+
+    pub(crate) fn record_idempotence_series(&mut self) {
+        let Some(series) = &self.idempotence_series else {
+            return;
+        };
+        if !self.step_count.wrapping_sub(series.start_step).is_multiple_of(series.every) {
+            return;
+        }
+        let interfaces = self.interface_count();
+        if series.columns != 1 + 2 * interfaces {
+            return;
+        }
+        let mut row = Vec::with_capacity(series.columns);
+        row.push(self.step_count as f64);
+        for interface in 0..interfaces {
+            let d = self.idempotence_defect_internal(interface, series.map, &series.region);
+            row.push(d.defect_l1);
+            row.push(d.packaging_err);
+        }
+        if let Some(series) = &mut self.idempotence_series {
+            if series.rows.len() >= IDEMPOTENCE_SERIES_MAX_ROWS * series.columns {
+                let half = (IDEMPOTENCE_SERIES_MAX_ROWS / 2) * series.columns;
+                series.rows.drain(..half);
+            }
+            series.rows.extend_from_slice(&row);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packaging_maps_and_defects() {
+        let mut sim = Sim::new(1, 4);
+        let g = 4usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 1;
+        sim.params.l_s = 4;
+        sim.params.op_coupling_on = true;
+        let mut lcg = 7u32;
+        sim.s_field = (0..g * g)
+            .map(|_| {
+                lcg = lcg.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                ((lcg >> 24) % 5) as u8
+            })
+            .collect();
+        sim.resize_meta_arrays();
+        sim.init_op_k();
+        // Concentrate every cell's tokens on a single +x shift: P is then a permutation.
+        let r_count = sim.op_r_count_internal();
+        for cell in sim.op_k.chunks_mut(r_count) {
+            cell.iter_mut().for_each(|k| *k = 0);
+            cell[1] = sim.params.op_budget_k;
+        }
+
+        let x = sim.lower_norm_field(0);
+        let px = sim.op_pred_apply(0, &x);
+        for (q, p) in px.iter().enumerate() {
+            assert!((p - sim.op_pred_norm(0, q)).abs() < 1e-6);
+        }
+
+        // Quantised projection is idempotent; a shift is not unless the field is constant.
+        let all = RegionMask::All;
+        let proj = sim.idempotence_defect_internal(0, PackagingMap::Projection { block: 2 }, &all);
+        assert_eq!(proj.defect_l1, 0.0);
+        assert!(proj.fixed_gap > 0.0);
+        let opk = sim.idempotence_defect_internal(0, PackagingMap::OpK, &all);
+        assert!(opk.defect_l1 > 0.0);
+        assert!(opk.defect_max >= opk.defect_l1);
+
+        sim.s_field = vec![2u8; g * g];
+        let flat = sim.idempotence_defect_internal(0, PackagingMap::OpK, &all);
+        assert_eq!(flat.defect_l1, 0.0);
+        assert_eq!(flat.fixed_gap, 0.0);
+        // Meta layer is still zero, so packaging error is the lower value itself.
+        assert!((flat.packaging_err - 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,612 @@
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    edge_index, torus_dist, Sim, StepDiag, MOVE_CLOCK, MOVE_OPK, MOVE_P1_BASE, MOVE_P2_BASE, MOVE_P4_BASE,
+    MOVE_P5_BASE,
+};
+
+// Continuous-time kinetic Monte Carlo (BKL / Gillespie) over the discrete base-level moves:
+// bond writes (P1), counter writes (P4), apparatus writes (P2), base S writes (P5), clock ticks
+// and op-K token hops. Each elementary move m fires at rate
+//   k_m = q_m * a(-beta (ΔE - W))
+// where q_m is the probability that one random-scan `step` proposes m and a is the kind's
+// acceptance rule (heat-bath kinds use Barker here), so one unit of time is one proposal of
+// `step`. Both modes share the same stationary state only for the base-level moves with
+// everything else held fixed: here positions (X), meta-layer fields, GC exchange and
+// reactions are frozen, and P5's share goes to the base field alone where `step` also
+// writes the meta targets. Op-K hops share P5's fraction evenly with the base S field (one
+// share per op-K interface). Every event is rejection-free: the waiting time is exponential
+// in the total rate, the event is picked with probability k_m / sum k. The proposal weights
+// q_m do not change under a move, so the exact EP of an event is -beta (ΔE - W), as in `step`.
+// The rates live in a table built once per `kmc_advance` call; after an event only the moves
+// whose ΔE it can change are re-evaluated (all of them under finite reservoirs, whose mu
+// follows the stock). Each event is one `step_count` tick and runs the per-step hooks (code
+// noise, deadline checks, lenses, the idempotence series); a noise hit rebuilds the table.
+// Windows run while X moves or meta layers are active are counted in `frozenX` /
+// `frozenMeta`, since those kernels are held fixed rather than sampled.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct MoveEval {
+    pub(crate) d_e: f32,
+    pub(crate) work: f32,
+    // Whether a P6-driven move uses the high reservoir.
+    pub(crate) high: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum KmcMove {
+    Bond(usize, usize, bool),
+    Counter(usize, bool),
+    Apparatus(usize, bool),
+    Cell(usize, bool),
+    Clock(bool),
+    OpHop(usize, usize, usize, usize),
+}
+
+fn kmc_kind(m: KmcMove) -> usize {
+    match m {
+        KmcMove::Bond(..) => MOVE_P1_BASE,
+        KmcMove::Counter(..) => MOVE_P4_BASE,
+        KmcMove::Apparatus(..) => MOVE_P2_BASE,
+        KmcMove::Cell(..) => MOVE_P5_BASE,
+        KmcMove::Clock(_) => MOVE_CLOCK,
+        KmcMove::OpHop(..) => MOVE_OPK,
+    }
+}
+
+const KMC_WINDOWS_MAX: usize = 100_000;
+
+// Physical time and events, plus one record per `kmc_advance` window (duration, clock
+// displacement, exact EP) for current fluctuations; the oldest half is dropped once full.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct KmcStats {
+    pub(crate) time: f64,
+    pub(crate) events: u64,
+    windows: Vec<(f64, f64, f64)>,
+    // Windows run with X moves (including GC and reactions) or meta layers frozen.
+    frozen_x: u64,
+    frozen_meta: u64,
+}
+
+// Every proposable move with its proposal weight, evaluation and rate (0 while impossible).
+// Families sit in fixed blocks (up then down for the two-way moves) so a move's entry can be
+// found again after an event.
+struct KmcTable {
+    moves: Vec<(KmcMove, MoveEval, f64)>,
+    weights: Vec<f64>,
+    // First entry of each bond (by edge index), or usize::MAX when it is never proposed.
+    bond_at: Vec<usize>,
+    counter_at: usize,
+    apparatus_at: usize,
+    cell_at: usize,
+    // First entry of each op-K interface's hops, or usize::MAX.
+    op_at: Vec<usize>,
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Runs kMC events until physical time has advanced by `duration` and returns the number
+    /// of events fired. Each call is one window of `kmc_stats()`; X moves and meta layers do
+    /// not run here, and windows that leave them frozen are counted in `frozenX`/`frozenMeta`.
+    pub fn kmc_advance(&mut self, duration: f64) -> u32 {
+        let (frozen_x, frozen_meta) = self.kmc_frozen();
+        self.kmc.frozen_x += frozen_x as u64;
+        self.kmc.frozen_meta += frozen_meta as u64;
+        let end = self.kmc.time + duration.max(0.0);
+        let (q0, ep0, t0) = (self.clock_q, self.ep_exact_total, self.kmc.time);
+        let mut fired = 0u32;
+        let mut table = self.kmc_table();
+        loop {
+            let total: f64 = table.moves.iter().map(|m| m.2).sum();
+            if total <= 0.0 {
+                self.kmc_wait(end - self.kmc.time);
+                break;
+            }
+            let dt = -(1.0 - self.rand01() as f64).ln() / total;
+            if self.kmc.time + dt > end {
+                // Memoryless: the pending event is redrawn at the start of the next window.
+                self.kmc_wait(end - self.kmc.time);
+                break;
+            }
+            self.kmc_wait(dt);
+            let mut pick = self.rand01() as f64 * total;
+            let mut possible = table.moves.iter().filter(|m| m.2 > 0.0);
+            let chosen = possible.find(|m| {
+                pick -= m.2;
+                pick < 0.0
+            });
+            let last = table.moves.iter().rev().find(|m| m.2 > 0.0);
+            if let Some((m, eval, _)) = chosen.or(last).copied() {
+                self.kmc_fire(m, eval);
+                let noise_before = self.code_noise_event_count();
+                self.run_step_hooks();
+                if self.code_noise_event_count() != noise_before {
+                    table = self.kmc_table();
+                } else {
+                    self.kmc_refresh(&mut table, m);
+                }
+                fired += 1;
+            }
+        }
+        let window = (self.kmc.time - t0, (self.clock_q - q0) as f64, self.ep_exact_total - ep0);
+        if self.kmc.windows.len() >= KMC_WINDOWS_MAX {
+            self.kmc.windows.drain(..KMC_WINDOWS_MAX / 2);
+        }
+        self.kmc.windows.push(window);
+        fired
+    }
+
+    pub fn kmc_time(&self) -> f64 {
+        self.kmc.time
+    }
+
+    /// kMC totals (`time`, `events`, `windows`, `frozenX`, `frozenMeta`) and per-unit-time
+    /// currents over the recorded windows: `clockFlux`, `epRate`, the per-window clock
+    /// displacement variance `clockVar` and the TUR ratio `tur` = Var(Q) <Σ> / (2 <Q>^2) (at
+    /// least 1 for equal windows).
+    pub fn kmc_stats(&self) -> Object {
+        let (flux, ep_rate, var, tur) = self.kmc_currents();
+        let o = Object::new();
+        let set = |key: &str, value: f64| {
+            let _ = Reflect::set(&o, &JsValue::from_str(key), &JsValue::from_f64(value));
+        };
+        set("time", self.kmc.time);
+        set("events", self.kmc.events as f64);
+        set("windows", self.kmc.windows.len() as f64);
+        set("frozenX", self.kmc.frozen_x as f64);
+        set("frozenMeta", self.kmc.frozen_meta as f64);
+        set("clockFlux", flux);
+        set("epRate", ep_rate);
+        set("clockVar", var);
+        set("tur", tur);
+        o
+    }
+
+    /// Clears the kMC time, event count and windows.
+    pub fn kmc_reset(&mut self) {
+        self.kmc = KmcStats::default();
+    }
+}
+
+impl Sim {
+    // Whether `step` would run kernels this mode holds fixed: X moves (the share left after
+    // the write fractions, which also carries GC exchange and reactions) and meta layers.
+    fn kmc_frozen(&self) -> (bool, bool) {
+        let p = &self.params;
+        let x_share = 1.0 - (p.p_write + p.p_n_write + p.p_a_write + p.p_s_write);
+        let x = x_share > 1e-6 && (self.n > 0 || p.gc_on);
+        (x, p.meta_layers > 0)
+    }
+
+    fn kmc_entry(&self, m: KmcMove, weight: f64) -> (KmcMove, MoveEval, f64) {
+        let eval = if weight > 0.0 {
+            match m {
+                KmcMove::Bond(i, j, up) => self.p1_write_eval(i, j, up),
+                KmcMove::Counter(k, up) => self.p4_write_eval(k, up),
+                KmcMove::Apparatus(k, up) => self.p2_write_eval(k, up),
+                KmcMove::Cell(idx, up) => self.p5_write_eval(idx, up),
+                KmcMove::Clock(up) => self.clock_eval(up),
+                KmcMove::OpHop(interface, q, r_from, r_to) => self.opk_hop_eval(interface, q, r_from, r_to),
+            }
+        } else {
+            None
+        };
+        match eval {
+            Some(eval) => {
+                let log_a = -(self.params.beta * (eval.d_e - eval.work)) as f64;
+                (m, eval, weight * self.acceptance(kmc_kind(m), log_a))
+            }
+            None => (m, MoveEval::default(), 0.0),
+        }
+    }
+
+    // Evaluates every proposable move.
+    fn kmc_table(&self) -> KmcTable {
+        let mut table = KmcTable {
+            moves: Vec::new(),
+            weights: Vec::new(),
+            bond_at: vec![usize::MAX; self.w.len()],
+            counter_at: usize::MAX,
+            apparatus_at: usize::MAX,
+            cell_at: usize::MAX,
+            op_at: vec![usize::MAX; self.interface_count()],
+        };
+        let push = |table: &mut KmcTable, m: KmcMove, weight: f64| {
+            table.moves.push(self.kmc_entry(m, weight));
+            table.weights.push(weight);
+        };
+        let p = &self.params;
+        if p.p_write > 0.0 {
+            let mut pairs = Vec::new();
+            for i in 0..self.n {
+                for j in (i + 1)..self.n {
+                    let (xi, yi) = (self.positions[2 * i], self.positions[2 * i + 1]);
+                    let r = torus_dist(xi, yi, self.positions[2 * j], self.positions[2 * j + 1]);
+                    if r <= p.r_propose && self.bond_allowed(i, j) {
+                        pairs.push((i, j));
+                    }
+                }
+            }
+            let weight = p.p_write as f64 / (2 * pairs.len().max(1)) as f64;
+            for (i, j) in pairs {
+                table.bond_at[edge_index(self.n, i, j)] = table.moves.len();
+                for up in [true, false] {
+                    push(&mut table, KmcMove::Bond(i, j, up), weight);
+                }
+            }
+        }
+        let clock_frac = if p.clock_on { p.clock_frac as f64 } else { 0.0 };
+        if p.p_n_write > 0.0 && clock_frac > 0.0 {
+            for up in [true, false] {
+                push(&mut table, KmcMove::Clock(up), p.p_n_write as f64 * clock_frac / 2.0);
+            }
+        }
+        if self.n > 0 {
+            let per_particle = 1.0 / (2 * self.n) as f64;
+            let counter_weight = p.p_n_write as f64 * (1.0 - clock_frac) * per_particle;
+            let apparatus_weight = p.p_a_write as f64 * per_particle;
+            table.counter_at = table.moves.len();
+            for k in 0..self.n {
+                for up in [true, false] {
+                    push(&mut table, KmcMove::Counter(k, up), counter_weight);
+                }
+            }
+            table.apparatus_at = table.moves.len();
+            for k in 0..self.n {
+                for up in [true, false] {
+                    push(&mut table, KmcMove::Apparatus(k, up), apparatus_weight);
+                }
+            }
+        }
+        let op_interfaces: Vec<usize> = if p.op_coupling_on && p.meta_layers > 0 && !self.op_k.is_empty() {
+            (0..self.interface_count()).filter(|i| self.interface_has_op(*i)).collect()
+        } else {
+            Vec::new()
+        };
+        let share = p.p_s_write as f64 / (1 + op_interfaces.len()) as f64;
+        let g = p.grid_size as usize;
+        if share > 0.0 && g > 0 {
+            let weight = share / (2 * g * g) as f64;
+            table.cell_at = table.moves.len();
+            for idx in 0..g * g {
+                for up in [true, false] {
+                    push(&mut table, KmcMove::Cell(idx, up), weight);
+                }
+            }
+        }
+        for interface in op_interfaces {
+            let g = self.level_grid(self.interface_levels(interface).1);
+            let r_count = self.op_r_count_at(interface);
+            if share <= 0.0 || g == 0 || r_count < 2 {
+                continue;
+            }
+            let weight = share / (g * g * r_count * (r_count - 1)) as f64;
+            table.op_at[interface] = table.moves.len();
+            for q in 0..g * g {
+                for r_from in 0..r_count {
+                    for r_to in (0..r_count).filter(|r| *r != r_from) {
+                        push(&mut table, KmcMove::OpHop(interface, q, r_from, r_to), weight);
+                    }
+                }
+            }
+        }
+        table
+    }
+
+    // Re-evaluates the entries whose ΔE or work the fired move `m` can have changed.
+    fn kmc_refresh(&self, table: &mut KmcTable, m: KmcMove) {
+        if self.params.p6_on && self.params.reservoir_mode != 0 {
+            *table = self.kmc_table();
+            return;
+        }
+        let mut touched = self.kmc_touched(table, m);
+        touched.sort_unstable();
+        touched.dedup();
+        for at in touched {
+            let (m, weight) = (table.moves[at].0, table.weights[at]);
+            table.moves[at] = self.kmc_entry(m, weight);
+        }
+    }
+
+    // Entries of `table` reading state that move `m` writes. Positions, species and the meta
+    // layers are frozen in kMC, so only the written variable's own moves and the moves coupled
+    // to it (shared deposit slots, S neighbours, blocks and op-K stencils) are affected.
+    fn kmc_touched(&self, table: &KmcTable, m: KmcMove) -> Vec<usize> {
+        let deposit_levels: Vec<usize> = if self.params.deposit_coupling_on && self.params.meta_layers > 0 {
+            self.interfaces_at(0).map(|i| self.interface_levels(i).1).collect()
+        } else {
+            Vec::new()
+        };
+        let pos = |k: usize| (self.positions[2 * k], self.positions[2 * k + 1]);
+        let mut touched = Vec::new();
+        match m {
+            KmcMove::Bond(i, j, _) => {
+                let slots: Vec<usize> = deposit_levels.iter().map(|l| self.bond_slot(*l, pos(i), pos(j))).collect();
+                for a in 0..self.n {
+                    for b in (a + 1)..self.n {
+                        let at = table.bond_at[edge_index(self.n, a, b)];
+                        let shared = (a, b) == (i, j)
+                            || deposit_levels.iter().zip(&slots).any(|(l, s)| self.bond_slot(*l, pos(a), pos(b)) == *s);
+                        if at != usize::MAX && shared {
+                            touched.extend([at, at + 1]);
+                        }
+                    }
+                }
+            }
+            KmcMove::Counter(k, _) | KmcMove::Apparatus(k, _) => {
+                let base = if matches!(m, KmcMove::Counter(..)) { table.counter_at } else { table.apparatus_at };
+                let cell = |k: usize, l: usize| self.level_cell_at(l, pos(k).0, pos(k).1);
+                for other in 0..self.n {
+                    if other == k || deposit_levels.iter().any(|l| cell(other, *l) == cell(k, *l)) {
+                        touched.extend([base + 2 * other, base + 2 * other + 1]);
+                    }
+                }
+            }
+            KmcMove::Cell(idx, _) => {
+                let g = self.params.grid_size as usize;
+                if table.cell_at != usize::MAX {
+                    let mut cells = vec![idx];
+                    if self.params.j_s != 0.0 {
+                        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                            cells.push(Self::offset_index(idx, dx, dy, g));
+                        }
+                    }
+                    // A base cell's coupling reads its block mean, and under op-K the block
+                    // means and tokens of the stencils around its block.
+                    for interface in self.interfaces_at(0) {
+                        let upper = self.interface_levels(interface).1;
+                        let radius = if self.s_edge_uses_op(interface) { 2 } else { 0 };
+                        cells.extend(self.kmc_base_cells_near(upper, self.ancestor_cell(idx, 0, upper), radius));
+                    }
+                    touched.extend(cells.iter().flat_map(|c| [table.cell_at + 2 * c, table.cell_at + 2 * c + 1]));
+                }
+                for interface in self.interfaces_at(0) {
+                    let (lower, upper) = self.interface_levels(interface);
+                    if lower == 0 && table.op_at[interface] != usize::MAX {
+                        let p = self.ancestor_cell(idx, 0, upper);
+                        for q in self.kmc_cells_near(upper, p, 1) {
+                            touched.extend(self.kmc_op_entries(table, interface, q));
+                        }
+                    }
+                }
+            }
+            KmcMove::Clock(_) => {}
+            KmcMove::OpHop(interface, q, _, _) => {
+                touched.extend(self.kmc_op_entries(table, interface, q));
+                let (lower, upper) = self.interface_levels(interface);
+                if lower == 0 && table.cell_at != usize::MAX && self.s_edge_uses_op(interface) {
+                    for c in self.kmc_base_cells_near(upper, q, 1) {
+                        touched.extend([table.cell_at + 2 * c, table.cell_at + 2 * c + 1]);
+                    }
+                }
+            }
+        }
+        touched
+    }
+
+    // Cells of `level` within torus Chebyshev distance `radius` of cell `p`.
+    fn kmc_cells_near(&self, level: usize, p: usize, radius: i32) -> Vec<usize> {
+        let g = self.level_grid(level);
+        let mut cells = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let q = Self::offset_index(p, dx, dy, g);
+                if !cells.contains(&q) {
+                    cells.push(q);
+                }
+            }
+        }
+        cells
+    }
+
+    // Base cells under the cells of `level` near `p`.
+    fn kmc_base_cells_near(&self, level: usize, p: usize, radius: i32) -> Vec<usize> {
+        let g = self.params.grid_size as usize;
+        let mut cells = Vec::new();
+        for q in self.kmc_cells_near(level, p, radius) {
+            let (xs, ys) = self.level_block(0, level, q);
+            cells.extend(ys.flat_map(|y| xs.clone().map(move |x| y * g + x)));
+        }
+        cells
+    }
+
+    // Entries of every hop out of cell `q` of an op-K interface.
+    fn kmc_op_entries(&self, table: &KmcTable, interface: usize, q: usize) -> std::ops::Range<usize> {
+        let at = table.op_at[interface];
+        if at == usize::MAX {
+            return 0..0;
+        }
+        let r_count = self.op_r_count_at(interface);
+        let per_cell = r_count * (r_count - 1);
+        at + q * per_cell..at + (q + 1) * per_cell
+    }
+
+    fn kmc_fire(&mut self, m: KmcMove, eval: MoveEval) {
+        self.step_count = self.step_count.wrapping_add(1);
+        self.kmc.events += 1;
+        let mut step_diag = StepDiag::default();
+        let ep_before = self.ep_exact_total;
+        self.book_accepted(eval.d_e, eval.work, 0.0, kmc_kind(m));
+        match m {
+            KmcMove::Bond(i, j, up) => {
+                self.p1_write_commit(i, j, up, eval.high);
+                *if up { &mut step_diag.w_plus } else { &mut step_diag.w_minus } = 1;
+            }
+            KmcMove::Counter(k, up) => {
+                self.p4_write_commit(k, up, eval.high);
+                *if up { &mut step_diag.n_plus } else { &mut step_diag.n_minus } = 1;
+            }
+            KmcMove::Apparatus(k, up) => {
+                self.p2_write_commit(k, up, eval.high);
+                *if up { &mut step_diag.a_plus } else { &mut step_diag.a_minus } = 1;
+            }
+            KmcMove::Cell(idx, up) => {
+                self.p5_write_commit(idx, up, eval.high, ep_before);
+                *if up { &mut step_diag.s_plus } else { &mut step_diag.s_minus } = 1;
+            }
+            KmcMove::Clock(up) => self.clock_commit(up),
+            KmcMove::OpHop(interface, q, r_from, r_to) => {
+                self.opk_hop_commit(interface, q, (r_from, r_to), ep_before);
+            }
+        }
+        self.diag.push(step_diag);
+    }
+
+    // Advances physical time; reservoirs refill at `reservoirRefill` per unit time.
+    fn kmc_wait(&mut self, dt: f64) {
+        if dt > 0.0 {
+            self.kmc.time += dt;
+            self.refill_reservoirs(dt as f32);
+        }
+    }
+
+    // (clock flux, EP rate, per-window clock variance, TUR ratio) over the recorded windows.
+    fn kmc_currents(&self) -> (f64, f64, f64, f64) {
+        let windows = &self.kmc.windows;
+        let time: f64 = windows.iter().map(|w| w.0).sum();
+        if windows.is_empty() || time <= 0.0 {
+            return (0.0, 0.0, 0.0, 0.0);
+        }
+        let count = windows.len() as f64;
+        let flux = windows.iter().map(|w| w.1).sum::<f64>() / time;
+        let ep_rate = windows.iter().map(|w| w.2).sum::<f64>() / time;
+        let mean_q = windows.iter().map(|w| w.1).sum::<f64>() / count;
+        let var = windows.iter().map(|w| (w.1 - mean_q).powi(2)).sum::<f64>() / (count - 1.0).max(1.0);
+        let mean_ep = windows.iter().map(|w| w.2).sum::<f64>() / count;
+        let tur = if mean_q != 0.0 { var * mean_ep / (2.0 * mean_q * mean_q) } else { 0.0 };
+        (flux, ep_rate, var, tur)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lens::{Lens, LensKind};
+    use crate::region::RegionMask;
+    use crate::test_support::{fill_random_fields, Lcg};
+
+    #[test]
+    fn test_kmc_time_average_matches_boltzmann() {
+        let mut sim = Sim::new(1, 5);
+        sim.params.p_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_s_write = 0.0;
+        sim.params.p_n_write = 1.0;
+        sim.params.l_n = 3;
+        sim.params.lambda_n = 0.5;
+        let samples = 20_000;
+        let mut sum = 0.0;
+        for _ in 0..samples {
+            sim.kmc_advance(1.0);
+            sum += (sim.n_counter[0] as f64).powi(2);
+        }
+        let weights: Vec<(f64, f64)> = (-3..=3).map(|n| ((n * n) as f64, (-0.25 * (n * n) as f64).exp())).collect();
+        let z: f64 = weights.iter().map(|w| w.1).sum();
+        let exact: f64 = weights.iter().map(|w| w.0 * w.1).sum::<f64>() / z;
+        assert!((sum / samples as f64 - exact).abs() < 0.1, "<n^2> {} vs {exact}", sum / samples as f64);
+        assert!((sim.kmc.time - samples as f64).abs() < 1e-6);
+        assert!(sim.kmc.events > 0);
+    }
+
+    #[test]
+    fn test_kmc_clock_flux_and_tur() {
+        // Only the driven clock: forward rate w, backward w exp(-beta mu).
+        let mut sim = Sim::new(0, 13);
+        sim.params.p_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_s_write = 0.0;
+        sim.params.p_n_write = 1.0;
+        sim.params.clock_on = true;
+        sim.params.clock_frac = 0.5;
+        sim.params.p6_on = true;
+        sim.params.mu_high = 1.0;
+        for _ in 0..400 {
+            sim.kmc_advance(20.0);
+        }
+        let w = 0.25;
+        let expected = w * (1.0 - (-1.0f64).exp());
+        let (flux, ep_rate, _, tur) = sim.kmc_currents();
+        assert!((flux - expected).abs() < 0.1 * expected, "flux {flux} vs {expected}");
+        assert!((ep_rate - flux).abs() < 1e-3 * (1.0 + ep_rate.abs()));
+        assert!(tur > 0.9, "TUR ratio {tur}");
+        assert_eq!(sim.clock_fwd - sim.clock_bwd, sim.clock_q as u64);
+    }
+
+    #[test]
+    fn test_kmc_events_run_step_hooks() {
+        let mut sim = Sim::new(4, 17);
+        let g = 4usize;
+        sim.params.grid_size = g as u16;
+        sim.s_field = vec![0u8; g * g];
+        sim.recompute_sum_s();
+        sim.params.p_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_n_write = 0.5;
+        sim.params.p_s_write = 0.5;
+        sim.code_noise_rates = vec![1.0];
+        sim.lenses.push(Lens {
+            id: 1,
+            kind: LensKind::SumS {
+                level: 0,
+                region: RegionMask::All,
+            },
+            every: 1,
+            start_step: sim.step_count,
+            value: sim.sum_s as i64,
+            plus: 0,
+            minus: 0,
+            plus_mass: 0,
+            minus_mass: 0,
+            window: 0,
+        });
+        let fired = sim.kmc_advance(200.0) as u64;
+        assert!(fired > 0);
+        // Noise hits every event and the table is rebuilt after it, so S still tracks sum_s.
+        assert_eq!(sim.code_noise_stats.events[0], fired);
+        assert_eq!(sim.lenses[0].window as u64, fired);
+        assert_eq!(sim.lenses[0].value, sim.s_field.iter().map(|s| *s as i64).sum::<i64>());
+        assert_eq!((sim.kmc.frozen_x, sim.kmc.frozen_meta), (0, 0));
+
+        sim.params.p_s_write = 0.2;
+        sim.kmc_advance(1.0);
+        assert_eq!((sim.kmc.frozen_x, sim.kmc.frozen_meta), (1, 0));
+    }
+
+    #[test]
+    fn test_kmc_table_updates_match_rebuild() {
+        let mut sim = Sim::new(10, 29);
+        let g = 8usize;
+        sim.params.grid_size = g as u16;
+        sim.s_field = vec![0u8; g * g];
+        sim.params.meta_layers = 2;
+        sim.params.meta_downsample = 2;
+        sim.params.op_coupling_on = true;
+        sim.params.s_coupling_mode = 1;
+        sim.params.eta = 0.6;
+        sim.params.deposit_coupling_on = true;
+        sim.params.j_s = 0.2;
+        sim.params.s_lateral_stencil = 1;
+        sim.params.kappa_field = 0.7;
+        sim.params.r_propose = 0.5;
+        sim.params.p_write = 0.3;
+        sim.params.p_a_write = 0.2;
+        sim.params.p_n_write = 0.2;
+        sim.params.p_s_write = 0.3;
+        sim.resize_meta_arrays();
+        sim.init_op_k();
+        fill_random_fields(&mut sim, &mut Lcg::new(5));
+        sim.recompute_deposit_bins();
+        let mut rng = Lcg::new(8);
+        let mut table = sim.kmc_table();
+        for _ in 0..400 {
+            let possible: Vec<_> = table.moves.iter().filter(|m| m.2 > 0.0).copied().collect();
+            let (m, eval, _) = possible[rng.next_usize(possible.len())];
+            sim.kmc_fire(m, eval);
+            sim.kmc_refresh(&mut table, m);
+            let fresh = sim.kmc_table();
+            for (kept, rebuilt) in table.moves.iter().zip(&fresh.moves) {
+                assert!((kept.2 - rebuilt.2).abs() < 1e-9 * (1.0 + rebuilt.2), "{:?} after {m:?}", rebuilt.0);
+            }
+        }
+    }
+}
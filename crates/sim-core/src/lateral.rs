@@ -0,0 +1,105 @@
+use crate::Sim;
+
+// Forward half-stencils: on a torus grid at least 3 wide each unordered neighbour pair is
+// visited once. On narrower grids an offset and its reverse can reach the same neighbour, so
+// that pair is counted once per offset (it touches across both sides of the torus), and on a
+// 1-wide grid a cell pairs with itself, which adds nothing in either mode.
+const LATERAL_SQUARE: [(i32, i32); 2] = [(1, 0), (0, 1)];
+const LATERAL_FULL: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+// In-layer S coupling on every level's own grid: sLateralMode 0 adds jS * ((s_i - s_j) / lS)^2
+// per neighbour pair (normalised like the inter-layer couplings), 1 adds jS per pair with
+// s_i != s_j (Potts). sLateralStencil 0 = 4-neighbour, 1 = 8-neighbour.
+impl Sim {
+    fn lateral_offsets(&self) -> &'static [(i32, i32)] {
+        if self.params.s_lateral_stencil == 1 {
+            &LATERAL_FULL
+        } else {
+            &LATERAL_SQUARE
+        }
+    }
+
+    fn lateral_pair(&self, a: u8, b: u8) -> f32 {
+        if self.params.s_lateral_mode == 1 {
+            if a != b {
+                self.params.j_s
+            } else {
+                0.0
+            }
+        } else {
+            let d = ((a as f32) - (b as f32)) / self.params.l_s.max(1) as f32;
+            self.params.j_s * d * d
+        }
+    }
+
+    pub(crate) fn s_lateral_energy(&self, level: usize) -> f32 {
+        if self.params.j_s == 0.0 {
+            return 0.0;
+        }
+        let g = self.level_grid(level);
+        let field = self.s_level(level);
+        let mut e = 0.0;
+        for (idx, s) in field.iter().enumerate() {
+            for (dx, dy) in self.lateral_offsets() {
+                e += self.lateral_pair(*s, field[Self::offset_index(idx, *dx, *dy, g)]);
+            }
+        }
+        e
+    }
+
+    // ΔE of the pairs touching cell `idx` of `level` when it moves from s0 to s1. A neighbour
+    // that wraps onto the cell itself (1-wide grids) contributes nothing either way.
+    pub(crate) fn delta_e_s_lateral(&self, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
+        if self.params.j_s == 0.0 {
+            return 0.0;
+        }
+        let g = self.level_grid(level);
+        let field = self.s_level(level);
+        let mut delta = 0.0;
+        for (dx, dy) in self.lateral_offsets() {
+            for (sx, sy) in [(*dx, *dy), (-*dx, -*dy)] {
+                let nb = Self::offset_index(idx, sx, sy, g);
+                if nb != idx {
+                    delta += self.lateral_pair(s1, field[nb]) - self.lateral_pair(s0, field[nb]);
+                }
+            }
+        }
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assert_delta, Lcg};
+
+    #[test]
+    fn test_lateral_deltas_match_energy_on_all_levels() {
+        for (mode, stencil, g) in [(0u8, 0u8, 5usize), (1, 1, 4), (0, 1, 2), (1, 0, 3)] {
+            let mut sim = Sim::new(1, 13);
+            sim.params.grid_size = g as u16;
+            sim.params.meta_layers = 2;
+            sim.params.meta_downsample = 2;
+            sim.params.j_s = 0.3;
+            sim.params.s_lateral_mode = mode;
+            sim.params.s_lateral_stencil = stencil;
+            sim.s_field = vec![0u8; g * g];
+            sim.resize_meta_arrays();
+            let mut rng = Lcg::new(97);
+            let l_s = sim.params.l_s;
+            for level in 0..3 {
+                for s in sim.s_level_mut(level) {
+                    *s = rng.next_u8_range(l_s);
+                }
+            }
+            for _ in 0..100 {
+                let level = rng.next_usize(3);
+                let idx = rng.next_usize(sim.level_cells(level));
+                let (s0, s1) = (sim.s_level(level)[idx], rng.next_u8_range(l_s));
+                let delta = sim.delta_e_s_lateral(level, idx, s0, s1) as f64;
+                let energy = |sim: &Sim| sim.s_lateral_energy(level) as f64;
+                assert_delta(&mut sim, energy, delta, |sim| sim.s_level_mut(level)[idx] = s1, 1e-3);
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use js_sys::{Array, Float64Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::deposit::DepositChannel;
+use crate::{get_f32, get_u16, Sim};
+
+// One coupling edge ("interface") between two S levels (0 = base, 1.. = meta layers) with
+// lower < upper. With no graph declared the interfaces are the chain i -> i + 1. `eta` and
+// `eta_drive` override the per-interface vectors and scalars; `op` gives the edge an op-K
+// interface, used for its S coupling when sCouplingMode = 1 (plain mismatch otherwise).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LayerEdge {
+    pub(crate) lower: usize,
+    pub(crate) upper: usize,
+    pub(crate) eta: Option<f32>,
+    pub(crate) eta_drive: Option<f32>,
+    pub(crate) op: bool,
+}
+
+impl LayerEdge {
+    fn from_js(v: &JsValue) -> Option<LayerEdge> {
+        if !v.is_object() {
+            return None;
+        }
+        let lower = get_u16(v, "lower")? as usize;
+        let upper = get_u16(v, "upper")? as usize;
+        if lower >= upper {
+            return None;
+        }
+        let coupling = |key: &str| get_f32(v, key).filter(|x| x.is_finite()).map(|x| x.clamp(0.0, 1.0));
+        Some(LayerEdge {
+            lower,
+            upper,
+            eta: coupling("eta"),
+            eta_drive: coupling("etaDrive"),
+            op: get_f32(v, "opK").is_none_or(|x| x >= 0.5),
+        })
+    }
+
+    // `layerGraph`: an array of `{ lower, upper, eta?, etaDrive?, opK? }`; an empty array
+    // (or anything else) restores the chain.
+    pub(crate) fn parse_graph(v: &JsValue) -> Vec<LayerEdge> {
+        if !Array::is_array(v) {
+            return Vec::new();
+        }
+        Array::from(v).iter().filter_map(|item| LayerEdge::from_js(&item)).collect()
+    }
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Effective coupling interfaces: `lower`, `upper`, `eta`, `etaDrive`, `opK`, and the
+    /// interface's op-K cell offset (`opOffset`) and cell count (`opCells`, 0 without op-K).
+    pub fn layer_graph(&self) -> Array {
+        let out = Array::new();
+        for interface in 0..self.interface_count() {
+            let (lower, upper) = self.interface_levels(interface);
+            let o = Object::new();
+            let set = |key: &str, value: f64| {
+                let _ = Reflect::set(&o, &JsValue::from_str(key), &JsValue::from_f64(value));
+            };
+            set("interface", interface as f64);
+            set("lower", lower as f64);
+            set("upper", upper as f64);
+            set("eta", self.eta_at(interface) as f64);
+            set("etaDrive", self.eta_drive_at(interface) as f64);
+            set("opOffset", self.op_offset(interface) as f64);
+            set("opCells", self.op_cells(interface) as f64);
+            let _ = Reflect::set(
+                &o,
+                &JsValue::from_str("opK"),
+                &JsValue::from_bool(self.interface_has_op(interface)),
+            );
+            out.push(&o);
+        }
+        out
+    }
+
+    /// Coupling energy per interface (S plus meta a/n/w terms, or the particle deposit for
+    /// interfaces from the base), following the layer graph.
+    pub fn coupling_energy_by_interface(&self) -> Float64Array {
+        Float64Array::from(self.coupling_energy_by_interface_inner().as_slice())
+    }
+}
+
+impl Sim {
+    pub(crate) fn interface_count(&self) -> usize {
+        if self.layer_graph.is_empty() {
+            self.params.meta_layers as usize
+        } else {
+            self.layer_graph.len()
+        }
+    }
+
+    pub(crate) fn interface_levels(&self, interface: usize) -> (usize, usize) {
+        match self.layer_graph.get(interface) {
+            Some(edge) => (edge.lower, edge.upper),
+            None => (interface, interface + 1),
+        }
+    }
+
+    pub(crate) fn interface_has_op(&self, interface: usize) -> bool {
+        self.layer_graph.get(interface).is_none_or(|edge| edge.op)
+    }
+
+    // Interfaces with `level` at either end: the one below first, as in the chain.
+    pub(crate) fn interfaces_at(&self, level: usize) -> impl Iterator<Item = usize> + '_ {
+        let chain = self.layer_graph.is_empty();
+        let layers = self.params.meta_layers as usize;
+        let below = (chain && level > 0 && level <= layers).then(|| level - 1);
+        let above = (chain && level < layers).then_some(level);
+        let graph = self
+            .layer_graph
+            .iter()
+            .enumerate()
+            .filter(move |(_, edge)| edge.upper == level || edge.lower == level)
+            .map(|(interface, _)| interface);
+        below.into_iter().chain(above).chain(graph)
+    }
+
+    // First interface with `level` as its upper end (the chain's level - 1).
+    pub(crate) fn interface_below(&self, level: usize) -> Option<usize> {
+        (0..self.interface_count()).find(|i| self.interface_levels(*i).1 == level)
+    }
+
+    // First interface with `level` as its lower end (the chain's level).
+    pub(crate) fn interface_above(&self, level: usize) -> Option<usize> {
+        (0..self.interface_count()).find(|i| self.interface_levels(*i).0 == level)
+    }
+
+    // op-K storage is per interface on the upper level's grid; interfaces without op-K hold none.
+    pub(crate) fn op_cells(&self, interface: usize) -> usize {
+        if self.interface_has_op(interface) {
+            self.level_cells(self.interface_levels(interface).1)
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn op_offset(&self, interface: usize) -> usize {
+        if self.layer_graph.is_empty() {
+            return self.meta_offset(interface);
+        }
+        (0..interface).map(|i| self.op_cells(i)).sum()
+    }
+
+    pub(crate) fn coupling_energy_by_interface_inner(&self) -> Vec<f64> {
+        let l_s = self.params.l_s.max(1) as f64;
+        let norm = |level: usize| -> Vec<f64> {
+            self.s_level(level).iter().map(|s| *s as f64 / l_s).collect()
+        };
+        (0..self.interface_count())
+            .map(|interface| {
+                let (lower, upper) = self.interface_levels(interface);
+                let eta = self.eta_at(interface) as f64;
+                let upper_s = norm(upper);
+                let mut e = 0.0;
+                if self.s_edge_uses_op(interface) {
+                    for (q, u) in upper_s.iter().enumerate() {
+                        e += 0.5 * eta * (u - self.op_pred_norm(interface, q) as f64).powi(2);
+                    }
+                } else {
+                    let lower_s = norm(lower);
+                    for (q, u) in upper_s.iter().enumerate() {
+                        e += 0.5 * eta * (u - self.level_block_mean(&lower_s, lower, upper, q)).powi(2);
+                    }
+                }
+                if lower > 0 {
+                    e += eta * self.meta_coupling_energy(lower - 1, upper - 1);
+                } else if self.params.deposit_coupling_on {
+                    let channels = [DepositChannel::A, DepositChannel::N, DepositChannel::W];
+                    e += eta * channels.iter().map(|c| self.deposit_energy(*c, upper - 1)).sum::<f64>();
+                }
+                e
+            })
+            .collect()
+    }
+
+    // Unweighted a/n/w mismatch energy between two meta layers (w per direction plane).
+    fn meta_coupling_energy(&self, lower: usize, upper: usize) -> f64 {
+        let plane = |layer: usize, field: usize| -> Vec<f64> {
+            let start = self.meta_offset(layer);
+            let cells = self.level_cells(layer + 1);
+            match field {
+                0 => self.meta_a_field[start..start + cells]
+                    .iter()
+                    .map(|a| *a as f64 / self.params.l_a.max(1) as f64)
+                    .collect(),
+                1 => self.meta_n_field[start..start + cells]
+                    .iter()
+                    .map(|n| *n as f64 / self.params.l_n.max(1) as f64)
+                    .collect(),
+                dir => {
+                    let start = self.meta_edge_offset(layer) + (dir - 2) * cells;
+                    self.meta_w_edges[start..start + cells]
+                        .iter()
+                        .map(|w| *w as f64 / self.params.l_w.max(1) as f64)
+                        .collect()
+                }
+            }
+        };
+        let mut e = 0.0;
+        for field in 0..4 {
+            let below = plane(lower, field);
+            for (q, u) in plane(upper, field).iter().enumerate() {
+                e += 0.5 * (u - self.level_block_mean(&below, lower + 1, upper + 1, q)).powi(2);
+            }
+        }
+        e
+    }
+
+    fn level_block_mean(&self, values: &[f64], lower: usize, upper: usize, q: usize) -> f64 {
+        let g = self.level_grid(lower);
+        let (xs, ys) = self.level_block(lower, upper, q);
+        let n = (xs.len() * ys.len()) as f64;
+        ys.flat_map(|y| xs.clone().map(move |x| values[y * g + x])).sum::<f64>() / n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assert_delta, fill_random_fields, Lcg};
+
+    fn total(sim: &Sim) -> f64 {
+        sim.energy_breakdown_inner().5 as f64 + sim.coupling_energy_by_interface_inner().iter().sum::<f64>()
+    }
+
+    #[test]
+    fn test_graph_deltas_match_coupling_energy() {
+        let mut sim = Sim::new(1, 5);
+        let g = 6usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 3;
+        sim.params.meta_downsample = 2;
+        sim.params.eta = 0.4;
+        sim.s_field = vec![0u8; g * g];
+        // Two layers watch the base in parallel; layer 3 summarises both of them.
+        sim.layer_graph = vec![
+            LayerEdge { lower: 0, upper: 1, eta: Some(0.8), eta_drive: None, op: true },
+            LayerEdge { lower: 0, upper: 2, eta: None, eta_drive: None, op: false },
+            LayerEdge { lower: 1, upper: 3, eta: Some(0.6), eta_drive: None, op: true },
+            LayerEdge { lower: 2, upper: 3, eta: Some(0.2), eta_drive: None, op: false },
+        ];
+        sim.resize_meta_arrays();
+        let mut rng = Lcg::new(17);
+        fill_random_fields(&mut sim, &mut rng);
+        assert_eq!(sim.interfaces_at(3).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(sim.interfaces_at(0).collect::<Vec<_>>(), vec![0, 1]);
+
+        for mode in 0..2u8 {
+            sim.params.op_coupling_on = mode == 1;
+            sim.params.s_coupling_mode = mode;
+            sim.init_op_k();
+            assert_eq!(sim.op_k.is_empty(), mode == 0);
+            for _ in 0..60 {
+                let level = rng.next_usize(4);
+                let idx = rng.next_usize(sim.level_cells(level));
+                let s0 = sim.s_level(level)[idx];
+                let s1 = if s0 == 0 { 1 } else { s0 - 1 };
+                let delta = sim.delta_e_s_level(level, idx, s0, s1) as f64;
+                assert_delta(&mut sim, total, delta, |sim| sim.s_level_mut(level)[idx] = s1, 1e-4);
+            }
+        }
+        for _ in 0..30 {
+            let layer = rng.next_usize(3);
+            let idx = rng.next_usize(sim.level_cells(layer + 1));
+            let at = sim.meta_offset(layer) + idx;
+            let a0 = sim.meta_a_field[at];
+            let a1 = if a0 == 0 { 1 } else { a0 - 1 };
+            let delta = sim.delta_e_meta_a_couple(layer, idx, a0, a1) as f64;
+            assert_delta(&mut sim, total, delta, |sim| sim.meta_a_field[at] = a1, 1e-4);
+
+            let edge = rng.next_usize(2 * sim.level_cells(layer + 1));
+            let at = sim.meta_edge_offset(layer) + edge;
+            let w0 = sim.meta_w_edges[at];
+            let w1 = if w0 == 0 { 1 } else { w0 - 1 };
+            let delta = sim.delta_e_meta_w_couple(layer, edge, w0, w1) as f64;
+            assert_delta(&mut sim, total, delta, |sim| sim.meta_w_edges[at] = w1, 1e-4);
+        }
+        // op-K lives only on interfaces 0 and 2, on the grids of levels 1 and 3.
+        assert_eq!(sim.op_cells(1), 0);
+        assert_eq!(sim.op_k.len(), (9 + 1) * sim.op_r_count_internal());
+    }
+}
@@ -0,0 +1,140 @@
+use js_sys::{Array, Float64Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{Sim, MOVE_KIND_COUNT, MOVE_KIND_LABELS};
+
+// First-law ledger over an interval starting at the last reset. Every accepted move books its
+// ΔE and the work W handed to `accept_move` (P6 chemical work, drive alignment work, the
+// ±mu of grand-canonical exchange) under its move kind; heat = ΔE - W is what the bath
+// supplied (negative: released to the bath). Parameter changes through `set_params` book
+// their energy change as protocol work (P3 only cycles kernels, so it adds none of its own),
+// and perturbations and code noise are read from `intervention_energy_total`. Internal energy
+// U is the energy-breakdown total plus the inter-level coupling, so over any interval
+//   U_now - U_start = sum ΔE + protocol work + intervention.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Ledger {
+    pub(crate) delta_e: [f64; MOVE_KIND_COUNT],
+    pub(crate) work: [f64; MOVE_KIND_COUNT],
+    pub(crate) protocol_work: f64,
+    pub(crate) intervention_start: f64,
+    pub(crate) u_start: f64,
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Ledger since the last reset: per move kind `labels`, `deltaE`, `work` and `heat`
+    /// (ΔE - W), plus `protocolWork`, `intervention`, `uStart`, `u` and the first-law
+    /// `residual` (U - U_start minus the booked terms; zero up to rounding).
+    pub fn ledger(&self) -> Object {
+        let labels = Array::new();
+        for label in MOVE_KIND_LABELS {
+            labels.push(&JsValue::from_str(label));
+        }
+        let heat: Vec<f64> = self.ledger.delta_e.iter().zip(&self.ledger.work).map(|(e, w)| e - w).collect();
+        let o = Object::new();
+        let _ = Reflect::set(&o, &JsValue::from_str("labels"), &labels);
+        let set_array = |key: &str, values: &[f64]| {
+            let _ = Reflect::set(&o, &JsValue::from_str(key), &Float64Array::from(values));
+        };
+        set_array("deltaE", &self.ledger.delta_e);
+        set_array("work", &self.ledger.work);
+        set_array("heat", &heat);
+        let set = |key: &str, value: f64| {
+            let _ = Reflect::set(&o, &JsValue::from_str(key), &JsValue::from_f64(value));
+        };
+        set("protocolWork", self.ledger.protocol_work);
+        set("intervention", self.ledger_intervention());
+        set("uStart", self.ledger.u_start);
+        set("u", self.internal_energy());
+        set("residual", self.ledger_residual());
+        o
+    }
+
+    /// Starts a new ledger interval at the current state.
+    pub fn ledger_reset(&mut self) {
+        self.ledger = Ledger {
+            intervention_start: self.intervention_energy_total,
+            u_start: self.internal_energy(),
+            ..Ledger::default()
+        };
+    }
+}
+
+impl Sim {
+    // The `energy_breakdown()` total: every term plus the inter-level coupling (op-K included).
+    pub(crate) fn internal_energy(&self) -> f64 {
+        let couple: f64 = self.coupling_energy_by_interface_inner().iter().sum();
+        self.energy_breakdown_inner().6 as f64 + couple
+    }
+
+    pub(crate) fn ledger_book_move(&mut self, move_kind: usize, delta_e: f32, work: f32) {
+        if move_kind < MOVE_KIND_COUNT {
+            self.ledger.delta_e[move_kind] += delta_e as f64;
+            self.ledger.work[move_kind] += work as f64;
+        }
+    }
+
+    // Runs a parameter change and books its energy change (net of interventions) as protocol work.
+    pub(crate) fn with_protocol_work(&mut self, change: impl FnOnce(&mut Sim)) {
+        let u0 = self.internal_energy();
+        let i0 = self.intervention_energy_total;
+        change(self);
+        let d_u = self.internal_energy() - u0;
+        self.ledger.protocol_work += d_u - (self.intervention_energy_total - i0);
+    }
+
+    fn ledger_intervention(&self) -> f64 {
+        self.intervention_energy_total - self.ledger.intervention_start
+    }
+
+    pub(crate) fn ledger_residual(&self) -> f64 {
+        let moves: f64 = self.ledger.delta_e.iter().sum();
+        let booked = moves + self.ledger.protocol_work + self.ledger_intervention();
+        self.internal_energy() - self.ledger.u_start - booked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MOVE_P5_BASE;
+
+    #[test]
+    fn test_ledger_closes_over_mixed_dynamics() {
+        let mut sim = Sim::new(10, 23);
+        let g = 8usize;
+        sim.params.grid_size = g as u16;
+        sim.s_field = vec![0u8; g * g];
+        sim.params.meta_layers = 2;
+        sim.params.meta_downsample = 2;
+        sim.resize_meta_arrays();
+        sim.params.eta = 0.4;
+        sim.params.p6_on = true;
+        sim.params.p_s_write = 0.3;
+        sim.params.j_s = 0.1;
+        sim.params.kappa_field = 0.5;
+        sim.params.deposit_coupling_on = true;
+        sim.params.code_noise_rate = 0.05;
+        sim.params.gc_on = true;
+        sim.params.gc_frac = 0.2;
+        sim.ledger_reset();
+        sim.step(3000);
+        let scale = 1.0 + sim.internal_energy().abs();
+        assert!(sim.ledger_residual().abs() < 1e-3 * scale, "residual {}", sim.ledger_residual());
+        assert!(sim.ledger.work[MOVE_P5_BASE] != 0.0);
+        assert!(sim.intervention_energy_total != sim.ledger.intervention_start);
+
+        sim.with_protocol_work(|sim| sim.params.lambda_s *= 2.0);
+        assert!(sim.ledger.protocol_work != 0.0);
+        sim.step(500);
+        assert!(sim.ledger_residual().abs() < 1e-3 * scale);
+
+        // The naive EP is -beta times the heat taken from the bath.
+        sim.ledger_reset();
+        let ep0 = sim.ep_naive_total;
+        sim.step(1000);
+        let heat: f64 = (0..MOVE_KIND_COUNT).map(|k| sim.ledger.delta_e[k] - sim.ledger.work[k]).sum();
+        let ep = sim.ep_naive_total - ep0;
+        assert!((ep + sim.params.beta as f64 * heat).abs() < 1e-3 * (1.0 + heat.abs()));
+    }
+}
@@ -0,0 +1,274 @@
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::region::RegionMask;
+use crate::{diag_flux_affinity, get_string, get_u16, get_u32, get_u8, Sim};
+
+// A lens is a user-registered integer coarse observable. Every `every` steps its value is
+// re-evaluated and the sign of the change is tallied, giving the same flux / affinity / sigma
+// estimates `diagnostics()` reports for the built-in w/n/a/s counters.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum LensKind {
+    // Sum of S over a region at level 0 (base) or 1.. (meta layers).
+    SumS { level: usize, region: RegionMask },
+    // Sum of all particle bond weights.
+    SumW,
+    // Number of particle bonds with w >= threshold.
+    BondsAbove { threshold: u8 },
+    // Cells in a region whose op-K token motif equals `class` (requires opMotifOn).
+    MotifCount { interface: usize, class: u16, region: RegionMask },
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Lens {
+    pub(crate) id: u32,
+    pub(crate) kind: LensKind,
+    pub(crate) every: u32,
+    pub(crate) start_step: u32,
+    pub(crate) value: i64,
+    pub(crate) plus: u32,
+    pub(crate) minus: u32,
+    pub(crate) plus_mass: u64,
+    pub(crate) minus_mass: u64,
+    pub(crate) window: u32,
+}
+
+impl LensKind {
+    fn from_js(spec: &JsValue, default_bins: u8) -> Option<LensKind> {
+        let region = || RegionMask::from_params(spec, "region", default_bins).unwrap_or(RegionMask::All);
+        let kind = match get_string(spec, "type")?.as_str() {
+            "sumS" => LensKind::SumS {
+                level: get_u16(spec, "level").unwrap_or(0) as usize,
+                region: region(),
+            },
+            "sumW" => LensKind::SumW,
+            "bondsAbove" => LensKind::BondsAbove {
+                threshold: get_u8(spec, "threshold").unwrap_or(1),
+            },
+            "motifCount" => LensKind::MotifCount {
+                interface: get_u16(spec, "interface").unwrap_or(0) as usize,
+                class: get_u16(spec, "class").unwrap_or(0),
+                region: region(),
+            },
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            LensKind::SumS { .. } => "sumS",
+            LensKind::SumW => "sumW",
+            LensKind::BondsAbove { .. } => "bondsAbove",
+            LensKind::MotifCount { .. } => "motifCount",
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Registers a lens and returns its id (0 if the spec is not recognised).
+    ///
+    /// Spec: `type` ("sumS" | "sumW" | "bondsAbove" | "motifCount") with `level`, `region`,
+    /// `threshold`, `interface`, `class` as relevant, and `every` (evaluation stride). Each
+    /// evaluation costs O(cells) or O(bonds), so `every` defaults to one sweep (particles plus
+    /// base cells); pass `every: 1` to tally every step like `diagnostics()`.
+    pub fn add_lens(&mut self, spec: JsValue) -> u32 {
+        if !spec.is_object() {
+            return 0;
+        }
+        let Some(kind) = LensKind::from_js(&spec, self.params.clock_k) else {
+            return 0;
+        };
+        let every = get_u32(&spec, "every").unwrap_or_else(|| self.lens_default_every()).max(1);
+        self.lens_next_id = self.lens_next_id.wrapping_add(1).max(1);
+        let value = self.lens_value_of(&kind);
+        self.lenses.push(Lens {
+            id: self.lens_next_id,
+            kind,
+            every,
+            start_step: self.step_count,
+            value,
+            plus: 0,
+            minus: 0,
+            plus_mass: 0,
+            minus_mass: 0,
+            window: 0,
+        });
+        self.lens_next_id
+    }
+
+    /// Per-lens counts and flux `j`, affinity `a` and `sigma = j * a` per evaluation.
+    pub fn lens_diagnostics(&self) -> Array {
+        let out = Array::new();
+        for lens in &self.lenses {
+            let (j, a, sigma) = diag_flux_affinity(lens.plus, lens.minus, lens.window);
+            let o = Object::new();
+            let _ = Reflect::set(&o, &JsValue::from_str("id"), &JsValue::from_f64(lens.id as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("type"), &JsValue::from_str(lens.kind.name()));
+            let _ = Reflect::set(&o, &JsValue::from_str("value"), &JsValue::from_f64(lens.value as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("plus"), &JsValue::from_f64(lens.plus as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("minus"), &JsValue::from_f64(lens.minus as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("plusMass"), &JsValue::from_f64(lens.plus_mass as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("minusMass"), &JsValue::from_f64(lens.minus_mass as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("window"), &JsValue::from_f64(lens.window as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("every"), &JsValue::from_f64(lens.every as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("j"), &JsValue::from_f64(j as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("a"), &JsValue::from_f64(a as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("sigma"), &JsValue::from_f64(sigma as f64));
+            out.push(&o);
+        }
+        out
+    }
+
+    pub fn lens_value(&self, id: u32) -> f64 {
+        match self.lenses.iter().find(|l| l.id == id) {
+            Some(lens) => self.lens_value_of(&lens.kind) as f64,
+            None => f64::NAN,
+        }
+    }
+
+    /// Zeroes the tallies and re-baselines every lens at the current state.
+    pub fn reset_lens_counters(&mut self) {
+        let values: Vec<i64> = self.lenses.iter().map(|l| self.lens_value_of(&l.kind)).collect();
+        let step = self.step_count;
+        for (lens, value) in self.lenses.iter_mut().zip(values) {
+            lens.value = value;
+            lens.start_step = step;
+            lens.plus = 0;
+            lens.minus = 0;
+            lens.plus_mass = 0;
+            lens.minus_mass = 0;
+            lens.window = 0;
+        }
+    }
+
+    pub fn clear_lenses(&mut self) {
+        self.lenses.clear();
+    }
+}
+
+impl Sim {
+    // One sweep: roughly one proposal per particle and per base cell.
+    fn lens_default_every(&self) -> u32 {
+        (self.n + self.level_cells(0)).max(1) as u32
+    }
+
+    // Region lenses test cells on their own level's grid.
+    pub(crate) fn lens_value_of(&self, kind: &LensKind) -> i64 {
+        match kind {
+            LensKind::SumS { level, region } => {
+                if *level > self.params.meta_layers as usize {
+                    return 0;
+                }
+                let field = self.s_level(*level);
+                let g = self.level_grid(*level);
+                if *region == RegionMask::All {
+                    return field.iter().map(|s| *s as i64).sum();
+                }
+                field
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| region.contains_cell(*idx, g))
+                    .map(|(_, s)| *s as i64)
+                    .sum()
+            }
+            LensKind::SumW => self.w.iter().map(|w| *w as i64).sum(),
+            LensKind::BondsAbove { threshold } => self.w.iter().filter(|w| **w >= *threshold).count() as i64,
+            LensKind::MotifCount {
+                interface,
+                class,
+                region,
+            } => {
+                if *interface >= self.interface_count() {
+                    return 0;
+                }
+                let g = self.level_grid(self.interface_levels(*interface).1);
+                let start = self.op_offset(*interface);
+                let Some(labels) = self.op_motif.get(start..start + self.op_cells(*interface)) else {
+                    return 0;
+                };
+                labels
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, label)| **label == *class && region.contains_cell(*idx, g))
+                    .count() as i64
+            }
+        }
+    }
+
+    pub(crate) fn update_lenses(&mut self) {
+        for i in 0..self.lenses.len() {
+            let lens = &self.lenses[i];
+            if !self.step_count.wrapping_sub(lens.start_step).is_multiple_of(lens.every) {
+                continue;
+            }
+            let value = self.lens_value_of(&lens.kind);
+            let lens = &mut self.lenses[i];
+            let delta = value - lens.value;
+            if delta > 0 {
+                lens.plus = lens.plus.saturating_add(1);
+                lens.plus_mass = lens.plus_mass.saturating_add(delta as u64);
+            } else if delta < 0 {
+                lens.minus = lens.minus.saturating_add(1);
+                lens.minus_mass = lens.minus_mass.saturating_add(delta.unsigned_abs());
+            }
+            lens.value = value;
+            lens.window = lens.window.saturating_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sum_s_lens_reproduces_builtin_s_counters() {
+        let mut sim = Sim::new(0, 21);
+        let g = 6usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 0;
+        sim.params.p_write = 0.0;
+        sim.params.p_n_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_s_write = 1.0;
+        sim.s_field = vec![1u8; g * g];
+        sim.recompute_sum_s();
+        let kind = LensKind::SumS {
+            level: 0,
+            region: RegionMask::All,
+        };
+        sim.lenses.push(Lens {
+            id: 1,
+            kind,
+            every: 1,
+            start_step: sim.step_count,
+            value: sim.sum_s as i64,
+            plus: 0,
+            minus: 0,
+            plus_mass: 0,
+            minus_mass: 0,
+            window: 0,
+        });
+        sim.step(500);
+
+        let lens = &sim.lenses[0];
+        let (w_plus, w_minus, n_plus, n_minus, a_plus, a_minus, s_plus, s_minus, window) = sim.diag.counts();
+        assert_eq!(w_plus + w_minus + n_plus + n_minus + a_plus + a_minus, 0);
+        assert_eq!((lens.plus, lens.minus, lens.window), (s_plus, s_minus, window));
+        assert!(lens.plus > 0 && lens.minus > 0);
+        assert_eq!(lens.value, sim.sum_s as i64);
+        assert_eq!(lens.plus_mass as i64 - lens.minus_mass as i64, sim.sum_s as i64 - 36);
+    }
+
+    #[test]
+    fn test_default_stride_is_one_sweep() {
+        let mut sim = Sim::new(10, 4);
+        sim.params.grid_size = 8;
+        assert_eq!(sim.lens_default_every(), 10 + 64);
+        let mut empty = Sim::new(0, 4);
+        empty.params.grid_size = 0;
+        assert_eq!(empty.lens_default_every(), 1);
+    }
+}
@@ -2,11 +2,50 @@ use js_sys::{
     Array, Float32Array, Float64Array, Int16Array, Int8Array, Object, Reflect, Uint16Array,
     Uint32Array, Uint8Array,
 };
+use std::borrow::Cow;
 use wasm_bindgen::prelude::*;
 
+mod accept_rule;
+mod coarse_ep;
+mod code_metrics;
+mod deadline;
+mod deposit;
+mod grand_canonical;
+mod idempotence;
+mod kmc;
+mod lateral;
+mod ledger;
+mod layer_graph;
+mod lens;
+mod motif;
+mod noise;
+mod particle_field;
+mod potential;
+mod reaction;
+mod region;
+mod replica;
+mod reservoir;
+mod species;
+#[cfg(test)]
+mod test_support;
+
+use code_metrics::LogicalEncoding;
+use deadline::DeadlineTask;
+use deposit::{DepositChannel, DepositEdit};
+use idempotence::IdempotenceSeries;
+use kmc::{KmcStats, MoveEval};
+use layer_graph::LayerEdge;
+use ledger::Ledger;
+use lens::Lens;
+use noise::CodeNoiseStats;
+use potential::Potential;
+use region::{Axis, RegionMask};
+use species::SpeciesTable;
+
 const DEFAULT_GRID_SIZE: usize = 16;
 const MAX_META_LAYERS: u16 = 16;
-const MOVE_KIND_COUNT: usize = 11;
+const MAX_S_LEVELS: usize = MAX_META_LAYERS as usize + 1;
+const MOVE_KIND_COUNT: usize = 14;
 const MOVE_KIND_LABELS: [&str; MOVE_KIND_COUNT] = [
     "X",
     "P1Base",
@@ -19,6 +58,24 @@ const MOVE_KIND_LABELS: [&str; MOVE_KIND_COUNT] = [
     "P5Meta",
     "OpK",
     "Clock",
+    "GC",
+    "ReactH",
+    "ReactL",
+];
+
+// Parameters that only steer proposals, drive, reservoirs or diagnostics: they cannot change
+// the internal energy, so a `set_params` call setting nothing else skips the protocol-work
+// bracket (two full energy evaluations).
+const NON_ENERGY_PARAMS: [&str; 52] = [
+    "beta", "stepSize", "pWrite", "pNWrite", "pAWrite", "pSWrite", "p3On", "p6On", "p6SFactor",
+    "muHigh", "muLow", "reservoirMode", "reservoirCapacity", "reservoirRefill", "reservoirStocks",
+    "gcOn", "gcFrac", "muParticle", "nMax", "reactionOn", "reactionFrac", "reactionA", "reactionB",
+    "reactionCatalyst", "reactionRadius", "etaDrive", "etaDriveByInterface", "opMotifOn",
+    "opMotifMode", "opDriveOnK", "acceptLogOn", "acceptLogMask", "acceptLogCap", "epDebug",
+    "acceptRule", "codeNoiseRate", "codeNoiseBatch", "codeNoiseLayer", "codeNoiseModel",
+    "codeNoiseBurst", "codeNoiseRates", "codeEncoding", "codeNoiseOpKRate", "clockOn", "clockK",
+    "clockFrac", "clockUsesP6", "repairClockGated", "repairGateMode", "repairGateRegions",
+    "repairGateSpan", "rPropose",
 ];
 
 const MOVE_X: usize = 0;
@@ -32,6 +89,9 @@ const MOVE_P5_BASE: usize = 7;
 const MOVE_P5_META: usize = 8;
 const MOVE_OPK: usize = 9;
 const MOVE_CLOCK: usize = 10;
+const MOVE_GC: usize = 11;
+const MOVE_REACT_H: usize = 12;
+const MOVE_REACT_L: usize = 13;
 
 const OP_STENCIL_CROSS: [(i32, i32); 5] = [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)];
 const OP_STENCIL_FULL: [(i32, i32); 9] = [
@@ -47,6 +107,7 @@ const OP_STENCIL_FULL: [(i32, i32); 9] = [
 ];
 
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct Sim {
     n: usize,
     positions: Vec<f32>, // [x0,y0,x1,y1,...] in [0,1)
@@ -78,6 +139,10 @@ pub struct Sim {
     ep_q_stats: [EpQStats; MOVE_KIND_COUNT],
     ep_naive_by_move: [f64; MOVE_KIND_COUNT],
     ep_exact_by_move: [f64; MOVE_KIND_COUNT],
+    intervention_energy_total: f64,
+    intervention_count: u64,
+    ledger: Ledger,
+    kmc: KmcStats,
     accept_log_u32: Vec<u32>,
     accept_log_ep: Vec<f64>,
     accept_log_overflowed: bool,
@@ -86,6 +151,49 @@ pub struct Sim {
     clock_q: i64,
     clock_fwd: u64,
     clock_bwd: u64,
+    repair_gate_regions: Vec<RegionMask>,
+    code_noise_region: Option<RegionMask>,
+    code_noise_cells: Vec<u32>,
+    code_noise_rates: Vec<f32>,
+    code_noise_stats: CodeNoiseStats,
+    code_encoding: LogicalEncoding,
+    // Optional per-interface / per-level overrides of the scalar params; missing entries
+    // fall back to the scalar, and setting the scalar clears the vector.
+    eta_by_interface: Vec<f32>,
+    eta_drive_by_interface: Vec<f32>,
+    op_budget_by_interface: Vec<u8>,
+    op_stencil_by_interface: Vec<u8>,
+    lambda_s_by_level: Vec<f32>,
+    // Declared inter-level coupling edges; empty means the default chain 0 -> 1 -> ... -> layers.
+    layer_graph: Vec<LayerEdge>,
+    // Custom particle potentials; None keeps the soft wall (kappaRep, r0) and the harmonic
+    // bond shape (kappaBond, rStar).
+    pair_potential: Option<Potential>,
+    bond_potential: Option<Potential>,
+    // Per-particle species label and the species parameter table (None: one species using the
+    // global parameters).
+    species: Vec<u8>,
+    species_table: Option<SpeciesTable>,
+    gc_inserted: u64,
+    gc_deleted: u64,
+    // Accepted conversions: A -> B and B -> A at the high reservoir, then at the low one.
+    reaction_counts: [u64; 4],
+    // Finite P6 reservoir stocks (high, low) and the running drawn / refilled / spilled totals.
+    reservoir_stock: [f32; 2],
+    reservoir_drawn: f64,
+    reservoir_refilled: f64,
+    reservoir_spilled: f64,
+    // Deposited bond bins (sum, count) per meta layer, updated on every bond or move commit.
+    deposit_w_bins: Vec<Vec<(i32, u32)>>,
+    // Particles per base cell while the particle-field coupling is on, updated on every move.
+    field_counts: Vec<u16>,
+    op_motif: Vec<u16>,
+    op_motif_trans: Vec<u32>,
+    deadline_tasks: Vec<DeadlineTask>,
+    deadline_next_id: u32,
+    lenses: Vec<Lens>,
+    lens_next_id: u32,
+    idempotence_series: Option<IdempotenceSeries>,
 }
 
 #[derive(Clone, Copy)]
@@ -106,6 +214,20 @@ struct Params {
     r0: f32,
     kappa_bond: f32,
     r_star: f32,
+    kappa_field: f32, // particle-field coupling: each particle lowers E by kappa_field * s / L_s of its base cell
+    gc_on: bool,       // grand-canonical particle insertion/deletion
+    gc_frac: f32,      // fraction of X-slot steps that become exchange moves
+    mu_particle: f32,  // reservoir chemical potential
+    n_max: u16,        // particle cap for insertions
+    reaction_on: bool, // A <-> B species interconversion
+    reaction_frac: f32, // fraction of X-slot steps that become conversion moves
+    reaction_a: u8,
+    reaction_b: u8,
+    reaction_catalyst: u8, // species a bonded partner needs to catalyse (255 = any)
+    reaction_radius: f32,  // catalysis range
+    reservoir_mode: u8,    // 0 = infinite P6 reservoirs, 1 = one finite stock, 2 = stock per half
+    reservoir_capacity: f32,
+    reservoir_refill: f32, // stock added per step, up to capacity
     lambda_w: f32,
     l_w: u8,
     lambda_n: f32,
@@ -114,25 +236,36 @@ struct Params {
     l_a: u16,
     lambda_s: f32,
     l_s: u8,
+    j_s: f32, // in-layer neighbour coupling of S (0 = off)
+    s_lateral_mode: u8, // 0 = jS * ((s_i - s_j) / lS)^2, 1 = Potts jS * [s_i != s_j]
+    s_lateral_stencil: u8, // 0 = 4-neighbour, 1 = 8-neighbour
     grid_size: u16,
     r_propose: f32, // neighbor radius for P1 proposals
     meta_layers: u16,
+    meta_downsample: u8, // each meta level is this many times coarser than the one below (1 = same grid)
     eta: f32,
     eta_drive: f32,
+    deposit_coupling_on: bool, // couple particle counters and bonds to the meta layers above the base
     op_coupling_on: bool,
     op_stencil: u8,
     op_budget_k: u8,
     op_k_target_weight: f32,
+    op_motif_on: bool, // track per-cell op-K motif labels and transitions
+    op_motif_mode: u8, // 0 = axis bins, 1 = dir9 argmax, 2 = dir9 x entropy
     s_coupling_mode: u8,
     op_drive_on_k: bool,
     accept_log_on: bool,
     accept_log_mask: u32,
     accept_log_cap: u32,
     ep_debug: bool,
+    accept_rule: [u8; MOVE_KIND_COUNT],
     init_random: bool,
     code_noise_rate: f32,
     code_noise_batch: u16,
     code_noise_layer: u16,
+    code_noise_model: u8,
+    code_noise_burst: u8,
+    code_noise_opk_rate: f32,
     clock_on: bool,
     clock_k: u8,
     clock_frac: f32,
@@ -210,6 +343,20 @@ impl Sim {
                 r0: 0.03,
                 kappa_bond: 3.0,
                 r_star: 0.18,
+                kappa_field: 0.0,
+                gc_on: false,
+                gc_frac: 0.1,
+                mu_particle: 0.0,
+                n_max: 256,
+                reaction_on: false,
+                reaction_frac: 0.1,
+                reaction_a: 0,
+                reaction_b: 1,
+                reaction_catalyst: u8::MAX,
+                reaction_radius: 0.22,
+                reservoir_mode: 0,
+                reservoir_capacity: 100.0,
+                reservoir_refill: 0.01,
                 lambda_w: 0.12,
                 l_w: 5,
                 lambda_n: 0.5,
@@ -218,25 +365,36 @@ impl Sim {
                 l_a: 6,
                 lambda_s: 0.5,
                 l_s: 6,
+                j_s: 0.0,
+                s_lateral_mode: 0,
+                s_lateral_stencil: 0,
                 grid_size: DEFAULT_GRID_SIZE as u16,
                 r_propose: 0.22,
                 meta_layers: 0,
+                meta_downsample: 1,
                 eta: 0.0,
                 eta_drive: 0.0,
+                deposit_coupling_on: false,
                 op_coupling_on: false,
                 op_stencil: 0,
                 op_budget_k: 16,
                 op_k_target_weight: 1.0,
+                op_motif_on: false,
+                op_motif_mode: 2,
                 s_coupling_mode: 0,
                 op_drive_on_k: true,
                 accept_log_on: false,
                 accept_log_mask: 0,
                 accept_log_cap: 100000,
                 ep_debug: false,
+                accept_rule: [accept_rule::ACCEPT_METROPOLIS; MOVE_KIND_COUNT],
                 init_random: false,
                 code_noise_rate: 0.0,
                 code_noise_batch: 1,
                 code_noise_layer: 0,
+                code_noise_model: 0,
+                code_noise_burst: 0,
+                code_noise_opk_rate: 0.0,
                 clock_on: false,
                 clock_k: 8,
                 clock_frac: 0.2,
@@ -262,6 +420,10 @@ impl Sim {
             ep_q_stats: [EpQStats::default(); MOVE_KIND_COUNT],
             ep_naive_by_move: [0.0; MOVE_KIND_COUNT],
             ep_exact_by_move: [0.0; MOVE_KIND_COUNT],
+            intervention_energy_total: 0.0,
+            intervention_count: 0,
+            ledger: Ledger::default(),
+            kmc: KmcStats::default(),
             accept_log_u32: Vec::new(),
             accept_log_ep: Vec::new(),
             accept_log_overflowed: false,
@@ -270,6 +432,38 @@ impl Sim {
             clock_q: 0,
             clock_fwd: 0,
             clock_bwd: 0,
+            repair_gate_regions: Vec::new(),
+            code_noise_region: None,
+            code_noise_cells: Vec::new(),
+            code_noise_rates: Vec::new(),
+            code_noise_stats: CodeNoiseStats::default(),
+            code_encoding: LogicalEncoding::default(),
+            eta_by_interface: Vec::new(),
+            eta_drive_by_interface: Vec::new(),
+            op_budget_by_interface: Vec::new(),
+            op_stencil_by_interface: Vec::new(),
+            lambda_s_by_level: Vec::new(),
+            layer_graph: Vec::new(),
+            pair_potential: None,
+            bond_potential: None,
+            species: vec![0; n],
+            species_table: None,
+            gc_inserted: 0,
+            gc_deleted: 0,
+            reaction_counts: [0; 4],
+            reservoir_stock: [100.0; 2],
+            reservoir_drawn: 0.0,
+            reservoir_refilled: 0.0,
+            reservoir_spilled: 0.0,
+            deposit_w_bins: Vec::new(),
+            field_counts: Vec::new(),
+            op_motif: Vec::new(),
+            op_motif_trans: Vec::new(),
+            deadline_tasks: Vec::new(),
+            deadline_next_id: 0,
+            lenses: Vec::new(),
+            lens_next_id: 0,
+            idempotence_series: None,
         };
         for i in 0..n {
             let x = sim.rand01();
@@ -277,6 +471,8 @@ impl Sim {
             sim.positions[2 * i] = x;
             sim.positions[2 * i + 1] = y;
         }
+        sim.recompute_field_counts();
+        sim.ledger.u_start = sim.internal_energy();
         sim
     }
 
@@ -374,12 +570,17 @@ impl Sim {
                     } else if delta < 0 {
                         step_diag.s_minus = 1;
                     }
+                } else if self.params.reaction_on && self.rand01() < self.params.reaction_frac {
+                    self.reaction_step();
+                } else if self.params.gc_on && self.rand01() < self.params.gc_frac {
+                    self.gc_exchange_step();
                 } else {
                     self.x_move_step();
                 }
             }
             self.diag.push(step_diag);
-            self.maybe_code_noise();
+            self.refill_reservoirs(1.0);
+            self.run_step_hooks();
         }
     }
 
@@ -455,8 +656,36 @@ impl Sim {
         }
     }
 
+    /// Edges per meta layer at the first meta level; use `level_shapes` when downsampled.
     pub fn meta_edge_count(&self) -> u32 {
-        meta_edge_count(self.params.grid_size as usize) as u32
+        meta_edge_count(self.level_grid(1)) as u32
+    }
+
+    /// Grid side of one S level (0 = base, 1.. = meta layers).
+    pub fn level_grid_size(&self, level: u16) -> u32 {
+        self.level_grid(level as usize) as u32
+    }
+
+    /// Shape of every S level: `level`, `grid` (side), `cells`, `edges`, and for meta layers
+    /// `offset` / `edgeOffset` into the concatenated meta arrays (also the op_k cell offset).
+    pub fn level_shapes(&self) -> Array {
+        let out = Array::new();
+        for level in 0..=self.params.meta_layers as usize {
+            let grid = self.level_grid(level);
+            let o = Object::new();
+            let _ = Reflect::set(&o, &JsValue::from_str("level"), &JsValue::from_f64(level as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("grid"), &JsValue::from_f64(grid as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("cells"), &JsValue::from_f64((grid * grid) as f64));
+            let _ = Reflect::set(&o, &JsValue::from_str("edges"), &JsValue::from_f64(meta_edge_count(grid) as f64));
+            if level > 0 {
+                let offset = self.meta_offset(level - 1);
+                let edge_offset = self.meta_edge_offset(level - 1);
+                let _ = Reflect::set(&o, &JsValue::from_str("offset"), &JsValue::from_f64(offset as f64));
+                let _ = Reflect::set(&o, &JsValue::from_str("edgeOffset"), &JsValue::from_f64(edge_offset as f64));
+            }
+            out.push(&o);
+        }
+        out
     }
 
     pub fn op_r_count(&self) -> u32 {
@@ -485,8 +714,26 @@ impl Sim {
         self.params.op_budget_k as u32
     }
 
+    /// Effective per-interface coupling parameters (and `lambdaS` per S level).
+    pub fn interface_params(&self) -> Object {
+        let layers = self.params.meta_layers as usize;
+        let interfaces = self.interface_count();
+        let eta: Vec<f32> = (0..interfaces).map(|i| self.eta_at(i)).collect();
+        let eta_drive: Vec<f32> = (0..interfaces).map(|i| self.eta_drive_at(i)).collect();
+        let budget: Vec<u8> = (0..interfaces).map(|i| self.op_budget_at(i)).collect();
+        let stencil: Vec<u8> = (0..interfaces).map(|i| self.op_stencil_at(i)).collect();
+        let lambda_s: Vec<f32> = (0..=layers).map(|l| self.lambda_s_at(l)).collect();
+        let o = Object::new();
+        let _ = Reflect::set(&o, &JsValue::from_str("eta"), &Float32Array::from(eta.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("etaDrive"), &Float32Array::from(eta_drive.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("opBudgetK"), &Uint8Array::from(budget.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("opStencil"), &Uint8Array::from(stencil.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("lambdaS"), &Float32Array::from(lambda_s.as_slice()));
+        o
+    }
+
     pub fn op_interfaces(&self) -> u32 {
-        self.params.meta_layers as u32
+        self.interface_count() as u32
     }
 
     pub fn op_stencil_id(&self) -> u32 {
@@ -521,6 +768,16 @@ impl Sim {
         labels
     }
 
+    /// Energy injected by external interventions (perturbations and code noise), kept apart from EP.
+    pub fn intervention_energy_total(&self) -> f64 {
+        self.intervention_energy_total
+    }
+
+    /// Perturbations applied plus code-noise events that changed a cell or moved a token.
+    pub fn intervention_count(&self) -> u64 {
+        self.intervention_count
+    }
+
     pub fn accept_log_len(&self) -> u32 {
         self.accept_log_ep.len() as u32
     }
@@ -588,135 +845,86 @@ impl Sim {
         o
     }
 
+    /// 0/1 cell mask for a region spec, as used by perturbations, gates and noise.
+    pub fn region_mask(&self, spec: JsValue) -> Uint8Array {
+        let g = self.params.grid_size as usize;
+        match RegionMask::from_js(&spec, self.params.clock_k) {
+            Some(region) => Uint8Array::from(region.to_mask(g).as_slice()),
+            None => Uint8Array::new_with_length(0),
+        }
+    }
+
+    /// Mean |S_a - S_b| over a region between two S levels (0 = base, 1.. = meta layers).
+    /// Downsampled levels are compared on the base grid.
+    pub fn region_s_mean_abs_diff(&self, spec: JsValue, level_a: u16, level_b: u16) -> f64 {
+        let layers = self.params.meta_layers as usize;
+        let (a, b) = (level_a as usize, level_b as usize);
+        if a > layers || b > layers {
+            return 0.0;
+        }
+        let region = RegionMask::from_js(&spec, self.params.clock_k).unwrap_or(RegionMask::All);
+        let g = self.params.grid_size as usize;
+        code_metrics::mean_abs_diff_region(&self.s_level_lifted(a), &self.s_level_lifted(b), g, &region)
+    }
+
     #[wasm_bindgen]
-    pub fn apply_perturbation(&mut self, params: JsValue) {
+    pub fn apply_perturbation(&mut self, params: JsValue) -> Object {
         if !params.is_object() {
-            return;
+            return perturb_report_object(&PerturbReport::default());
         }
         let target = match get_string(&params, "target") {
             Some(v) => v,
-            None => return,
+            None => return perturb_report_object(&PerturbReport::default()),
         };
-        let mode = get_string(&params, "mode").unwrap_or_else(|| "randomize".to_string());
-        let region = get_string(&params, "region").unwrap_or_else(|| "all".to_string());
-        let frac = get_f32(&params, "frac").unwrap_or(0.0).clamp(0.0, 1.0);
-        if frac <= 0.0 {
-            return;
-        }
-        let target_quadrant = if region == "quadrant" {
-            Some(get_u8(&params, "quadrant").unwrap_or(0).min(3))
+        let level = if target == "baseS" {
+            0
+        } else if target == "metaS" {
+            let layer = get_u16(&params, "layer").unwrap_or(0) as usize;
+            if layer >= self.params.meta_layers as usize {
+                return perturb_report_object(&PerturbReport::default());
+            }
+            layer + 1
         } else {
-            None
+            return perturb_report_object(&PerturbReport::default());
         };
-        let target_stripe = if region == "stripe" {
-            let bins = get_u8(&params, "bins").unwrap_or(self.params.clock_k).max(1);
-            let span = get_u8(&params, "span").unwrap_or(1).max(1);
-            let bin = get_u8(&params, "bin").unwrap_or(0);
-            Some((bins, span, bin))
+        let mode_name = get_string(&params, "mode").unwrap_or_else(|| "randomize".to_string());
+        let mode = match mode_name.as_str() {
+            "zero" => PerturbMode::Zero,
+            "set" => PerturbMode::Set(get_u8(&params, "value").unwrap_or(0)),
+            "invert" => PerturbMode::Invert,
+            "bias" => PerturbMode::Bias(get_i16(&params, "k").unwrap_or(1)),
+            "shift" => PerturbMode::Shift(
+                get_i16(&params, "dx").unwrap_or(0) as i32,
+                get_i16(&params, "dy").unwrap_or(0) as i32,
+            ),
+            "copyFrom" => PerturbMode::CopyFrom,
+            _ => PerturbMode::Randomize,
+        };
+        let frac = get_f32(&params, "frac").unwrap_or(0.0).clamp(0.0, 1.0);
+        if frac <= 0.0 {
+            return perturb_report_object(&PerturbReport::default());
+        }
+        let region =
+            RegionMask::from_params(&params, "region", self.params.clock_k).unwrap_or(RegionMask::All);
+        let source = if mode == PerturbMode::CopyFrom {
+            match self.perturb_copy_source(&params, level) {
+                Some(v) => Some(v),
+                None => return perturb_report_object(&PerturbReport::default()),
+            }
         } else {
             None
         };
         let seed = get_u32(&params, "seed").unwrap_or_else(|| self.rand_u32());
-        let mut rng = seed;
-        let mut next_u32 = || {
-            // xorshift32
-            let mut x = rng;
-            x ^= x << 13;
-            x ^= x >> 17;
-            x ^= x << 5;
-            rng = x;
-            x
+        let spec = PerturbSpec {
+            level,
+            mode,
+            frac,
+            region,
+            source,
+            seed,
         };
-
-        let g = self.params.grid_size as usize;
-        let cells = g * g;
-        let l_s = self.params.l_s;
-        let mut touched_base = false;
-
-        if target == "baseS" {
-            for (idx, s) in self.s_field.iter_mut().enumerate() {
-                if let Some(q) = target_quadrant {
-                    let x = idx % g;
-                    let y = idx / g;
-                    let qx = if x < g / 2 { 0 } else { 1 };
-                    let qy = if y < g / 2 { 0 } else { 1 };
-                    let quad = (qy * 2 + qx) as u8;
-                    if quad != q {
-                        continue;
-                    }
-                }
-                if let Some((bins, span, bin)) = target_stripe {
-                    let x = idx % g;
-                    let stripe = ((x as f32 / g as f32) * (bins as f32)).floor() as u8;
-                    let span = span.min(bins);
-                    let mut ok = false;
-                    for i in 0..span {
-                        if stripe == (bin + i) % bins {
-                            ok = true;
-                            break;
-                        }
-                    }
-                    if !ok {
-                        continue;
-                    }
-                }
-                let u = next_u32() >> 8;
-                let r = (u as f32) / ((1u32 << 24) as f32);
-                if r < frac {
-                    *s = match mode.as_str() {
-                        "zero" => 0,
-                        _ => (next_u32() % (l_s as u32 + 1)) as u8,
-                    };
-                    touched_base = true;
-                }
-            }
-        } else if target == "metaS" {
-            let layer = get_u16(&params, "layer").unwrap_or(0) as usize;
-            if layer >= self.params.meta_layers as usize {
-                return;
-            }
-            let base = layer * cells;
-            let end = base + cells;
-            for (offset, s) in self.meta_field[base..end].iter_mut().enumerate() {
-                if let Some(q) = target_quadrant {
-                    let x = offset % g;
-                    let y = offset / g;
-                    let qx = if x < g / 2 { 0 } else { 1 };
-                    let qy = if y < g / 2 { 0 } else { 1 };
-                    let quad = (qy * 2 + qx) as u8;
-                    if quad != q {
-                        continue;
-                    }
-                }
-                if let Some((bins, span, bin)) = target_stripe {
-                    let x = offset % g;
-                    let stripe = ((x as f32 / g as f32) * (bins as f32)).floor() as u8;
-                    let span = span.min(bins);
-                    let mut ok = false;
-                    for i in 0..span {
-                        if stripe == (bin + i) % bins {
-                            ok = true;
-                            break;
-                        }
-                    }
-                    if !ok {
-                        continue;
-                    }
-                }
-                let u = next_u32() >> 8;
-                let r = (u as f32) / ((1u32 << 24) as f32);
-                if r < frac {
-                    *s = match mode.as_str() {
-                        "zero" => 0,
-                        _ => (next_u32() % (l_s as u32 + 1)) as u8,
-                    };
-                }
-            }
-        }
-
-        if touched_base {
-            self.recompute_sum_s();
-        }
+        let report = self.apply_perturbation_spec(&spec);
+        perturb_report_object(&report)
     }
 
     #[wasm_bindgen]
@@ -729,7 +937,22 @@ impl Sim {
         let _ = Reflect::set(&o, &JsValue::from_str("eN"), &JsValue::from_f64(e_n as f64));
         let _ = Reflect::set(&o, &JsValue::from_str("eA"), &JsValue::from_f64(e_a as f64));
         let _ = Reflect::set(&o, &JsValue::from_str("eS"), &JsValue::from_f64(e_s as f64));
-        let _ = Reflect::set(&o, &JsValue::from_str("total"), &JsValue::from_f64(total as f64));
+        let e_s_lateral: f32 = (0..=self.params.meta_layers as usize).map(|l| self.s_lateral_energy(l)).sum();
+        let _ = Reflect::set(&o, &JsValue::from_str("eSLateral"), &JsValue::from_f64(e_s_lateral as f64));
+        let u_field = self.particle_field_energy();
+        let _ = Reflect::set(&o, &JsValue::from_str("uField"), &JsValue::from_f64(u_field as f64));
+        let u_species = self.species_energy_total();
+        let _ = Reflect::set(&o, &JsValue::from_str("uSpecies"), &JsValue::from_f64(u_species as f64));
+        // Inter-level coupling along the layer graph; `total` includes it and is the ledger's U.
+        let couple = self.coupling_energy_by_interface_inner();
+        let e_couple: f64 = couple.iter().sum();
+        let _ = Reflect::set(&o, &JsValue::from_str("total"), &JsValue::from_f64(total as f64 + e_couple));
+        let _ = Reflect::set(&o, &JsValue::from_str("eCouple"), &JsValue::from_f64(e_couple));
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("eCoupleByInterface"),
+            &Float64Array::from(couple.as_slice()),
+        );
         o
     }
 
@@ -824,6 +1047,22 @@ impl Sim {
 
     #[wasm_bindgen]
     pub fn set_params(&mut self, params: JsValue) {
+        // Energy changes from new parameters are protocol work; initRandom starts a new ledger.
+        let init = get_f32(&params, "initRandom").is_some_and(|v| v >= 0.5);
+        let energy_params = Reflect::own_keys(&params).map_or(true, |keys| {
+            keys.iter().any(|k| k.as_string().is_none_or(|k| !NON_ENERGY_PARAMS.contains(&k.as_str())))
+        });
+        if energy_params {
+            self.with_protocol_work(|sim| sim.apply_params(params));
+        } else {
+            self.apply_params(params);
+        }
+        if init {
+            self.ledger_reset();
+        }
+    }
+
+    fn apply_params(&mut self, params: JsValue) {
         // Accept a plain JS object with numeric fields; ignore missing fields.
         if !params.is_object() {
             return;
@@ -832,9 +1071,13 @@ impl Sim {
         let prev_p3 = self.params.p3_on;
         let prev_grid_size = self.params.grid_size;
         let prev_meta_layers = self.params.meta_layers;
+        let prev_meta_downsample = self.params.meta_downsample;
         let prev_op_on = self.params.op_coupling_on;
         let prev_op_stencil = self.params.op_stencil;
         let prev_op_budget = self.params.op_budget_k;
+        let prev_op_stencils = self.op_stencil_by_interface.clone();
+        let prev_op_budgets = self.op_budget_by_interface.clone();
+        let prev_layer_graph = self.layer_graph.clone();
         if let Some(v) = get_f32(&params, "beta") {
             if v.is_finite() && v > 0.0 {
                 self.params.beta = v;
@@ -890,6 +1133,12 @@ impl Sim {
                 self.params.mu_low = v;
             }
         }
+        self.configure_reservoirs(
+            get_u8(&params, "reservoirMode"),
+            get_f32(&params, "reservoirCapacity"),
+            get_f32(&params, "reservoirRefill"),
+            get_f32_vec(&params, "reservoirStocks"),
+        );
         if self.params.p3_on != prev_p3 {
             self.phase = 0;
             self.p3_cycle_len = 0;
@@ -928,6 +1177,63 @@ impl Sim {
                 self.params.r_star = v;
             }
         }
+        if let Ok(v) = Reflect::get(&params, &JsValue::from_str("pairPotential")) {
+            if !v.is_undefined() {
+                self.pair_potential = Potential::from_js(&v);
+            }
+        }
+        if let Ok(v) = Reflect::get(&params, &JsValue::from_str("bondPotential")) {
+            if !v.is_undefined() {
+                self.bond_potential = Potential::from_js(&v);
+            }
+        }
+        if let Some(v) = get_f32(&params, "kappaField") {
+            if v.is_finite() {
+                self.params.kappa_field = v.clamp(-10.0, 10.0);
+            }
+        }
+        if let Some(v) = get_f32(&params, "gcOn") {
+            if v.is_finite() {
+                self.params.gc_on = v >= 0.5;
+            }
+        }
+        if let Some(v) = get_f32(&params, "gcFrac") {
+            if v.is_finite() {
+                self.params.gc_frac = v.clamp(0.0, 1.0);
+            }
+        }
+        if let Some(v) = get_f32(&params, "muParticle") {
+            if v.is_finite() {
+                self.params.mu_particle = v.clamp(-50.0, 50.0);
+            }
+        }
+        if let Some(v) = get_u16(&params, "nMax") {
+            self.params.n_max = v.max(1);
+        }
+        if let Some(v) = get_f32(&params, "reactionOn") {
+            if v.is_finite() {
+                self.params.reaction_on = v >= 0.5;
+            }
+        }
+        if let Some(v) = get_f32(&params, "reactionFrac") {
+            if v.is_finite() {
+                self.params.reaction_frac = v.clamp(0.0, 1.0);
+            }
+        }
+        if let Some(v) = get_u8(&params, "reactionA") {
+            self.params.reaction_a = v;
+        }
+        if let Some(v) = get_u8(&params, "reactionB") {
+            self.params.reaction_b = v;
+        }
+        if let Some(v) = get_u8(&params, "reactionCatalyst") {
+            self.params.reaction_catalyst = v;
+        }
+        if let Some(v) = get_f32(&params, "reactionRadius") {
+            if v.is_finite() {
+                self.params.reaction_radius = v.clamp(0.0, 0.5);
+            }
+        }
         if let Some(v) = get_f32(&params, "lambdaW") {
             if v.is_finite() && v >= 0.0 {
                 self.params.lambda_w = v;
@@ -943,11 +1249,29 @@ impl Sim {
                 self.params.lambda_a = v;
             }
         }
+        if let Some(v) = get_f32(&params, "jS") {
+            if v.is_finite() {
+                self.params.j_s = v.clamp(-10.0, 10.0);
+            }
+        }
+        if let Some(v) = get_u8(&params, "sLateralMode") {
+            self.params.s_lateral_mode = v.min(1);
+        }
+        if let Some(v) = get_u8(&params, "sLateralStencil") {
+            self.params.s_lateral_stencil = v.min(1);
+        }
         if let Some(v) = get_f32(&params, "lambdaS") {
             if v.is_finite() && v >= 0.0 {
                 self.params.lambda_s = v;
+                self.lambda_s_by_level.clear();
             }
         }
+        if let Some(v) = get_f32_vec(&params, "lambdaSByLevel") {
+            self.lambda_s_by_level = v
+                .iter()
+                .map(|x| if x.is_finite() { x.max(0.0) } else { self.params.lambda_s })
+                .collect();
+        }
         if let Some(v) = get_u8(&params, "lW") {
             let new_lw = v.max(1);
             self.params.l_w = new_lw;
@@ -995,6 +1319,12 @@ impl Sim {
                 }
             }
         }
+        if let Ok(v) = Reflect::get(&params, &JsValue::from_str("species")) {
+            if !v.is_undefined() {
+                self.set_species_table(SpeciesTable::from_js(&v));
+            }
+        }
+        self.reject_species_potentials();
         if let Some(v) = get_u8(&params, "lS") {
             let new_ls = v.max(1);
             self.params.l_s = new_ls;
@@ -1022,14 +1352,41 @@ impl Sim {
             let new_layers = v.min(MAX_META_LAYERS);
             self.params.meta_layers = new_layers;
         }
+        if let Some(v) = get_u8(&params, "metaDownsample") {
+            self.params.meta_downsample = v.clamp(1, 8);
+        }
+        if let Ok(v) = Reflect::get(&params, &JsValue::from_str("layerGraph")) {
+            if !v.is_undefined() {
+                self.layer_graph = LayerEdge::parse_graph(&v);
+            }
+        }
         if let Some(v) = get_f32(&params, "eta") {
             if v.is_finite() {
                 self.params.eta = v.clamp(0.0, 1.0);
+                self.eta_by_interface.clear();
             }
         }
+        if let Some(v) = get_f32_vec(&params, "etaByInterface") {
+            self.eta_by_interface = v
+                .iter()
+                .map(|x| if x.is_finite() { x.clamp(0.0, 1.0) } else { self.params.eta })
+                .collect();
+        }
         if let Some(v) = get_f32(&params, "etaDrive") {
             if v.is_finite() {
                 self.params.eta_drive = v.clamp(0.0, 1.0);
+                self.eta_drive_by_interface.clear();
+            }
+        }
+        if let Some(v) = get_f32_vec(&params, "etaDriveByInterface") {
+            self.eta_drive_by_interface = v
+                .iter()
+                .map(|x| if x.is_finite() { x.clamp(0.0, 1.0) } else { self.params.eta_drive })
+                .collect();
+        }
+        if let Some(v) = get_f32(&params, "depositCouplingOn") {
+            if v.is_finite() {
+                self.params.deposit_coupling_on = v >= 0.5;
             }
         }
         if let Some(v) = get_f32(&params, "opCouplingOn") {
@@ -1039,15 +1396,31 @@ impl Sim {
         }
         if let Some(v) = get_u8(&params, "opStencil") {
             self.params.op_stencil = v.min(1);
+            self.op_stencil_by_interface.clear();
+        }
+        if let Some(v) = get_u8_vec(&params, "opStencilByInterface") {
+            self.op_stencil_by_interface = v.iter().map(|x| (*x).min(1)).collect();
         }
         if let Some(v) = get_u8(&params, "opBudgetK") {
             self.params.op_budget_k = v.max(1);
+            self.op_budget_by_interface.clear();
+        }
+        if let Some(v) = get_u8_vec(&params, "opBudgetKByInterface") {
+            self.op_budget_by_interface = v.iter().map(|x| (*x).max(1)).collect();
         }
         if let Some(v) = get_f32(&params, "opKTargetWeight") {
             if v.is_finite() {
                 self.params.op_k_target_weight = v.clamp(0.0, 10.0);
             }
         }
+        if let Some(v) = get_f32(&params, "opMotifOn") {
+            if v.is_finite() {
+                self.params.op_motif_on = v >= 0.5;
+            }
+        }
+        if let Some(v) = get_u8(&params, "opMotifMode") {
+            self.params.op_motif_mode = v.min(2);
+        }
         if let Some(v) = get_u8(&params, "sCouplingMode") {
             self.params.s_coupling_mode = v.min(1);
         }
@@ -1072,16 +1445,27 @@ impl Sim {
                 self.params.ep_debug = v >= 0.5;
             }
         }
+        if let Some(v) = get_u8(&params, "acceptRule") {
+            self.params.accept_rule = [v.min(accept_rule::ACCEPT_HEAT_BATH); MOVE_KIND_COUNT];
+        } else if let Some(v) = get_u8_vec(&params, "acceptRule") {
+            for (rule, value) in self.params.accept_rule.iter_mut().zip(v) {
+                *rule = value.min(accept_rule::ACCEPT_HEAT_BATH);
+            }
+        }
         if let Some(v) = get_f32(&params, "initRandom") {
             if v.is_finite() {
                 let on = v >= 0.5;
                 self.params.init_random = on;
                 if on {
                     self.randomize_state();
+                    self.rebase_deadline_ep();
                     self.ep_naive_total = 0.0;
                     self.ep_exact_total = 0.0;
                     self.ep_naive_by_move = [0.0; MOVE_KIND_COUNT];
                     self.ep_exact_by_move = [0.0; MOVE_KIND_COUNT];
+                    self.intervention_energy_total = 0.0;
+                    self.intervention_count = 0;
+                    self.code_noise_stats = CodeNoiseStats::default();
                 }
             }
         }
@@ -1097,6 +1481,29 @@ impl Sim {
         if let Some(v) = get_u16(&params, "codeNoiseLayer") {
             self.params.code_noise_layer = v;
         }
+        if let Some(v) = get_u8(&params, "codeNoiseModel") {
+            self.params.code_noise_model = v.min(noise::NOISE_MODEL_FLIP);
+        }
+        if let Some(v) = get_u8(&params, "codeNoiseBurst") {
+            self.params.code_noise_burst = v.min(8);
+        }
+        if let Some(v) = get_f32_vec(&params, "codeNoiseRates") {
+            self.code_noise_rates = v
+                .iter()
+                .take(MAX_S_LEVELS)
+                .map(|r| if r.is_finite() { r.clamp(0.0, 1.0) } else { 0.0 })
+                .collect();
+        }
+        if let Ok(v) = Reflect::get(&params, &JsValue::from_str("codeEncoding")) {
+            if let Some(enc) = LogicalEncoding::from_js(&v, self.params.clock_k) {
+                self.code_encoding = enc;
+            }
+        }
+        if let Some(v) = get_f32(&params, "codeNoiseOpKRate") {
+            if v.is_finite() {
+                self.params.code_noise_opk_rate = v.clamp(0.0, 1.0);
+            }
+        }
         if let Some(v) = get_f32(&params, "clockOn") {
             if v.is_finite() {
                 self.params.clock_on = v >= 0.5;
@@ -1125,7 +1532,28 @@ impl Sim {
             }
         }
         if let Some(v) = get_u8(&params, "repairGateMode") {
-            self.params.repair_gate_mode = v.min(1);
+            self.params.repair_gate_mode = v.min(2);
+        }
+        if let Ok(v) = Reflect::get(&params, &JsValue::from_str("repairGateRegions")) {
+            if Array::is_array(&v) {
+                let mut regions = Vec::new();
+                for item in Array::from(&v).iter() {
+                    if let Some(region) = RegionMask::from_js(&item, self.params.clock_k) {
+                        regions.push(region);
+                    }
+                }
+                self.repair_gate_regions = regions;
+                if !self.repair_gate_regions.is_empty() {
+                    self.params.repair_gate_mode = 2;
+                }
+            }
+        }
+        if let Some(region) = RegionMask::from_params(&params, "codeNoiseRegion", self.params.clock_k) {
+            self.code_noise_region = if region == RegionMask::All {
+                None
+            } else {
+                Some(region)
+            };
         }
         if let Some(v) = get_u8(&params, "repairGateSpan") {
             self.params.repair_gate_span = v.max(1);
@@ -1133,19 +1561,33 @@ impl Sim {
         if self.params.s_coupling_mode > 0 && !self.params.op_coupling_on {
             self.params.s_coupling_mode = 0;
         }
-        if self.params.grid_size != prev_grid_size || self.params.meta_layers != prev_meta_layers {
+        let layers = self.params.meta_layers as usize;
+        self.layer_graph.retain(|edge| edge.upper <= layers);
+        let meta_shape_changed = self.params.grid_size != prev_grid_size
+            || self.params.meta_layers != prev_meta_layers
+            || self.params.meta_downsample != prev_meta_downsample;
+        if meta_shape_changed {
             self.resize_meta_arrays();
         }
+        self.code_noise_cells = match &self.code_noise_region {
+            Some(region) => region.cells(self.params.grid_size as usize),
+            None => Vec::new(),
+        };
         if !self.params.op_coupling_on || self.params.meta_layers == 0 {
             self.op_k.clear();
-        } else if self.params.grid_size != prev_grid_size
-            || self.params.meta_layers != prev_meta_layers
+        } else if meta_shape_changed
             || prev_op_on != self.params.op_coupling_on
             || prev_op_stencil != self.params.op_stencil
             || prev_op_budget != self.params.op_budget_k
+            || prev_op_stencils != self.op_stencil_by_interface
+            || prev_op_budgets != self.op_budget_by_interface
+            || prev_layer_graph != self.layer_graph
         {
             self.init_op_k();
         }
+        self.refresh_op_motifs();
+        self.recompute_deposit_bins();
+        self.recompute_field_counts();
         if let Some(v) = get_f32(&params, "rPropose") {
             if v.is_finite() && v >= 0.0 && v <= 0.5 {
                 self.params.r_propose = v;
@@ -1163,183 +1605,466 @@ impl Sim {
         self.sum_s = self.s_field.iter().map(|s| *s as i32).sum();
     }
 
-    fn resize_meta_arrays(&mut self) {
-        let layers = self.params.meta_layers as usize;
-        if layers == 0 {
-            self.meta_field.clear();
-            self.meta_n_field.clear();
-            self.meta_a_field.clear();
-            self.meta_w_edges.clear();
-            return;
+    fn s_level(&self, level: usize) -> &[u8] {
+        if level == 0 {
+            &self.s_field
+        } else {
+            &self.meta_field[self.meta_offset(level - 1)..self.meta_offset(level)]
         }
-        let g = self.params.grid_size as usize;
-        let cells = layers * g * g;
-        self.meta_field = vec![0u8; cells];
-        self.meta_n_field = vec![0i16; cells];
-        self.meta_a_field = vec![0u16; cells];
-        self.meta_w_edges = vec![0u8; layers * meta_edge_count(g)];
     }
 
-    fn op_offsets_internal(&self) -> &'static [(i32, i32)] {
-        if self.params.op_stencil == 1 {
-            &OP_STENCIL_FULL
+    fn s_level_mut(&mut self, level: usize) -> &mut [u8] {
+        if level == 0 {
+            &mut self.s_field
         } else {
-            &OP_STENCIL_CROSS
+            let (start, end) = (self.meta_offset(level - 1), self.meta_offset(level));
+            &mut self.meta_field[start..end]
         }
     }
 
-    fn op_r_count_internal(&self) -> usize {
-        self.op_offsets_internal().len()
-    }
-
-    fn op_k_index(&self, interface: usize, q: usize, r_idx: usize) -> usize {
+    // Grid side at `level` (0 = base). With metaDownsample f > 1 every meta level is f times
+    // coarser than the level below, down to a single cell.
+    fn level_grid(&self, level: usize) -> usize {
         let g = self.params.grid_size as usize;
-        let cells = g * g;
-        let r_count = self.op_r_count_internal();
-        ((interface * cells + q) * r_count) + r_idx
-    }
-
-    fn mismatch_bin(upper: u8, lower: u8) -> u8 {
-        let diff = (upper as i16) - (lower as i16);
-        if diff < 0 {
-            0
-        } else if diff == 0 {
-            1
-        } else {
-            2
+        let f = self.params.meta_downsample as usize;
+        if f <= 1 {
+            return g;
         }
+        (0..level).fold(g, |side, _| (side / f).max(1))
     }
 
-    fn op_k_dir(&self, interface: usize, q: usize) -> u8 {
-        if self.op_k.is_empty() {
-            return 0;
+    // Per-step bookkeeping after a move: code noise, deadline checks, lenses and the
+    // idempotence series. `step` runs it once per step and `kmc_advance` once per event.
+    fn run_step_hooks(&mut self) {
+        self.maybe_code_noise();
+        if !self.deadline_tasks.is_empty() {
+            self.check_deadline_tasks();
         }
-        if interface >= self.params.meta_layers as usize {
-            return 0;
-        }
-        let r_count = self.op_r_count_internal();
-        if r_count == 0 || r_count > u8::MAX as usize {
-            // Stencil sizes should be small; clamp to 0 if out of range.
-            return 0;
+        if !self.lenses.is_empty() {
+            self.update_lenses();
         }
-        let mut best_idx = 0usize;
-        let mut best_val = 0u8;
-        for r_idx in 0..r_count {
-            let idx = self.op_k_index(interface, q, r_idx);
-            let val = self.op_k[idx];
-            if val > best_val {
-                best_val = val;
-                best_idx = r_idx;
-            }
+        if self.idempotence_series.is_some() {
+            self.record_idempotence_series();
         }
-        best_idx as u8
     }
 
-    fn offset_index(q: usize, dx: i32, dy: i32, g: usize) -> usize {
-        let x = (q % g) as i32;
-        let y = (q / g) as i32;
-        let nx = (x + dx).rem_euclid(g as i32) as usize;
-        let ny = (y + dy).rem_euclid(g as i32) as usize;
-        ny * g + nx
+    fn level_cells(&self, level: usize) -> usize {
+        let side = self.level_grid(level);
+        side * side
     }
 
-    fn op_lower_norm(&self, interface: usize, q: usize) -> f32 {
-        let denom = self.params.l_s.max(1) as f32;
-        let val = if interface == 0 {
-            self.s_field[q]
-        } else {
+    // Start of meta layer `layer` in the concatenated meta arrays; op_k and op-motif arrays use
+    // the same cell offsets since interface i lives on the grid of meta layer i.
+    fn meta_offset(&self, layer: usize) -> usize {
+        if self.params.meta_downsample <= 1 {
             let g = self.params.grid_size as usize;
-            let cells = g * g;
-            self.meta_field[(interface - 1) * cells + q]
-        };
-        (val as f32) / denom
+            return layer * g * g;
+        }
+        (1..=layer).map(|level| self.level_cells(level)).sum()
     }
 
-    fn op_upper_norm(&self, interface: usize, q: usize) -> f32 {
-        let denom = self.params.l_s.max(1) as f32;
-        let g = self.params.grid_size as usize;
-        let cells = g * g;
-        let val = self.meta_field[interface * cells + q];
-        (val as f32) / denom
+    fn meta_edge_offset(&self, layer: usize) -> usize {
+        2 * self.meta_offset(layer)
     }
 
-    fn op_pred_norm(&self, interface: usize, q: usize) -> f32 {
-        let g = self.params.grid_size as usize;
-        let cells = g * g;
-        if self.op_k.is_empty() || interface >= self.params.meta_layers as usize || q >= cells {
-            return 0.0;
+    // Cell of `level` containing cell `idx` of the finer level `from`.
+    fn ancestor_cell(&self, idx: usize, from: usize, level: usize) -> usize {
+        let mut idx = idx;
+        for l in from..level {
+            idx = coarse_cell(idx, self.level_grid(l), self.level_grid(l + 1));
         }
-        let budget = self.params.op_budget_k as f32;
-        if budget <= 0.0 {
-            return 0.0;
+        idx
+    }
+
+    // Coordinates of `lower` covered by cell `q` of the coarser (or equal) level `upper`,
+    // as (x range, y range); composes `block_span` down the levels in between.
+    fn level_block(&self, lower: usize, upper: usize, q: usize) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let side = self.level_grid(upper);
+        let (mut xs, mut ys) = (q % side..q % side + 1, q / side..q / side + 1);
+        for l in (lower..upper).rev() {
+            let (fine, coarse) = (self.level_grid(l), self.level_grid(l + 1));
+            xs = block_span(xs.start, fine, coarse).start..block_span(xs.end - 1, fine, coarse).end;
+            ys = block_span(ys.start, fine, coarse).start..block_span(ys.end - 1, fine, coarse).end;
         }
-        let mut acc = 0.0;
-        for (r_idx, (dx, dy)) in self.op_offsets_internal().iter().enumerate() {
-            let q_off = Self::offset_index(q, *dx, *dy, g);
-            let k_idx = self.op_k_index(interface, q, r_idx);
-            let weight = (self.op_k[k_idx] as f32) / budget;
-            acc += weight * self.op_lower_norm(interface, q_off);
+        (xs, ys)
+    }
+
+    // Sum and cell count of a grid-shaped `field` of `lower` under cell `q` of `upper`.
+    fn level_block_sum<T: Copy + Into<i32>>(&self, field: &[T], lower: usize, upper: usize, q: usize) -> (i32, usize) {
+        if upper == lower + 1 {
+            return block_sum(field, self.level_grid(lower), self.level_grid(upper), q);
         }
-        acc
+        let g = self.level_grid(lower);
+        let (xs, ys) = self.level_block(lower, upper, q);
+        let count = xs.len() * ys.len();
+        let sum = ys.flat_map(|y| xs.clone().map(move |x| y * g + x)).map(|i| field[i].into()).sum();
+        (sum, count)
     }
 
-    fn delta_raw_s_op(&self, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
-        if !self.params.op_coupling_on || self.params.s_coupling_mode == 0 {
-            return 0.0;
+    // Sum and cell count of `lower` under cell `q` of `upper`.
+    fn s_block_sum(&self, lower: usize, upper: usize, q: usize) -> (i32, usize) {
+        self.level_block_sum(self.s_level(lower), lower, upper, q)
+    }
+
+    // Rounded block mean of `lower` under cell `q` of `upper` (the cell itself if unscaled).
+    fn s_block_mean(&self, lower: usize, upper: usize, q: usize) -> u8 {
+        let (sum, count) = self.s_block_sum(lower, upper, q);
+        ((sum as f32) / (count as f32)).round() as u8
+    }
+
+    // `level` drawn on the base grid, each base cell showing the cell of `level` above it.
+    fn s_level_lifted(&self, level: usize) -> Cow<'_, [u8]> {
+        let field = self.s_level(level);
+        if self.level_grid(level) == self.params.grid_size as usize {
+            return Cow::Borrowed(field);
         }
-        let layers = self.params.meta_layers as usize;
+        let cells = self.level_cells(0);
+        Cow::Owned((0..cells).map(|idx| field[self.ancestor_cell(idx, 0, level)]).collect())
+    }
+
+    // Base-grid values block-averaged (rounded) onto the grid of `level`.
+    fn project_from_base(&self, values: &[u8], level: usize) -> Vec<u8> {
+        if self.level_grid(level) == self.params.grid_size as usize {
+            return values.to_vec();
+        }
+        let mut sums = vec![(0u32, 0u32); self.level_cells(level)];
+        for (idx, v) in values.iter().enumerate() {
+            let bin = &mut sums[self.ancestor_cell(idx, 0, level)];
+            bin.0 += *v as u32;
+            bin.1 += 1;
+        }
+        sums.iter()
+            .map(|(sum, count)| ((*sum as f32) / (*count).max(1) as f32).round() as u8)
+            .collect()
+    }
+
+    // Copy source resampled onto the grid of the target `level`; explicit `values` may be
+    // given on either the target grid or the base grid.
+    fn perturb_copy_source(&self, params: &JsValue, level: usize) -> Option<Vec<u8>> {
+        if let Some(values) = get_u8_vec(params, "values") {
+            if values.len() == self.level_cells(level) {
+                return Some(values);
+            }
+            if values.len() != self.level_cells(0) {
+                return None;
+            }
+            return Some(self.project_from_base(&values, level));
+        }
+        let from = get_string(params, "from")?;
+        let from_level = if from == "baseS" {
+            0
+        } else if from == "metaS" {
+            let layer = get_u16(params, "fromLayer").unwrap_or(0) as usize;
+            if layer >= self.params.meta_layers as usize {
+                return None;
+            }
+            layer + 1
+        } else {
+            return None;
+        };
+        Some(self.project_from_base(&self.s_level_lifted(from_level), level))
+    }
+
+    fn apply_perturbation_spec(&mut self, spec: &PerturbSpec) -> PerturbReport {
+        let mut report = PerturbReport::default();
+        if spec.level > self.params.meta_layers as usize
+            || (spec.level > 0 && self.meta_field.len() < self.meta_offset(spec.level))
+        {
+            return report;
+        }
+        let g = self.level_grid(spec.level);
+        let cells = g * g;
+        let l_s = self.params.l_s;
+        let field = self.s_level(spec.level);
+        let source: &[u8] = match (&spec.mode, &spec.source) {
+            (PerturbMode::CopyFrom, Some(src)) if src.len() == cells => src,
+            (PerturbMode::CopyFrom, _) => return report,
+            // Shift reads the pre-perturbation field, so translated values never read back writes.
+            (PerturbMode::Shift(_, _), _) => field,
+            _ => &[],
+        };
+        let mut rng = spec.seed;
+        let mut next_u32 = || {
+            // xorshift32
+            let mut x = rng;
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            rng = x;
+            x
+        };
+        let mut writes: Vec<(usize, u8)> = Vec::new();
+        for (idx, s) in field.iter().enumerate() {
+            if !spec.region.contains_cell(idx, g) {
+                continue;
+            }
+            let u = next_u32() >> 8;
+            let r = (u as f32) / ((1u32 << 24) as f32);
+            if r >= spec.frac {
+                continue;
+            }
+            let s1 = match spec.mode {
+                PerturbMode::Randomize => (next_u32() % (l_s as u32 + 1)) as u8,
+                PerturbMode::Zero => 0,
+                PerturbMode::Set(v) => v.min(l_s),
+                PerturbMode::Invert => l_s - (*s).min(l_s),
+                PerturbMode::Bias(k) => ((*s as i32) + (k as i32)).clamp(0, l_s as i32) as u8,
+                PerturbMode::Shift(dx, dy) => source[Self::offset_index(idx, -dx, -dy, g)].min(l_s),
+                PerturbMode::CopyFrom => source[idx].min(l_s),
+            };
+            writes.push((idx, s1));
+        }
+        // Apply cell by cell so the summed local ΔE equals the exact total energy change.
+        let mut abs_sum = 0.0f64;
+        for (idx, s1) in writes {
+            let s0 = self.s_level(spec.level)[idx];
+            report.indices.push(idx as u32);
+            if s0 == s1 {
+                continue;
+            }
+            report.energy_delta += self.delta_e_s_level(spec.level, idx, s0, s1) as f64;
+            self.s_level_mut(spec.level)[idx] = s1;
+            report.changed += 1;
+            abs_sum += ((s1 as i32) - (s0 as i32)).unsigned_abs() as f64;
+        }
+        if report.changed > 0 {
+            report.mean_abs_change = abs_sum / (report.changed as f64);
+        }
+        if spec.level == 0 {
+            self.recompute_sum_s();
+        }
+        self.intervention_energy_total += report.energy_delta;
+        self.intervention_count = self.intervention_count.saturating_add(1);
+        report
+    }
+
+    fn resize_meta_arrays(&mut self) {
+        let layers = self.params.meta_layers as usize;
         if layers == 0 {
+            self.meta_field.clear();
+            self.meta_n_field.clear();
+            self.meta_a_field.clear();
+            self.meta_w_edges.clear();
+            return;
+        }
+        let cells = self.meta_offset(layers);
+        self.meta_field = vec![0u8; cells];
+        self.meta_n_field = vec![0i16; cells];
+        self.meta_a_field = vec![0u16; cells];
+        self.meta_w_edges = vec![0u8; self.meta_edge_offset(layers)];
+    }
+
+    // Token storage stride: the full stencil if any interface uses it. The cross stencil is
+    // a prefix of the full one, so cross interfaces simply leave the diagonal slots empty.
+    fn op_offsets_internal(&self) -> &'static [(i32, i32)] {
+        if (0..self.interface_count().max(1)).any(|i| self.op_stencil_at(i) == 1) {
+            &OP_STENCIL_FULL
+        } else {
+            &OP_STENCIL_CROSS
+        }
+    }
+
+    fn op_r_count_internal(&self) -> usize {
+        self.op_offsets_internal().len()
+    }
+
+    fn op_offsets_at(&self, interface: usize) -> &'static [(i32, i32)] {
+        if self.op_stencil_at(interface) == 1 {
+            &OP_STENCIL_FULL
+        } else {
+            &OP_STENCIL_CROSS
+        }
+    }
+
+    fn op_r_count_at(&self, interface: usize) -> usize {
+        self.op_offsets_at(interface).len()
+    }
+
+    // A layer-graph edge's own coupling wins over the per-interface vector and the scalar.
+    fn eta_at(&self, interface: usize) -> f32 {
+        self.layer_graph
+            .get(interface)
+            .and_then(|edge| edge.eta)
+            .or_else(|| self.eta_by_interface.get(interface).copied())
+            .unwrap_or(self.params.eta)
+    }
+
+    fn eta_drive_at(&self, interface: usize) -> f32 {
+        self.layer_graph
+            .get(interface)
+            .and_then(|edge| edge.eta_drive)
+            .or_else(|| self.eta_drive_by_interface.get(interface).copied())
+            .unwrap_or(self.params.eta_drive)
+    }
+
+    fn op_budget_at(&self, interface: usize) -> u8 {
+        self.op_budget_by_interface
+            .get(interface)
+            .copied()
+            .unwrap_or(self.params.op_budget_k)
+    }
+
+    fn op_stencil_at(&self, interface: usize) -> u8 {
+        self.op_stencil_by_interface
+            .get(interface)
+            .copied()
+            .unwrap_or(self.params.op_stencil)
+    }
+
+    fn lambda_s_at(&self, level: usize) -> f32 {
+        self.lambda_s_by_level.get(level).copied().unwrap_or(self.params.lambda_s)
+    }
+
+    fn op_k_index(&self, interface: usize, q: usize, r_idx: usize) -> usize {
+        let r_count = self.op_r_count_internal();
+        ((self.op_offset(interface) + q) * r_count) + r_idx
+    }
+
+    fn mismatch_bin(upper: u8, lower: u8) -> u8 {
+        let diff = (upper as i16) - (lower as i16);
+        if diff < 0 {
+            0
+        } else if diff == 0 {
+            1
+        } else {
+            2
+        }
+    }
+
+    fn op_k_dir(&self, interface: usize, q: usize) -> u8 {
+        if self.op_k.is_empty() {
+            return 0;
+        }
+        if interface >= self.interface_count() || !self.interface_has_op(interface) {
+            return 0;
+        }
+        let r_count = self.op_r_count_internal();
+        if r_count == 0 || r_count > u8::MAX as usize {
+            // Stencil sizes should be small; clamp to 0 if out of range.
+            return 0;
+        }
+        let mut best_idx = 0usize;
+        let mut best_val = 0u8;
+        for r_idx in 0..r_count {
+            let idx = self.op_k_index(interface, q, r_idx);
+            let val = self.op_k[idx];
+            if val > best_val {
+                best_val = val;
+                best_idx = r_idx;
+            }
+        }
+        best_idx as u8
+    }
+
+    fn offset_index(q: usize, dx: i32, dy: i32, g: usize) -> usize {
+        let x = (q % g) as i32;
+        let y = (q / g) as i32;
+        let nx = (x + dx).rem_euclid(g as i32) as usize;
+        let ny = (y + dy).rem_euclid(g as i32) as usize;
+        ny * g + nx
+    }
+
+    // Lower level seen from cell `q` of the interface grid: the block mean under `q`.
+    fn op_lower_norm(&self, interface: usize, q: usize) -> f32 {
+        let denom = self.params.l_s.max(1) as f32;
+        let (lower, upper) = self.interface_levels(interface);
+        let (sum, count) = self.s_block_sum(lower, upper, q);
+        (sum as f32) / (count as f32) / denom
+    }
+
+    fn op_upper_norm(&self, interface: usize, q: usize) -> f32 {
+        let denom = self.params.l_s.max(1) as f32;
+        let val = self.s_level(self.interface_levels(interface).1)[q];
+        (val as f32) / denom
+    }
+
+    // Stencils act on the grid of the upper level, reading block means of the lower one.
+    fn op_pred_norm(&self, interface: usize, q: usize) -> f32 {
+        if self.op_k.is_empty() || interface >= self.interface_count() || !self.interface_has_op(interface) {
             return 0.0;
         }
-        let g = self.params.grid_size as usize;
-        if g == 0 {
+        let g = self.level_grid(self.interface_levels(interface).1);
+        if q >= g * g {
+            return 0.0;
+        }
+        let budget = self.op_budget_at(interface) as f32;
+        if budget <= 0.0 {
+            return 0.0;
+        }
+        let mut acc = 0.0;
+        for (r_idx, (dx, dy)) in self.op_offsets_at(interface).iter().enumerate() {
+            let q_off = Self::offset_index(q, *dx, *dy, g);
+            let k_idx = self.op_k_index(interface, q, r_idx);
+            let weight = (self.op_k[k_idx] as f32) / budget;
+            acc += weight * self.op_lower_norm(interface, q_off);
+        }
+        acc
+    }
+
+    // Raw op-K mismatch change on one interface when cell `idx` of `level` (either end of the
+    // interface) moves from s0 to s1.
+    fn delta_raw_s_op_edge(&self, interface: usize, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
+        if !self.params.op_coupling_on || self.op_k.is_empty() || !self.interface_has_op(interface) {
             return 0.0;
         }
         let denom = self.params.l_s.max(1) as f32;
         let s0n = (s0 as f32) / denom;
         let s1n = (s1 as f32) / denom;
-        let mut delta = 0.0;
-        if level > 0 {
-            let interface = level - 1;
-            if interface < layers {
-                let pred = self.op_pred_norm(interface, idx);
-                delta += 0.5 * ((s1n - pred).powi(2) - (s0n - pred).powi(2));
+        let (lower, upper) = self.interface_levels(interface);
+        if level == upper {
+            let pred = self.op_pred_norm(interface, idx);
+            return 0.5 * ((s1n - pred).powi(2) - (s0n - pred).powi(2));
+        }
+        let budget = self.op_budget_at(interface) as f32;
+        // The cell moves the block mean of its ancestor `p` by 1/count of its own change.
+        let coarse = self.level_grid(upper);
+        let p = self.ancestor_cell(idx, lower, upper);
+        let (xs, ys) = self.level_block(lower, upper, p);
+        let step = (s1n - s0n) / (xs.len() * ys.len()) as f32;
+        // On grids narrower than the stencil several offsets wrap onto the same cell, so
+        // weights are gathered per affected cell before its prediction is updated.
+        let mut affected = [(0usize, 0.0f32); OP_STENCIL_FULL.len()];
+        let mut len = 0;
+        for (r_idx, (dx, dy)) in self.op_offsets_at(interface).iter().enumerate() {
+            let q_prime = Self::offset_index(p, -*dx, -*dy, coarse);
+            let k_idx = self.op_k_index(interface, q_prime, r_idx);
+            let weight = (self.op_k[k_idx] as f32) / budget;
+            match affected[..len].iter_mut().find(|(q, _)| *q == q_prime) {
+                Some((_, w)) => *w += weight,
+                None => {
+                    affected[len] = (q_prime, weight);
+                    len += 1;
+                }
             }
         }
-        if level < layers {
-            let interface = level;
-            for (r_idx, (dx, dy)) in self.op_offsets_internal().iter().enumerate() {
-                let q_prime = Self::offset_index(idx, -*dx, -*dy, g);
-                let pred_old = self.op_pred_norm(interface, q_prime);
-                let upper = self.op_upper_norm(interface, q_prime);
-                let k_idx = self.op_k_index(interface, q_prime, r_idx);
-                let weight = (self.op_k[k_idx] as f32) / (self.params.op_budget_k as f32);
-                let pred_new = pred_old + weight * (s1n - s0n);
-                delta += 0.5 * ((upper - pred_new).powi(2) - (upper - pred_old).powi(2));
-            }
+        let mut above = 0.0;
+        for &(q_prime, weight) in &affected[..len] {
+            let pred_old = self.op_pred_norm(interface, q_prime);
+            let upper = self.op_upper_norm(interface, q_prime);
+            let pred_new = pred_old + weight * step;
+            above += 0.5 * ((upper - pred_new).powi(2) - (upper - pred_old).powi(2));
         }
-        delta
+        above
     }
 
     fn delta_raw_k_op(&self, interface: usize, q: usize, r_from: usize, r_to: usize) -> f32 {
         if !self.params.op_coupling_on || self.params.s_coupling_mode == 0 {
             return 0.0;
         }
-        let layers = self.params.meta_layers as usize;
-        if layers == 0 || interface >= layers {
+        if interface >= self.interface_count() || !self.interface_has_op(interface) {
             return 0.0;
         }
-        let g = self.params.grid_size as usize;
+        let g = self.level_grid(self.interface_levels(interface).1);
         if g == 0 {
             return 0.0;
         }
-        let budget = self.params.op_budget_k as f32;
+        let budget = self.op_budget_at(interface) as f32;
         if budget <= 0.0 {
             return 0.0;
         }
         let pred_old = self.op_pred_norm(interface, q);
         let upper = self.op_upper_norm(interface, q);
-        let offsets = self.op_offsets_internal();
+        let offsets = self.op_offsets_at(interface);
         let (dx_from, dy_from) = offsets[r_from];
         let (dx_to, dy_to) = offsets[r_to];
         let lower_from = self.op_lower_norm(interface, Self::offset_index(q, dx_from, dy_from, g));
@@ -1353,21 +2078,24 @@ impl Sim {
             self.op_k.clear();
             return;
         }
-        let layers = self.params.meta_layers as usize;
-        let g = self.params.grid_size as usize;
-        let cells = g * g;
-        let r_count = self.op_r_count_internal();
-        let budget = self.params.op_budget_k;
-        if r_count == 0 || budget == 0 {
+        let interfaces = self.interface_count();
+        let stride = self.op_r_count_internal();
+        if stride == 0 || (0..interfaces).any(|i| self.interface_has_op(i) && self.op_budget_at(i) == 0) {
             self.op_k.clear();
             return;
         }
-        let base = budget / (r_count as u8);
-        let rem = (budget % (r_count as u8)) as usize;
-        self.op_k = vec![0u8; layers * cells * r_count];
-        for interface in 0..layers {
-            for q in 0..cells {
-                let start = (interface * cells + q) * r_count;
+        self.op_k = vec![0u8; self.op_offset(interfaces) * stride];
+        for interface in 0..interfaces {
+            if !self.interface_has_op(interface) {
+                continue;
+            }
+            let r_count = self.op_r_count_at(interface);
+            let budget = self.op_budget_at(interface);
+            let base = budget / (r_count as u8);
+            let rem = (budget % (r_count as u8)) as usize;
+            let offset = self.op_offset(interface);
+            for q in 0..self.op_cells(interface) {
+                let start = (offset + q) * stride;
                 for r in 0..r_count {
                     let mut val = base;
                     if r < rem {
@@ -1400,9 +2128,14 @@ impl Sim {
         let l_a = self.params.l_a as usize;
         let l_n = self.params.l_n.max(1) as i32;
 
-        let weights_s: Vec<f64> = (0..=l_s)
-            .map(|v| (-0.5 * beta * (self.params.lambda_s as f64) * (v as f64).powi(2)).exp())
-            .collect();
+        let weights_s_at = |level: usize| -> Vec<f64> {
+            let lambda_s = self.lambda_s_at(level) as f64;
+            (0..=l_s)
+                .map(|v| (-0.5 * beta * lambda_s * (v as f64).powi(2)).exp())
+                .collect()
+        };
+        let weights_s = weights_s_at(0);
+        let weights_s_meta: Vec<Vec<f64>> = (1..=self.params.meta_layers as usize).map(weights_s_at).collect();
         let weights_a: Vec<f64> = (0..=l_a)
             .map(|v| (-0.5 * beta * (self.params.lambda_a as f64) * (v as f64).powi(2)).exp())
             .collect();
@@ -1439,9 +2172,13 @@ impl Sim {
                         self.positions[2 * j],
                         self.positions[2 * j + 1],
                     );
-                    let bond_shape =
-                        0.5 * (self.params.kappa_bond as f64) * (r - self.params.r_star).powi(2) as f64;
-                    let weights = if bond_shape > 0.0 {
+                    if !self.bond_allowed(i, j) {
+                        self.w[idx] = 0;
+                        idx += 1;
+                        continue;
+                    }
+                    let bond_shape = self.bond_shape(i, j, r) as f64;
+                    let weights = if bond_shape != 0.0 {
                         let mut local = Vec::with_capacity(l_w + 1);
                         for v in 0..=l_w {
                             let vf = v as f64;
@@ -1458,19 +2195,24 @@ impl Sim {
                 }
             }
         }
-        for n in &mut self.n_counter {
-            let idx = sample_index(&weights_n, rand01());
-            *n = (idx as i16) - (l_n as i16);
+        // Species caps truncate the counter distributions to their own range.
+        let caps: Vec<(usize, i32)> = (0..self.n).map(|k| (self.l_a_of(k) as usize, self.l_n_of(k) as i32)).collect();
+        for (n, (_, cap)) in self.n_counter.iter_mut().zip(&caps) {
+            let lo = (l_n - cap) as usize;
+            let idx = sample_index(&weights_n[lo..=(l_n + cap) as usize], rand01());
+            *n = (idx as i16) - (*cap as i16);
         }
-        for a in &mut self.a_counter {
-            *a = sample_index(&weights_a, rand01()) as u16;
+        for (a, (cap, _)) in self.a_counter.iter_mut().zip(&caps) {
+            *a = sample_index(&weights_a[..=*cap], rand01()) as u16;
         }
         for s in &mut self.s_field {
             *s = sample_index(&weights_s, rand01()) as u8;
         }
         if self.params.meta_layers > 0 {
-            for s in &mut self.meta_field {
-                *s = sample_index(&weights_s, rand01()) as u8;
+            for (layer, weights) in weights_s_meta.iter().enumerate() {
+                for s in self.s_level_mut(layer + 1) {
+                    *s = sample_index(weights, rand01()) as u8;
+                }
             }
             for n in &mut self.meta_n_field {
                 let idx = sample_index(&weights_n, rand01());
@@ -1488,9 +2230,12 @@ impl Sim {
         } else {
             self.op_k.clear();
         }
+        self.refresh_op_motifs();
         self.rng = rng;
         self.recompute_sum_w();
         self.recompute_sum_s();
+        self.recompute_deposit_bins();
+        self.recompute_field_counts();
     }
 
     fn pick_write_target(&mut self) -> usize {
@@ -1500,17 +2245,18 @@ impl Sim {
 
     fn pick_p5_target_op(&mut self) -> usize {
         let layers = self.params.meta_layers as u32;
+        let interfaces = self.interface_count() as u32;
         let weight = self.params.op_k_target_weight;
         if (weight - 1.0).abs() < 1e-6 {
-            let total = 1 + (2 * layers);
+            let total = 1 + layers + interfaces;
             (self.rand_u32() % total) as usize
         } else {
             let layers_usize = layers as usize;
-            if layers_usize == 0 {
+            if layers_usize == 0 || interfaces == 0 {
                 return 0;
             }
             let s_weight = (layers_usize + 1) as f32;
-            let op_weight = (layers_usize as f32) * weight.max(0.0);
+            let op_weight = (interfaces as f32) * weight.max(0.0);
             let total = s_weight + op_weight;
             if total <= 0.0 {
                 return 0;
@@ -1519,7 +2265,7 @@ impl Sim {
             if r < s_weight {
                 (self.rand_u32() % (layers + 1)) as usize
             } else {
-                let idx = (self.rand_u32() % layers) as usize;
+                let idx = (self.rand_u32() % interfaces) as usize;
                 (layers_usize + 1) + idx
             }
         }
@@ -1542,10 +2288,35 @@ impl Sim {
     }
 
     fn clock_step(&mut self) -> bool {
-        let k = self.params.clock_k.max(3);
         let up = self.rand01() < 0.5;
+        let Some(eval) = self.clock_eval(up) else {
+            return false;
+        };
+        if self.accept_move(0.0, eval.work, 0.0, MOVE_CLOCK) {
+            self.clock_commit(up);
+            return true;
+        }
+        false
+    }
+
+    fn clock_eval(&self, up: bool) -> Option<MoveEval> {
+        let driven = self.params.p6_on && self.params.clock_uses_p6;
+        if driven && up && self.resource_exhausted(true) {
+            return None;
+        }
+        let work = if driven {
+            let mu = self.reservoir_mu(true);
+            if up { mu } else { -mu }
+        } else {
+            0.0
+        };
+        Some(MoveEval { d_e: 0.0, work, high: true })
+    }
+
+    fn clock_commit(&mut self, up: bool) {
+        let k = self.params.clock_k.max(3);
         let c0 = self.clock_state;
-        let c1 = if up {
+        self.clock_state = if up {
             if c0 + 1 >= k {
                 0
             } else {
@@ -1556,27 +2327,20 @@ impl Sim {
         } else {
             c0 - 1
         };
-        let work = if self.params.p6_on && self.params.clock_uses_p6 {
-            let mu = self.params.mu_high;
-            if up { mu } else { -mu }
+        if self.params.p6_on && self.params.clock_uses_p6 {
+            self.draw_resource(true, up);
+        }
+        if up {
+            self.clock_q += 1;
+            self.clock_fwd = self.clock_fwd.saturating_add(1);
         } else {
-            0.0
-        };
-        if self.accept_move(0.0, work, 0.0, MOVE_CLOCK) {
-            self.clock_state = c1;
-            if up {
-                self.clock_q += 1;
-                self.clock_fwd = self.clock_fwd.saturating_add(1);
-            } else {
-                self.clock_q -= 1;
-                self.clock_bwd = self.clock_bwd.saturating_add(1);
-            }
-            return true;
+            self.clock_q -= 1;
+            self.clock_bwd = self.clock_bwd.saturating_add(1);
         }
-        false
     }
 
-    fn clock_gate_allows(&self, x: f32, y: f32) -> bool {
+    // `idx` is a cell of a `g x g` level grid; gate regions are resolution independent.
+    fn clock_gate_allows(&self, idx: usize, g: usize) -> bool {
         if !self.params.repair_clock_gated {
             return true;
         }
@@ -1586,25 +2350,25 @@ impl Sim {
             0
         };
         match self.params.repair_gate_mode {
-            1 => {
-                let bins = self.params.clock_k.max(1) as usize;
-                let idx = ((x * (bins as f32)).floor() as usize).min(bins - 1);
-                let active_bin = (active as usize) % bins;
-                let span = self.params.repair_gate_span.max(1) as usize;
-                let span = span.min(bins);
-                for i in 0..span {
-                    if (active_bin + i) % bins == idx {
-                        return true;
-                    }
+            2 => {
+                // One region per clock phase, cycled by the clock state.
+                if self.repair_gate_regions.is_empty() {
+                    return true;
                 }
-                false
+                let phase = (active as usize) % self.repair_gate_regions.len();
+                self.repair_gate_regions[phase].contains_cell(idx, g)
             }
-            _ => {
-                let qx = if x < 0.5 { 0 } else { 1 };
-                let qy = if y < 0.5 { 0 } else { 1 };
-                let quadrant = (qy * 2 + qx) as u8;
-                quadrant == (active % 4)
+            1 => {
+                let bins = self.params.clock_k.max(1);
+                RegionMask::Stripe {
+                    axis: Axis::X,
+                    bins,
+                    bin: active % bins,
+                    span: self.params.repair_gate_span,
+                }
+                .contains_cell(idx, g)
             }
+            _ => RegionMask::Quadrant(active % 4).contains_cell(idx, g),
         }
     }
 
@@ -1622,8 +2386,12 @@ impl Sim {
         let x1 = wrap01(x0 + dx);
         let y1 = wrap01(y0 + dy);
 
-        let d_e = self.delta_e_move_particle(i, x0, y0, x1, y1);
+        let d_e = self.delta_e_move_particle(i, x0, y0, x1, y1)
+            + self.delta_e_particle_field_move(x0, y0, x1, y1)
+            + self.delta_e_deposit(DepositEdit::Move(i, x1, y1));
         if self.accept_move(d_e, 0.0, 0.0, MOVE_X) {
+            self.deposit_commit(DepositEdit::Move(i, x1, y1));
+            self.field_count_move(i, x1, y1);
             self.positions[2 * i] = x1;
             self.positions[2 * i + 1] = y1;
         }
@@ -1646,7 +2414,7 @@ impl Sim {
                     self.positions[2 * j],
                     self.positions[2 * j + 1],
                 );
-                if r <= self.params.r_propose {
+                if r <= self.params.r_propose && self.bond_allowed(i, j) {
                     count += 1;
                     if self.rand01() < 1.0 / (count as f32) {
                         chosen = Some((i, j));
@@ -1659,18 +2427,30 @@ impl Sim {
             Some(pair) => pair,
             None => return 0,
         };
+        let up = self.rand01() < 0.5;
+        let Some(eval) = self.p1_write_eval(i, j, up) else {
+            return 0;
+        };
+        if self.accept_move(eval.d_e, eval.work, 0.0, MOVE_P1_BASE) {
+            self.p1_write_commit(i, j, up, eval.high);
+            return if up { 1 } else { -1 };
+        }
+        0
+    }
+
+    // ΔE and work of a ±1 write on bond (i, j); None when the write is out of range or its
+    // reservoir is exhausted.
+    fn p1_write_eval(&self, i: usize, j: usize, up: bool) -> Option<MoveEval> {
         let idx = edge_index(self.n, i, j);
         let w0 = self.w[idx];
-
-        let up = self.rand01() < 0.5;
         let w1 = if up {
             if w0 >= self.params.l_w {
-                return 0;
+                return None;
             }
             w0 + 1
         } else {
             if w0 == 0 {
-                return 0;
+                return None;
             }
             w0 - 1
         };
@@ -1681,7 +2461,7 @@ impl Sim {
             self.positions[2 * j],
             self.positions[2 * j + 1],
         );
-        let d_e = self.delta_e_write(w0, w1, r);
+        let d_e = self.delta_e_write(w0, w1, (i, j), r) + self.delta_e_deposit(DepositEdit::Bond(i, j, w1));
         let (work, high_ctx) = if self.params.p6_on {
             let (mx, my) = torus_midpoint(
                 self.positions[2 * i],
@@ -1694,25 +2474,32 @@ impl Sim {
         } else {
             (0.0, false)
         };
-        if self.accept_move(d_e, work, 0.0, MOVE_P1_BASE) {
-            self.w[idx] = w1;
-            self.sum_w += if up { 1 } else { -1 };
-            if self.params.p6_on {
-                if high_ctx {
-                    if up {
-                        self.diag.w_plus_h = self.diag.w_plus_h.saturating_add(1);
-                    } else {
-                        self.diag.w_minus_h = self.diag.w_minus_h.saturating_add(1);
-                    }
-                } else if up {
-                    self.diag.w_plus_l = self.diag.w_plus_l.saturating_add(1);
+        if self.params.p6_on && up && self.resource_exhausted(high_ctx) {
+            return None;
+        }
+        Some(MoveEval { d_e, work, high: high_ctx })
+    }
+
+    fn p1_write_commit(&mut self, i: usize, j: usize, up: bool, high_ctx: bool) {
+        let idx = edge_index(self.n, i, j);
+        let w1 = if up { self.w[idx] + 1 } else { self.w[idx] - 1 };
+        self.deposit_commit(DepositEdit::Bond(i, j, w1));
+        self.w[idx] = w1;
+        self.sum_w += if up { 1 } else { -1 };
+        if self.params.p6_on {
+            self.draw_resource(high_ctx, up);
+            if high_ctx {
+                if up {
+                    self.diag.w_plus_h = self.diag.w_plus_h.saturating_add(1);
                 } else {
-                    self.diag.w_minus_l = self.diag.w_minus_l.saturating_add(1);
+                    self.diag.w_minus_h = self.diag.w_minus_h.saturating_add(1);
                 }
+            } else if up {
+                self.diag.w_plus_l = self.diag.w_plus_l.saturating_add(1);
+            } else {
+                self.diag.w_minus_l = self.diag.w_minus_l.saturating_add(1);
             }
-            return if up { 1 } else { -1 };
         }
-        0
     }
 
     fn p4_write_step(&mut self) -> i8 {
@@ -1726,20 +2513,43 @@ impl Sim {
             return 0;
         }
         let k = (self.rand_u32() as usize) % self.n;
-        let n0 = self.n_counter[k];
+        if self.heat_bath_on(MOVE_P4_BASE) {
+            let current = self.n_counter[k] as i32;
+            let delta = self.heat_bath_resample(
+                MOVE_P4_BASE,
+                current,
+                |sim, v| sim.n_counter[k] = v as i16,
+                |sim, up| sim.p4_write_eval(k, up),
+                |sim, up, high, _| sim.p4_write_commit(k, up, high),
+            );
+            return delta.signum() as i8;
+        }
         let up = self.rand01() < 0.5;
+        let Some(eval) = self.p4_write_eval(k, up) else {
+            return 0;
+        };
+        if self.accept_move(eval.d_e, eval.work, 0.0, MOVE_P4_BASE) {
+            self.p4_write_commit(k, up, eval.high);
+            return if up { 1 } else { -1 };
+        }
+        0
+    }
+
+    fn p4_write_eval(&self, k: usize, up: bool) -> Option<MoveEval> {
+        let n0 = self.n_counter[k];
+        let l_n = self.l_n_of(k);
         let n1 = if up {
-            if n0 >= self.params.l_n {
-                return 0;
+            if n0 >= l_n {
+                return None;
             }
             n0 + 1
         } else {
-            if n0 <= -self.params.l_n {
-                return 0;
+            if n0 <= -l_n {
+                return None;
             }
             n0 - 1
         };
-        let d_e = self.delta_e_counter(n0, n1);
+        let d_e = self.delta_e_counter(n0, n1) + self.delta_e_deposit(DepositEdit::N(k, n1));
         let (work, high_ctx) = if self.params.p6_on {
             let x = self.positions[2 * k];
             let y = self.positions[2 * k + 1];
@@ -1748,24 +2558,28 @@ impl Sim {
         } else {
             (0.0, false)
         };
-        if self.accept_move(d_e, work, 0.0, MOVE_P4_BASE) {
-            self.n_counter[k] = n1;
-            if self.params.p6_on {
-                if high_ctx {
-                    if up {
-                        self.diag.n_plus_h = self.diag.n_plus_h.saturating_add(1);
-                    } else {
-                        self.diag.n_minus_h = self.diag.n_minus_h.saturating_add(1);
-                    }
-                } else if up {
-                    self.diag.n_plus_l = self.diag.n_plus_l.saturating_add(1);
+        if self.params.p6_on && up && self.resource_exhausted(high_ctx) {
+            return None;
+        }
+        Some(MoveEval { d_e, work, high: high_ctx })
+    }
+
+    fn p4_write_commit(&mut self, k: usize, up: bool, high_ctx: bool) {
+        self.n_counter[k] += if up { 1 } else { -1 };
+        if self.params.p6_on {
+            self.draw_resource(high_ctx, up);
+            if high_ctx {
+                if up {
+                    self.diag.n_plus_h = self.diag.n_plus_h.saturating_add(1);
                 } else {
-                    self.diag.n_minus_l = self.diag.n_minus_l.saturating_add(1);
+                    self.diag.n_minus_h = self.diag.n_minus_h.saturating_add(1);
                 }
+            } else if up {
+                self.diag.n_plus_l = self.diag.n_plus_l.saturating_add(1);
+            } else {
+                self.diag.n_minus_l = self.diag.n_minus_l.saturating_add(1);
             }
-            return if up { 1 } else { -1 };
         }
-        0
     }
 
     fn p2_write_step(&mut self) -> i8 {
@@ -1773,20 +2587,42 @@ impl Sim {
             return 0;
         }
         let k = (self.rand_u32() as usize) % self.n;
-        let a0 = self.a_counter[k];
+        if self.heat_bath_on(MOVE_P2_BASE) {
+            let current = self.a_counter[k] as i32;
+            let delta = self.heat_bath_resample(
+                MOVE_P2_BASE,
+                current,
+                |sim, v| sim.a_counter[k] = v as u16,
+                |sim, up| sim.p2_write_eval(k, up),
+                |sim, up, high, _| sim.p2_write_commit(k, up, high),
+            );
+            return delta.signum() as i8;
+        }
         let up = self.rand01() < 0.5;
+        let Some(eval) = self.p2_write_eval(k, up) else {
+            return 0;
+        };
+        if self.accept_move(eval.d_e, eval.work, 0.0, MOVE_P2_BASE) {
+            self.p2_write_commit(k, up, eval.high);
+            return if up { 1 } else { -1 };
+        }
+        0
+    }
+
+    fn p2_write_eval(&self, k: usize, up: bool) -> Option<MoveEval> {
+        let a0 = self.a_counter[k];
         let a1 = if up {
-            if a0 >= self.params.l_a {
-                return 0;
+            if a0 >= self.l_a_of(k) {
+                return None;
             }
             a0 + 1
         } else {
             if a0 == 0 {
-                return 0;
+                return None;
             }
             a0 - 1
         };
-        let d_e = self.delta_e_apparatus(a0, a1);
+        let d_e = self.delta_e_apparatus(a0, a1) + self.delta_e_deposit(DepositEdit::A(k, a1));
         let (work, high_ctx) = if self.params.p6_on {
             let x = self.positions[2 * k];
             let y = self.positions[2 * k + 1];
@@ -1795,24 +2631,28 @@ impl Sim {
         } else {
             (0.0, false)
         };
-        if self.accept_move(d_e, work, 0.0, MOVE_P2_BASE) {
-            self.a_counter[k] = a1;
-            if self.params.p6_on {
-                if high_ctx {
-                    if up {
-                        self.diag.a_plus_h = self.diag.a_plus_h.saturating_add(1);
-                    } else {
-                        self.diag.a_minus_h = self.diag.a_minus_h.saturating_add(1);
-                    }
-                } else if up {
-                    self.diag.a_plus_l = self.diag.a_plus_l.saturating_add(1);
+        if self.params.p6_on && up && self.resource_exhausted(high_ctx) {
+            return None;
+        }
+        Some(MoveEval { d_e, work, high: high_ctx })
+    }
+
+    fn p2_write_commit(&mut self, k: usize, up: bool, high_ctx: bool) {
+        self.a_counter[k] = if up { self.a_counter[k] + 1 } else { self.a_counter[k] - 1 };
+        if self.params.p6_on {
+            self.draw_resource(high_ctx, up);
+            if high_ctx {
+                if up {
+                    self.diag.a_plus_h = self.diag.a_plus_h.saturating_add(1);
                 } else {
-                    self.diag.a_minus_l = self.diag.a_minus_l.saturating_add(1);
+                    self.diag.a_minus_h = self.diag.a_minus_h.saturating_add(1);
                 }
+            } else if up {
+                self.diag.a_plus_l = self.diag.a_plus_l.saturating_add(1);
+            } else {
+                self.diag.a_minus_l = self.diag.a_minus_l.saturating_add(1);
             }
-            return if up { 1 } else { -1 };
         }
-        0
     }
 
     fn p5_write_step(&mut self) -> i8 {
@@ -1821,35 +2661,48 @@ impl Sim {
             return 0;
         }
         let idx = (self.rand_u32() as usize) % (g * g);
-        let s0 = self.s_field[idx];
-        let mismatch_bin = if self.params.meta_layers > 0 {
-            let lower = self.meta_field[idx];
-            Self::mismatch_bin(s0, lower)
-        } else {
-            1
+        if self.heat_bath_on(MOVE_P5_BASE) {
+            let current = self.s_field[idx] as i32;
+            let delta = self.heat_bath_resample(
+                MOVE_P5_BASE,
+                current,
+                |sim, v| sim.s_field[idx] = v as u8,
+                |sim, up| sim.p5_write_eval(idx, up),
+                |sim, up, high, ep_before| sim.p5_write_commit(idx, up, high, ep_before),
+            );
+            return delta.signum() as i8;
+        }
+        let up = self.rand01() < 0.5;
+        let Some(eval) = self.p5_write_eval(idx, up) else {
+            return 0;
         };
-        let k_dir = self.op_k_dir(0, idx);
+        let ep_before = self.ep_exact_total;
+        if self.accept_move(eval.d_e, eval.work, 0.0, MOVE_P5_BASE) {
+            self.p5_write_commit(idx, up, eval.high, ep_before);
+            return if up { 1 } else { -1 };
+        }
+        0
+    }
+
+    // The returned work includes the drive alignment work.
+    fn p5_write_eval(&self, idx: usize, up: bool) -> Option<MoveEval> {
+        let g = self.params.grid_size as usize;
+        let s0 = self.s_field[idx];
         let g_f = self.params.grid_size as f32;
         let x = ((idx % g as usize) as f32 + 0.5) / g_f;
         let y = ((idx / g as usize) as f32 + 0.5) / g_f;
-        let up = self.rand01() < 0.5;
         let s1 = if up {
             if s0 >= self.params.l_s {
-                return 0;
+                return None;
             }
             s0 + 1
         } else {
             if s0 == 0 {
-                return 0;
+                return None;
             }
             s0 - 1
         };
-        let mut d_e = self.delta_e_field(s0, s1);
-        if self.params.s_coupling_mode == 0 {
-            d_e += self.delta_e_s_couple_level(0, idx, s0, s1);
-        } else {
-            d_e += self.params.eta * self.delta_raw_s_op(0, idx, s0, s1);
-        }
+        let d_e = self.delta_e_s_level(0, idx, s0, s1);
         let (work, high_ctx) = if self.params.p6_on {
             let mu = self.mu_at(x, y);
             let scaled = mu * self.params.p6_s_factor;
@@ -1857,59 +2710,74 @@ impl Sim {
         } else {
             (0.0, false)
         };
+        if self.params.p6_on && up && self.resource_exhausted(high_ctx) {
+            return None;
+        }
         let align_work = self.drive_align_work(0, idx, s0, s1, x, y);
-        let ep_before = self.ep_exact_total;
-        if self.accept_move(d_e, work + align_work, 0.0, MOVE_P5_BASE) {
-            self.s_field[idx] = s1;
-            self.sum_s += if up { 1 } else { -1 };
-            let ep_delta = self.ep_exact_total - ep_before;
-            self.accept_log_push(
-                self.step_count,
-                idx as u32,
-                MOVE_P5_BASE as u8,
-                255,
-                mismatch_bin,
-                k_dir,
-                ep_delta,
-            );
-            if self.params.p6_on {
-                if high_ctx {
-                    if up {
-                        self.diag.s_plus_h = self.diag.s_plus_h.saturating_add(1);
-                    } else {
-                        self.diag.s_minus_h = self.diag.s_minus_h.saturating_add(1);
-                    }
-                } else if up {
-                    self.diag.s_plus_l = self.diag.s_plus_l.saturating_add(1);
+        Some(MoveEval { d_e, work: work + align_work, high: high_ctx })
+    }
+
+    // `ep_before` is the exact EP total before the move was booked (for the accept log).
+    fn p5_write_commit(&mut self, idx: usize, up: bool, high_ctx: bool, ep_before: f64) {
+        let s0 = self.s_field[idx];
+        // Logged context comes from the first interface above the base (the chain's level 1).
+        let (mismatch_bin, k_dir) = match self.interface_above(0) {
+            Some(interface) => {
+                let upper = self.interface_levels(interface).1;
+                let parent = self.ancestor_cell(idx, 0, upper);
+                let bin = Self::mismatch_bin(s0, self.s_level(upper)[parent]);
+                (bin, self.op_k_dir(interface, parent))
+            }
+            None => (1, 0),
+        };
+        self.s_field[idx] = if up { s0 + 1 } else { s0 - 1 };
+        self.sum_s += if up { 1 } else { -1 };
+        let ep_delta = self.ep_exact_total - ep_before;
+        self.accept_log_push(
+            self.step_count,
+            idx as u32,
+            MOVE_P5_BASE as u8,
+            255,
+            mismatch_bin,
+            k_dir,
+            ep_delta,
+        );
+        if self.params.p6_on {
+            self.draw_resource(high_ctx, up);
+            if high_ctx {
+                if up {
+                    self.diag.s_plus_h = self.diag.s_plus_h.saturating_add(1);
                 } else {
-                    self.diag.s_minus_l = self.diag.s_minus_l.saturating_add(1);
+                    self.diag.s_minus_h = self.diag.s_minus_h.saturating_add(1);
                 }
+            } else if up {
+                self.diag.s_plus_l = self.diag.s_plus_l.saturating_add(1);
+            } else {
+                self.diag.s_minus_l = self.diag.s_minus_l.saturating_add(1);
             }
-            return if up { 1 } else { -1 };
         }
-        0
     }
 
     fn p5_write_step_meta(&mut self, layer: usize) -> i8 {
-        let g = self.params.grid_size as usize;
+        let g = self.level_grid(layer + 1);
         if g == 0 || layer >= self.params.meta_layers as usize {
             return 0;
         }
         let cells = g * g;
-        let base = layer * cells;
+        let base = self.meta_offset(layer);
         let idx_local = (self.rand_u32() as usize) % cells;
         let idx = base + idx_local;
         let s0 = self.meta_field[idx];
-        let lower = if layer == 0 {
-            self.s_field[idx_local]
-        } else {
-            self.meta_field[(layer - 1) * cells + idx_local]
+        let (mismatch_bin, k_dir) = match self.interface_below(layer + 1) {
+            Some(interface) => {
+                let lower = self.s_block_mean(self.interface_levels(interface).0, layer + 1, idx_local);
+                (Self::mismatch_bin(s0, lower), self.op_k_dir(interface, idx_local))
+            }
+            None => (1, 0),
         };
-        let mismatch_bin = Self::mismatch_bin(s0, lower);
-        let k_dir = self.op_k_dir(layer, idx_local);
         let (x, y) = grid_cell_center(idx_local, g);
-        // When gated, only allow P5 updates in the active quadrant.
-        if self.params.repair_clock_gated && !self.clock_gate_allows(x, y) {
+        // When gated, only allow P5 updates in the active gate region.
+        if self.params.repair_clock_gated && !self.clock_gate_allows(idx_local, g) {
             return 0;
         }
         let up = self.rand01() < 0.5;
@@ -1924,12 +2792,7 @@ impl Sim {
             }
             s0 - 1
         };
-        let mut d_e = self.delta_e_field(s0, s1);
-        if self.params.s_coupling_mode == 0 {
-            d_e += self.delta_e_s_couple_level(layer + 1, idx_local, s0, s1);
-        } else {
-            d_e += self.params.eta * self.delta_raw_s_op(layer + 1, idx_local, s0, s1);
-        }
+        let d_e = self.delta_e_s_level(layer + 1, idx_local, s0, s1);
         let (work, high_ctx) = if self.params.p6_on {
             let mu = self.mu_at(x, y);
             let scaled = mu * self.params.p6_s_factor;
@@ -1937,6 +2800,9 @@ impl Sim {
         } else {
             (0.0, false)
         };
+        if self.params.p6_on && up && self.resource_exhausted(high_ctx) {
+            return 0;
+        }
         let align_work = self.drive_align_work(layer + 1, idx_local, s0, s1, x, y);
         let ep_before = self.ep_exact_total;
         if self.accept_move(d_e, work + align_work, 0.0, MOVE_P5_META) {
@@ -1952,6 +2818,7 @@ impl Sim {
                 ep_delta,
             );
             if self.params.p6_on {
+                self.draw_resource(high_ctx, up);
                 if high_ctx {
                     if up {
                         self.diag.s_plus_h = self.diag.s_plus_h.saturating_add(1);
@@ -1973,15 +2840,14 @@ impl Sim {
         if !self.params.op_coupling_on {
             return 0;
         }
-        let layers = self.params.meta_layers as usize;
-        if layers == 0 || interface >= layers {
+        if interface >= self.interface_count() || !self.interface_has_op(interface) {
             return 0;
         }
-        let g = self.params.grid_size as usize;
+        let g = self.level_grid(self.interface_levels(interface).1);
         if g == 0 {
             return 0;
         }
-        let r_count = self.op_r_count_internal();
+        let r_count = self.op_r_count_at(interface);
         if r_count < 2 || self.op_k.is_empty() {
             return 0;
         }
@@ -1992,39 +2858,54 @@ impl Sim {
         if r_to >= r_from {
             r_to += 1;
         }
-        let idx_from = self.op_k_index(interface, q, r_from);
-        if self.op_k[idx_from] == 0 {
+        let Some(eval) = self.opk_hop_eval(interface, q, r_from, r_to) else {
             return 0;
+        };
+        let ep_before = self.ep_exact_total;
+        if self.accept_move(eval.d_e, eval.work, 0.0, MOVE_OPK) {
+            self.opk_hop_commit(interface, q, (r_from, r_to), ep_before);
         }
-        let idx_to = self.op_k_index(interface, q, r_to);
+        0
+    }
+
+    // ΔE and work of moving one op-K token of cell q from offset r_from to r_to; None when
+    // r_from holds no token.
+    fn opk_hop_eval(&self, interface: usize, q: usize, r_from: usize, r_to: usize) -> Option<MoveEval> {
+        if self.op_k[self.op_k_index(interface, q, r_from)] == 0 {
+            return None;
+        }
+        let g = self.level_grid(self.interface_levels(interface).1);
         let delta_raw = self.delta_raw_k_op(interface, q, r_from, r_to);
         let mut d_e = 0.0;
         if self.params.s_coupling_mode == 1 {
-            d_e = self.params.eta * delta_raw;
+            d_e = self.eta_at(interface) * delta_raw;
         }
         let mut work = 0.0;
         if self.params.p6_on && self.params.op_drive_on_k && self.params.s_coupling_mode == 1 {
             let (x, y) = grid_cell_center(q, g);
             let mu_scale = self.mu_at(x, y).abs();
             let scale = self.params.l_s.max(1) as f32;
-            work = -self.params.eta_drive * delta_raw * scale * scale * mu_scale;
+            work = -self.eta_drive_at(interface) * delta_raw * scale * scale * mu_scale;
         }
-        let ep_before = self.ep_exact_total;
-        if self.accept_move(d_e, work, 0.0, MOVE_OPK) {
-            self.op_k[idx_from] = self.op_k[idx_from].saturating_sub(1);
-            self.op_k[idx_to] = self.op_k[idx_to].saturating_add(1);
-            let ep_delta = self.ep_exact_total - ep_before;
-            self.accept_log_push(
-                self.step_count,
-                q as u32,
-                MOVE_OPK as u8,
-                0,
-                r_from as u8,
-                r_to as u8,
-                ep_delta,
-            );
-        }
-        0
+        Some(MoveEval { d_e, work, high: false })
+    }
+
+    fn opk_hop_commit(&mut self, interface: usize, q: usize, (r_from, r_to): (usize, usize), ep_before: f64) {
+        let idx_from = self.op_k_index(interface, q, r_from);
+        let idx_to = self.op_k_index(interface, q, r_to);
+        self.op_k[idx_from] = self.op_k[idx_from].saturating_sub(1);
+        self.op_k[idx_to] = self.op_k[idx_to].saturating_add(1);
+        self.update_op_motif(interface, q);
+        let ep_delta = self.ep_exact_total - ep_before;
+        self.accept_log_push(
+            self.step_count,
+            q as u32,
+            MOVE_OPK as u8,
+            0,
+            r_from as u8,
+            r_to as u8,
+            ep_delta,
+        );
     }
 
     fn p4_write_step_meta(&mut self, layer: usize) -> i8 {
@@ -2038,8 +2919,9 @@ impl Sim {
         if g == 0 || layer >= self.params.meta_layers as usize {
             return 0;
         }
+        let g = self.level_grid(layer + 1);
         let cells = g * g;
-        let base = layer * cells;
+        let base = self.meta_offset(layer);
         let idx_local = (self.rand_u32() as usize) % cells;
         let idx = base + idx_local;
         let n0 = self.meta_n_field[idx];
@@ -2064,9 +2946,13 @@ impl Sim {
         } else {
             (0.0, false)
         };
+        if self.params.p6_on && up && self.resource_exhausted(high_ctx) {
+            return 0;
+        }
         if self.accept_move(d_e, work, 0.0, MOVE_P4_META) {
             self.meta_n_field[idx] = n1;
             if self.params.p6_on {
+                self.draw_resource(high_ctx, up);
                 if high_ctx {
                     if up {
                         self.diag.n_plus_h = self.diag.n_plus_h.saturating_add(1);
@@ -2085,12 +2971,12 @@ impl Sim {
     }
 
     fn p2_write_step_meta(&mut self, layer: usize) -> i8 {
-        let g = self.params.grid_size as usize;
+        let g = self.level_grid(layer + 1);
         if g == 0 || layer >= self.params.meta_layers as usize {
             return 0;
         }
         let cells = g * g;
-        let base = layer * cells;
+        let base = self.meta_offset(layer);
         let idx_local = (self.rand_u32() as usize) % cells;
         let idx = base + idx_local;
         let a0 = self.meta_a_field[idx];
@@ -2115,9 +3001,13 @@ impl Sim {
         } else {
             (0.0, false)
         };
+        if self.params.p6_on && up && self.resource_exhausted(high_ctx) {
+            return 0;
+        }
         if self.accept_move(d_e, work, 0.0, MOVE_P2_META) {
             self.meta_a_field[idx] = a1;
             if self.params.p6_on {
+                self.draw_resource(high_ctx, up);
                 if high_ctx {
                     if up {
                         self.diag.a_plus_h = self.diag.a_plus_h.saturating_add(1);
@@ -2136,7 +3026,7 @@ impl Sim {
     }
 
     fn p1_write_step_meta(&mut self, layer: usize) -> i8 {
-        let g = self.params.grid_size as usize;
+        let g = self.level_grid(layer + 1);
         if g == 0 || layer >= self.params.meta_layers as usize {
             return 0;
         }
@@ -2144,7 +3034,7 @@ impl Sim {
         if edges_per_layer == 0 {
             return 0;
         }
-        let base = layer * edges_per_layer;
+        let base = self.meta_edge_offset(layer);
         let edge = (self.rand_u32() as usize) % edges_per_layer;
         let idx = base + edge;
         let w0 = self.meta_w_edges[idx];
@@ -2171,9 +3061,13 @@ impl Sim {
         } else {
             (0.0, false)
         };
+        if self.params.p6_on && up && self.resource_exhausted(high_ctx) {
+            return 0;
+        }
         if self.accept_move(d_e, work, 0.0, MOVE_P1_META) {
             self.meta_w_edges[idx] = w1;
             if self.params.p6_on {
+                self.draw_resource(high_ctx, up);
                 if high_ctx {
                     if up {
                         self.diag.w_plus_h = self.diag.w_plus_h.saturating_add(1);
@@ -2192,7 +3086,7 @@ impl Sim {
     }
 
     fn protocol_step(&mut self, step_diag: &mut StepDiag) {
-        let mut kernels = [0u8; 5];
+        let mut kernels = [0u8; 7];
         let mut len = 0usize;
         kernels[len] = 0; // X
         len += 1;
@@ -2212,6 +3106,14 @@ impl Sim {
             kernels[len] = 4;
             len += 1;
         }
+        if self.params.reaction_on {
+            kernels[len] = 5;
+            len += 1;
+        }
+        if self.params.gc_on {
+            kernels[len] = 6;
+            len += 1;
+        }
         if len == 0 {
             return;
         }
@@ -2306,6 +3208,10 @@ impl Sim {
                     step_diag.s_minus = 1;
                 }
             }
+            5 => {
+                self.reaction_step();
+            }
+            6 => self.gc_exchange_step(),
             _ => {}
         }
         if idx < self.p3_obs1.len() {
@@ -2354,66 +3260,44 @@ impl Sim {
     fn accept_move(&mut self, delta_e: f32, work: f32, log_q_ratio: f32, move_kind: usize) -> bool {
         let effective = delta_e - work;
         let log_a_ratio = -self.params.beta * effective;
-        let accepted = if effective <= 0.0 {
+        // log_q_ratio = ln(q_rev / q_fwd) enters the acceptance for asymmetric proposals.
+        let metropolis = self.params.accept_rule.get(move_kind).is_none_or(|r| *r == accept_rule::ACCEPT_METROPOLIS);
+        let accepted = if !metropolis {
+            let x = (log_a_ratio + log_q_ratio) as f64;
+            (self.rand01() as f64) < self.acceptance(move_kind, x)
+        } else if effective <= 0.0 && log_q_ratio >= 0.0 {
             true
         } else {
-            let a = log_a_ratio.exp();
+            let a = (log_a_ratio + log_q_ratio).exp();
             self.rand01() < a.min(1.0)
         };
         if accepted {
-            self.ep_naive_total += log_a_ratio as f64;
-            self.ep_exact_total += (log_a_ratio + log_q_ratio) as f64;
-            if move_kind < MOVE_KIND_COUNT {
-                self.ep_naive_by_move[move_kind] += log_a_ratio as f64;
-                self.ep_exact_by_move[move_kind] += (log_a_ratio + log_q_ratio) as f64;
-            }
-            if self.params.ep_debug {
-                self.ep_q_stats[move_kind].record(log_q_ratio);
-            }
+            self.book_accepted(delta_e, work, log_q_ratio, move_kind);
         }
         accepted
     }
 
-    fn maybe_code_noise(&mut self) {
-        if self.params.code_noise_rate <= 0.0 {
-            return;
-        }
-        let layers = self.params.meta_layers as usize;
-        if layers == 0 {
-            return;
+    // Ledger and EP bookkeeping of an accepted move.
+    fn book_accepted(&mut self, delta_e: f32, work: f32, log_q_ratio: f32, move_kind: usize) {
+        let log_a_ratio = -self.params.beta * (delta_e - work);
+        self.ledger_book_move(move_kind, delta_e, work);
+        self.ep_naive_total += log_a_ratio as f64;
+        self.ep_exact_total += (log_a_ratio + log_q_ratio) as f64;
+        if move_kind < MOVE_KIND_COUNT {
+            self.ep_naive_by_move[move_kind] += log_a_ratio as f64;
+            self.ep_exact_by_move[move_kind] += (log_a_ratio + log_q_ratio) as f64;
         }
-        if self.rand01() >= self.params.code_noise_rate {
-            return;
-        }
-        let g = self.params.grid_size as usize;
-        if g == 0 {
-            return;
-        }
-        let cells = g * g;
-        let layer = self.params.code_noise_layer as usize;
-        if layer >= layers {
-            return;
-        }
-        let base = layer * cells;
-        let max_val = self.params.l_s as u32 + 1;
-        let batch = self.params.code_noise_batch.max(1) as usize;
-        for _ in 0..batch {
-            let idx = base + ((self.rand_u32() as usize) % cells);
-            let val = if max_val == 0 {
-                0
-            } else {
-                (self.rand_u32() % max_val) as u8
-            };
-            self.meta_field[idx] = val;
+        if self.params.ep_debug {
+            self.ep_q_stats[move_kind].record(log_q_ratio);
         }
     }
 
-    fn delta_e_write(&self, w0: u8, w1: u8, r: f32) -> f32 {
+    fn delta_e_write(&self, w0: u8, w1: u8, (i, j): (usize, usize), r: f32) -> f32 {
         let w0f = w0 as f32;
         let w1f = w1 as f32;
         let e_w0 = 0.5 * self.params.lambda_w * w0f * w0f;
         let e_w1 = 0.5 * self.params.lambda_w * w1f * w1f;
-        let bond_shape = 0.5 * self.params.kappa_bond * (r - self.params.r_star).powi(2);
+        let bond_shape = self.bond_shape(i, j, r);
         (e_w1 - e_w0) + bond_shape * (w1f - w0f)
     }
 
@@ -2433,171 +3317,168 @@ impl Sim {
         e1 - e0
     }
 
-    fn delta_e_field(&self, s0: u8, s1: u8) -> f32 {
+    fn delta_e_field(&self, level: usize, s0: u8, s1: u8) -> f32 {
+        let lambda_s = self.lambda_s_at(level);
         let s0f = s0 as f32;
         let s1f = s1 as f32;
-        let e0 = 0.5 * self.params.lambda_s * s0f * s0f;
-        let e1 = 0.5 * self.params.lambda_s * s1f * s1f;
+        let e0 = 0.5 * lambda_s * s0f * s0f;
+        let e1 = 0.5 * lambda_s * s1f * s1f;
         e1 - e0
     }
 
-    fn delta_e_s_couple_level(&self, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
-        let layers = self.params.meta_layers as usize;
-        if self.params.eta == 0.0 || layers == 0 {
-            return 0.0;
+    // Full ΔE for changing one S cell at `level` (0 = base), including in-layer neighbours,
+    // inter-layer coupling and, on the base, the particles sitting in the cell.
+    fn delta_e_s_level(&self, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
+        let mut d_e = self.delta_e_field(level, s0, s1)
+            + self.delta_e_s_lateral(level, idx, s0, s1)
+            + self.delta_e_s_couple_level(level, idx, s0, s1);
+        if level == 0 {
+            d_e += self.delta_e_field_particles(idx, s0, s1);
         }
-        let denom = self.params.l_s.max(1) as f32;
-        let s0n = (s0 as f32) / denom;
-        let s1n = (s1 as f32) / denom;
-        let g = self.params.grid_size as usize;
-        let cells = g * g;
+        d_e
+    }
+
+    fn delta_e_s_couple_level(&self, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
         let mut delta = 0.0;
-        if level > 0 {
-            let neighbor = if level == 1 {
-                self.s_field[idx]
-            } else {
-                self.meta_field[(level - 2) * cells + idx]
-            };
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * self.params.eta * ((s1n - nn).powi(2) - (s0n - nn).powi(2));
-        }
-        if level < layers {
-            let neighbor = self.meta_field[level * cells + idx];
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * self.params.eta * ((s1n - nn).powi(2) - (s0n - nn).powi(2));
+        for interface in self.interfaces_at(level) {
+            delta += self.eta_at(interface) * self.delta_s_edge(interface, level, idx, s0, s1);
         }
         delta
     }
 
-    fn delta_s_mismatch_level(&self, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
-        let layers = self.params.meta_layers as usize;
-        if layers == 0 {
-            return 0.0;
+    // Under sCouplingMode = 1 interfaces with op-K couple through their stencil prediction;
+    // every other interface compares the upper level with the block mean below it.
+    fn s_edge_uses_op(&self, interface: usize) -> bool {
+        self.params.s_coupling_mode == 1 && self.interface_has_op(interface)
+    }
+
+    // Unweighted coupling change on one interface for a move at either of its ends.
+    fn delta_s_edge(&self, interface: usize, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
+        if self.s_edge_uses_op(interface) {
+            self.delta_raw_s_op_edge(interface, level, idx, s0, s1)
+        } else {
+            self.delta_s_mismatch_edge(interface, level, idx, s0, s1)
         }
+    }
+
+    fn delta_s_mismatch_edge(&self, interface: usize, level: usize, idx: usize, s0: u8, s1: u8) -> f32 {
         let denom = self.params.l_s.max(1) as f32;
-        let s0n = (s0 as f32) / denom;
-        let s1n = (s1 as f32) / denom;
-        let g = self.params.grid_size as usize;
-        let cells = g * g;
-        let mut delta = 0.0;
-        if level > 0 {
-            let neighbor = if level == 1 {
-                self.s_field[idx]
-            } else {
-                self.meta_field[(level - 2) * cells + idx]
-            };
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * ((s1n - nn).powi(2) - (s0n - nn).powi(2));
-        }
-        if level < layers {
-            let neighbor = self.meta_field[level * cells + idx];
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * ((s1n - nn).powi(2) - (s0n - nn).powi(2));
-        }
-        delta
+        let (lower, upper) = self.interface_levels(interface);
+        if level == upper {
+            let s0n = (s0 as f32) / denom;
+            let s1n = (s1 as f32) / denom;
+            let (sum, count) = self.s_block_sum(lower, upper, idx);
+            let nn = (sum as f32) / (count as f32) / denom;
+            return 0.5 * ((s1n - nn).powi(2) - (s0n - nn).powi(2));
+        }
+        let p = self.ancestor_cell(idx, lower, upper);
+        let nn = (self.s_level(upper)[p] as f32) / denom;
+        let (sum, count) = self.s_block_sum(lower, upper, p);
+        let m0 = (sum as f32) / (count as f32) / denom;
+        let m1 = ((sum - s0 as i32 + s1 as i32) as f32) / (count as f32) / denom;
+        0.5 * ((m1 - nn).powi(2) - (m0 - nn).powi(2))
     }
 
     fn drive_align_work(&self, level: usize, idx: usize, s0: u8, s1: u8, x: f32, y: f32) -> f32 {
-        if !self.params.p6_on || self.params.eta_drive == 0.0 {
+        if !self.params.p6_on || self.interfaces_at(level).all(|i| self.eta_drive_at(i) == 0.0) {
             return 0.0;
         }
         if self.params.repair_clock_gated && level == 0 {
             return 0.0;
         }
         let gated = self.params.repair_clock_gated && level > 0;
-        if gated && !self.clock_gate_allows(x, y) {
+        if gated && !self.clock_gate_allows(idx, self.level_grid(level)) {
             return 0.0;
         }
-        let delta = if self.params.s_coupling_mode == 1 {
-            self.delta_raw_s_op(level, idx, s0, s1)
-        } else {
-            self.delta_s_mismatch_level(level, idx, s0, s1)
-        };
+        let mut driven = 0.0;
+        for interface in self.interfaces_at(level) {
+            driven += self.eta_drive_at(interface) * self.delta_s_edge(interface, level, idx, s0, s1);
+        }
         let scale = self.params.l_s.max(1) as f32;
         // Boost work under quadrant gating to offset reduced coverage.
         let gate_scale = if gated { 16.0 } else { 1.0 };
         let mu_scale = self.mu_at(x, y).abs();
-        -self.params.eta_drive * delta * scale * scale * gate_scale * mu_scale
+        -driven * scale * scale * gate_scale * mu_scale
+    }
+
+    // Unweighted mismatch change on an interface between meta layers `lower` and `upper`
+    // (layer indices) when cell `idx` of `layer` (one of the two) moves from v0 to v1.
+    // `start(l)` locates layer l's grid-shaped slice of `field`; the upper layer is compared
+    // with the block mean of the lower one beneath it.
+    fn meta_mismatch<T: Copy + Into<i32>>(
+        &self,
+        field: &[T],
+        start: impl Fn(usize) -> usize,
+        (lower, upper): (usize, usize),
+        (layer, idx): (usize, usize),
+        (v0, v1): (T, T),
+        denom: f32,
+    ) -> f32 {
+        let plane = |l: usize| &field[start(l)..start(l) + self.level_cells(l + 1)];
+        let (v0, v1) = (v0.into(), v1.into());
+        if layer == upper {
+            let v0n = (v0 as f32) / denom;
+            let v1n = (v1 as f32) / denom;
+            let (sum, count) = self.level_block_sum(plane(lower), lower + 1, upper + 1, idx);
+            let nn = (sum as f32) / (count as f32) / denom;
+            return 0.5 * ((v1n - nn).powi(2) - (v0n - nn).powi(2));
+        }
+        let p = self.ancestor_cell(idx, lower + 1, upper + 1);
+        let nn = (plane(upper)[p].into() as f32) / denom;
+        let (sum, count) = self.level_block_sum(plane(lower), lower + 1, upper + 1, p);
+        let m0 = (sum as f32) / (count as f32) / denom;
+        let m1 = ((sum - v0 + v1) as f32) / (count as f32) / denom;
+        0.5 * ((m1 - nn).powi(2) - (m0 - nn).powi(2))
+    }
+
+    // Weighted a/n/w coupling change summed over the interfaces joining `layer` to another
+    // meta layer (interfaces down to the base couple a/n/w through the particle deposit).
+    fn delta_e_meta_couple<T: Copy + Into<i32>>(
+        &self,
+        field: &[T],
+        start: impl Fn(usize) -> usize,
+        (layer, idx): (usize, usize),
+        values: (T, T),
+        denom: f32,
+    ) -> f32 {
+        let mut delta = 0.0;
+        for interface in self.interfaces_at(layer + 1) {
+            let (lower, upper) = self.interface_levels(interface);
+            if lower == 0 {
+                continue;
+            }
+            let ends = (lower - 1, upper - 1);
+            delta += self.eta_at(interface) * self.meta_mismatch(field, &start, ends, (layer, idx), values, denom);
+        }
+        delta
     }
 
     fn delta_e_meta_a_couple(&self, layer: usize, idx: usize, a0: u16, a1: u16) -> f32 {
-        let layers = self.params.meta_layers as usize;
-        if self.params.eta == 0.0 || layers < 2 {
-            return 0.0;
-        }
         let denom = self.params.l_a.max(1) as f32;
-        let a0n = (a0 as f32) / denom;
-        let a1n = (a1 as f32) / denom;
-        let g = self.params.grid_size as usize;
-        let cells = g * g;
-        let mut delta = 0.0;
-        if layer > 0 {
-            let neighbor = self.meta_a_field[(layer - 1) * cells + idx];
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * self.params.eta * ((a1n - nn).powi(2) - (a0n - nn).powi(2));
-        }
-        if layer + 1 < layers {
-            let neighbor = self.meta_a_field[(layer + 1) * cells + idx];
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * self.params.eta * ((a1n - nn).powi(2) - (a0n - nn).powi(2));
-        }
-        delta
+        let start = |l: usize| self.meta_offset(l);
+        self.delta_e_meta_couple(&self.meta_a_field, start, (layer, idx), (a0, a1), denom)
+            + self.delta_e_deposit_meta(DepositChannel::A, (layer, idx), a0 as i32, a1 as i32)
     }
 
     fn delta_e_meta_n_couple(&self, layer: usize, idx: usize, n0: i16, n1: i16) -> f32 {
-        let layers = self.params.meta_layers as usize;
-        if self.params.eta == 0.0 || layers < 2 {
-            return 0.0;
-        }
         let denom = self.params.l_n.max(1) as f32;
-        let n0n = (n0 as f32) / denom;
-        let n1n = (n1 as f32) / denom;
-        let g = self.params.grid_size as usize;
-        let cells = g * g;
-        let mut delta = 0.0;
-        if layer > 0 {
-            let neighbor = self.meta_n_field[(layer - 1) * cells + idx];
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * self.params.eta * ((n1n - nn).powi(2) - (n0n - nn).powi(2));
-        }
-        if layer + 1 < layers {
-            let neighbor = self.meta_n_field[(layer + 1) * cells + idx];
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * self.params.eta * ((n1n - nn).powi(2) - (n0n - nn).powi(2));
-        }
-        delta
+        let start = |l: usize| self.meta_offset(l);
+        self.delta_e_meta_couple(&self.meta_n_field, start, (layer, idx), (n0, n1), denom)
+            + self.delta_e_deposit_meta(DepositChannel::N, (layer, idx), n0 as i32, n1 as i32)
     }
 
+    // Edges couple per direction plane: horizontal edges to horizontal, vertical to vertical.
     fn delta_e_meta_w_couple(&self, layer: usize, edge: usize, w0: u8, w1: u8) -> f32 {
-        let layers = self.params.meta_layers as usize;
-        if self.params.eta == 0.0 || layers < 2 {
-            return 0.0;
-        }
         let denom = self.params.l_w.max(1) as f32;
-        let w0n = (w0 as f32) / denom;
-        let w1n = (w1 as f32) / denom;
-        let g = self.params.grid_size as usize;
-        let edges = meta_edge_count(g);
-        let mut delta = 0.0;
-        if layer > 0 {
-            let neighbor = self.meta_w_edges[(layer - 1) * edges + edge];
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * self.params.eta * ((w1n - nn).powi(2) - (w0n - nn).powi(2));
-        }
-        if layer + 1 < layers {
-            let neighbor = self.meta_w_edges[(layer + 1) * edges + edge];
-            let nn = (neighbor as f32) / denom;
-            delta += 0.5 * self.params.eta * ((w1n - nn).powi(2) - (w0n - nn).powi(2));
-        }
-        delta
+        let cells = self.level_cells(layer + 1);
+        let (dir, idx) = (edge / cells, edge % cells);
+        let start = |l: usize| self.meta_edge_offset(l) + dir * self.level_cells(l + 1);
+        self.delta_e_meta_couple(&self.meta_w_edges, start, (layer, idx), (w0, w1), denom)
+            + self.delta_e_deposit_meta(DepositChannel::W, (layer, edge), w0 as i32, w1 as i32)
     }
 
     fn mu_at(&self, x: f32, _y: f32) -> f32 {
-        if x < 0.5 {
-            self.params.mu_high
-        } else {
-            self.params.mu_low
-        }
+        self.reservoir_mu(x < 0.5)
     }
 
     fn delta_e_move_particle(&self, i: usize, x0: f32, y0: f32, x1: f32, y1: f32) -> f32 {
@@ -2611,45 +3492,25 @@ impl Sim {
             let yj = self.positions[2 * j + 1];
             let r0 = torus_dist(x0, y0, xj, yj);
             let r1 = torus_dist(x1, y1, xj, yj);
-            d_rep += repulsion_energy(self.params.kappa_rep, self.params.r0, r1)
-                - repulsion_energy(self.params.kappa_rep, self.params.r0, r0);
+            d_rep += self.pair_energy(i, j, r1) - self.pair_energy(i, j, r0);
 
             let (a, b) = if i < j { (i, j) } else { (j, i) };
             let w = self.w[edge_index(self.n, a, b)];
             if w > 0 {
                 let wf = w as f32;
-                let bond0 = 0.5 * self.params.kappa_bond * wf * (r0 - self.params.r_star).powi(2);
-                let bond1 = 0.5 * self.params.kappa_bond * wf * (r1 - self.params.r_star).powi(2);
-                d_bond += bond1 - bond0;
+                d_bond += wf * self.bond_shape(i, j, r1) - wf * self.bond_shape(i, j, r0);
             }
         }
         d_rep + d_bond
     }
 
     fn energy_breakdown_inner(&self) -> (f32, f32, f32, f32, f32, f32, f32) {
-        let mut u_rep = 0.0f32;
-        let mut u_bond = 0.0f32;
+        let (u_rep, u_bond) = self.pair_energy_total();
         let mut e_w = 0.0f32;
         let mut e_n = 0.0f32;
         let mut e_a = 0.0f32;
         let mut e_s = 0.0f32;
 
-        for i in 0..self.n {
-            for j in (i + 1)..self.n {
-                let r = torus_dist(
-                    self.positions[2 * i],
-                    self.positions[2 * i + 1],
-                    self.positions[2 * j],
-                    self.positions[2 * j + 1],
-                );
-                u_rep += repulsion_energy(self.params.kappa_rep, self.params.r0, r);
-                let w = self.w[edge_index(self.n, i, j)] as f32;
-                if w > 0.0 {
-                    u_bond += 0.5 * self.params.kappa_bond * w * (r - self.params.r_star).powi(2);
-                }
-            }
-        }
-
         for &w in &self.w {
             let wf = w as f32;
             e_w += 0.5 * self.params.lambda_w * wf * wf;
@@ -2665,9 +3526,10 @@ impl Sim {
             e_a += 0.5 * self.params.lambda_a * af * af;
         }
 
+        let lambda_s0 = self.lambda_s_at(0);
         for &s in &self.s_field {
             let sf = s as f32;
-            e_s += 0.5 * self.params.lambda_s * sf * sf;
+            e_s += 0.5 * lambda_s0 * sf * sf;
         }
 
         for &w in &self.meta_w_edges {
@@ -2685,12 +3547,20 @@ impl Sim {
             e_a += 0.5 * self.params.lambda_a * af * af;
         }
 
-        for &s in &self.meta_field {
-            let sf = s as f32;
-            e_s += 0.5 * self.params.lambda_s * sf * sf;
+        for level in 1..=self.params.meta_layers as usize {
+            let lambda_s = self.lambda_s_at(level);
+            for &s in self.s_level(level) {
+                let sf = s as f32;
+                e_s += 0.5 * lambda_s * sf * sf;
+            }
         }
 
-        let total = u_rep + u_bond + e_w + e_n + e_a + e_s;
+        for level in 0..=self.params.meta_layers as usize {
+            e_s += self.s_lateral_energy(level);
+        }
+
+        let total =
+            u_rep + u_bond + e_w + e_n + e_a + e_s + self.particle_field_energy() + self.species_energy_total();
         (u_rep, u_bond, e_w, e_n, e_a, e_s, total)
     }
 
@@ -2715,6 +3585,57 @@ impl Sim {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum PerturbMode {
+    Randomize,
+    Zero,
+    Set(u8),
+    Invert,
+    Bias(i16),
+    Shift(i32, i32),
+    CopyFrom,
+}
+
+// Perturbation request resolved from JS params; `level` 0 is base S, 1.. are meta layers.
+struct PerturbSpec {
+    level: usize,
+    mode: PerturbMode,
+    frac: f32,
+    region: RegionMask,
+    source: Option<Vec<u8>>,
+    seed: u32,
+}
+
+#[derive(Default)]
+struct PerturbReport {
+    indices: Vec<u32>,
+    changed: u32,
+    mean_abs_change: f64, // mean |s1 - s0| over the changed cells
+    energy_delta: f64,
+}
+
+fn perturb_report_object(report: &PerturbReport) -> Object {
+    let o = Object::new();
+    let _ = Reflect::set(
+        &o,
+        &JsValue::from_str("touched"),
+        &JsValue::from_f64(report.indices.len() as f64),
+    );
+    let _ = Reflect::set(&o, &JsValue::from_str("changed"), &JsValue::from_f64(report.changed as f64));
+    let _ = Reflect::set(
+        &o,
+        &JsValue::from_str("indices"),
+        &Uint32Array::from(report.indices.as_slice()),
+    );
+    let _ = Reflect::set(
+        &o,
+        &JsValue::from_str("meanAbsChange"),
+        &JsValue::from_f64(report.mean_abs_change),
+    );
+    let _ = Reflect::set(&o, &JsValue::from_str("energyDelta"), &JsValue::from_f64(report.energy_delta));
+    o
+}
+
 #[derive(Clone, Copy, Default)]
 struct EpQStats {
     count: u64,
@@ -2911,6 +3832,36 @@ fn get_u8(obj: &JsValue, key: &str) -> Option<u8> {
     Some(n.round().clamp(0.0, 255.0) as u8)
 }
 
+fn get_u8_vec(obj: &JsValue, key: &str) -> Option<Vec<u8>> {
+    let v = Reflect::get(obj, &JsValue::from_str(key)).ok()?;
+    if v.is_undefined() || v.is_null() || !v.is_object() {
+        return None;
+    }
+    let arr = Array::from(&v);
+    let mut out = Vec::with_capacity(arr.length() as usize);
+    for item in arr.iter() {
+        let n = item.as_f64()?;
+        if !n.is_finite() {
+            return None;
+        }
+        out.push(n.round().clamp(0.0, 255.0) as u8);
+    }
+    Some(out)
+}
+
+fn get_f32_vec(obj: &JsValue, key: &str) -> Option<Vec<f32>> {
+    let v = Reflect::get(obj, &JsValue::from_str(key)).ok()?;
+    if v.is_undefined() || v.is_null() || !v.is_object() {
+        return None;
+    }
+    let arr = Array::from(&v);
+    let mut out = Vec::with_capacity(arr.length() as usize);
+    for item in arr.iter() {
+        out.push(item.as_f64()? as f32);
+    }
+    Some(out)
+}
+
 fn get_i16(obj: &JsValue, key: &str) -> Option<i16> {
     let v = Reflect::get(obj, &JsValue::from_str(key)).ok()?;
     if v.is_undefined() || v.is_null() {
@@ -2992,6 +3943,40 @@ fn torus_midpoint(x0: f32, y0: f32, x1: f32, y1: f32) -> (f32, f32) {
     (wrap01(x0 + 0.5 * dx), wrap01(y0 + 0.5 * dy))
 }
 
+// Cell of a `coarse` grid covering cell `idx` of a `fine` grid on the same torus.
+fn coarse_cell(idx: usize, fine: usize, coarse: usize) -> usize {
+    if fine == coarse {
+        return idx;
+    }
+    let x = (idx % fine) * coarse / fine;
+    let y = (idx / fine) * coarse / fine;
+    y * coarse + x
+}
+
+// Fine-grid coordinates covered by coarse coordinate `c` (inverse of `coarse_cell` per axis).
+fn block_span(c: usize, fine: usize, coarse: usize) -> std::ops::Range<usize> {
+    (c * fine).div_ceil(coarse)..((c + 1) * fine).div_ceil(coarse)
+}
+
+fn block_len(q: usize, fine: usize, coarse: usize) -> usize {
+    block_span(q % coarse, fine, coarse).len() * block_span(q / coarse, fine, coarse).len()
+}
+
+// Sum and cell count of a `fine` field under cell `q` of a `coarse` grid.
+fn block_sum<T: Copy + Into<i32>>(field: &[T], fine: usize, coarse: usize, q: usize) -> (i32, usize) {
+    if fine == coarse {
+        return (field[q].into(), 1);
+    }
+    let xs = block_span(q % coarse, fine, coarse);
+    let mut sum = 0i32;
+    for y in block_span(q / coarse, fine, coarse) {
+        for x in xs.clone() {
+            sum += field[y * fine + x].into();
+        }
+    }
+    (sum, block_len(q, fine, coarse))
+}
+
 fn meta_edge_count(grid: usize) -> usize {
     grid.saturating_mul(grid).saturating_mul(2)
 }
@@ -3034,53 +4019,7 @@ fn repulsion_energy(kappa_rep: f32, r0: f32, r: f32) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    struct Lcg {
-        state: u32,
-    }
-
-    impl Lcg {
-        fn new(seed: u32) -> Self {
-            Self { state: seed }
-        }
-
-        fn next_u32(&mut self) -> u32 {
-            self.state = self.state.wrapping_mul(1664525).wrapping_add(1013904223);
-            self.state
-        }
-
-        fn next_usize(&mut self, max: usize) -> usize {
-            if max == 0 {
-                0
-            } else {
-                (self.next_u32() as usize) % max
-            }
-        }
-
-        fn next_u8_range(&mut self, max_inclusive: u8) -> u8 {
-            if max_inclusive == 0 {
-                0
-            } else {
-                (self.next_u32() % (max_inclusive as u32 + 1)) as u8
-            }
-        }
-
-        fn next_u16_range(&mut self, max_inclusive: u16) -> u16 {
-            if max_inclusive == 0 {
-                0
-            } else {
-                (self.next_u32() % (max_inclusive as u32 + 1)) as u16
-            }
-        }
-
-        fn next_i16_range(&mut self, min_inclusive: i16, max_inclusive: i16) -> i16 {
-            if min_inclusive >= max_inclusive {
-                return min_inclusive;
-            }
-            let span = (max_inclusive as i32 - min_inclusive as i32 + 1) as u32;
-            min_inclusive + (self.next_u32() % span) as i16
-        }
-    }
+    use crate::test_support::{fill_random_fields, Lcg};
 
     fn coupling_energy_s(
         params: &Params,
@@ -3191,28 +4130,6 @@ mod tests {
         energy
     }
 
-    fn fill_random_fields(sim: &mut Sim, rng: &mut Lcg) {
-        let l_s = sim.params.l_s;
-        let l_w = sim.params.l_w;
-        let l_a = sim.params.l_a;
-        let l_n = sim.params.l_n;
-        for s in &mut sim.s_field {
-            *s = rng.next_u8_range(l_s);
-        }
-        for s in &mut sim.meta_field {
-            *s = rng.next_u8_range(l_s);
-        }
-        for w in &mut sim.meta_w_edges {
-            *w = rng.next_u8_range(l_w);
-        }
-        for a in &mut sim.meta_a_field {
-            *a = rng.next_u16_range(l_a);
-        }
-        for n in &mut sim.meta_n_field {
-            *n = rng.next_i16_range(-l_n, l_n);
-        }
-    }
-
     fn propose_u8(rng: &mut Lcg, max: u8, current: u8) -> u8 {
         if max == 0 {
             return 0;
@@ -3391,4 +4308,351 @@ mod tests {
         let diff = (e_after - e_before - delta as f64).abs();
         assert!(diff < 1e-5);
     }
+
+    #[test]
+    fn test_structured_perturbations_apply_expected_values() {
+        let mut sim = Sim::new(1, 1);
+        let g = 4usize;
+        let cells = g * g;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 1;
+        sim.params.l_s = 6;
+        sim.s_field = vec![0u8; cells];
+        sim.resize_meta_arrays();
+        let mut rng = Lcg::new(42);
+        fill_random_fields(&mut sim, &mut rng);
+        let base = sim.s_field.clone();
+        let spec = |mode: PerturbMode, source: Option<Vec<u8>>| PerturbSpec {
+            level: 0,
+            mode,
+            frac: 1.0,
+            region: RegionMask::All,
+            source,
+            seed: 7,
+        };
+
+        sim.apply_perturbation_spec(&spec(PerturbMode::Invert, None));
+        for (s, b) in sim.s_field.iter().zip(&base) {
+            assert_eq!(*s, 6 - *b);
+        }
+        sim.apply_perturbation_spec(&spec(PerturbMode::Invert, None));
+        assert_eq!(sim.s_field, base);
+
+        sim.apply_perturbation_spec(&spec(PerturbMode::Bias(4), None));
+        for (s, b) in sim.s_field.iter().zip(&base) {
+            assert_eq!(*s, (*b + 4).min(6));
+        }
+
+        sim.s_field = base.clone();
+        sim.apply_perturbation_spec(&spec(PerturbMode::Shift(1, -1), None));
+        for (i, b) in base.iter().enumerate() {
+            let (x, y) = (i % g, i / g);
+            let dst = ((y + g - 1) % g) * g + (x + 1) % g;
+            assert_eq!(sim.s_field[dst], *b);
+        }
+
+        let meta = sim.meta_field.clone();
+        sim.apply_perturbation_spec(&spec(PerturbMode::CopyFrom, Some(meta.clone())));
+        assert_eq!(sim.s_field, meta);
+        assert_eq!(sim.sum_s, meta.iter().map(|v| *v as i32).sum::<i32>());
+
+        let mut quad = spec(PerturbMode::Set(3), None);
+        quad.region = RegionMask::Quadrant(1);
+        sim.apply_perturbation_spec(&quad);
+        for (i, m) in meta.iter().enumerate() {
+            let in_quad = i % g >= g / 2 && i / g < g / 2;
+            assert_eq!(sim.s_field[i], if in_quad { 3 } else { *m });
+        }
+    }
+
+    #[test]
+    fn test_perturbation_report_energy_matches_energy_diff() {
+        let mut sim = Sim::new(1, 1);
+        let g = 4usize;
+        let layers = 2usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = layers as u16;
+        sim.params.l_s = 6;
+        sim.params.eta = 0.7;
+        sim.s_field = vec![0u8; g * g];
+        sim.resize_meta_arrays();
+        let mut rng = Lcg::new(99);
+        fill_random_fields(&mut sim, &mut rng);
+
+        let energy = |sim: &Sim| {
+            sim.energy_breakdown_inner().6 as f64
+                + coupling_energy_s(&sim.params, g, layers, &sim.s_field, &sim.meta_field)
+        };
+        let e_before = energy(&sim);
+        let meta_before = sim.meta_field.clone();
+        let report = sim.apply_perturbation_spec(&PerturbSpec {
+            level: 1,
+            mode: PerturbMode::Randomize,
+            frac: 0.5,
+            region: RegionMask::All,
+            source: None,
+            seed: 11,
+        });
+        let e_after = energy(&sim);
+
+        assert!(!report.indices.is_empty());
+        let changed = meta_before
+            .iter()
+            .zip(&sim.meta_field)
+            .filter(|(a, b)| a != b)
+            .count();
+        assert_eq!(report.changed as usize, changed);
+        assert!((e_after - e_before - report.energy_delta).abs() < 1e-3);
+        assert!((sim.intervention_energy_total - report.energy_delta).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_per_interface_params_match_energy_diff() {
+        let mut sim = Sim::new(1, 1);
+        let g = 4usize;
+        let layers = 3usize;
+        let cells = g * g;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = layers as u16;
+        sim.params.l_s = 6;
+        sim.params.l_a = 5;
+        sim.params.eta = 0.7;
+        sim.eta_by_interface = vec![0.9, 0.4];
+        sim.lambda_s_by_level = vec![0.0, 0.3, 0.05, 0.2];
+        sim.params.j_s = 0.15;
+        sim.params.s_lateral_stencil = 1;
+        sim.s_field = vec![0u8; cells];
+        sim.resize_meta_arrays();
+        let mut rng = Lcg::new(4321);
+        fill_random_fields(&mut sim, &mut rng);
+
+        let energy = |sim: &Sim| -> f64 {
+            let mut e = sim.energy_breakdown_inner().5 as f64;
+            for interface in 0..layers {
+                let eta = sim.eta_at(interface) as f64;
+                let (lo, hi) = (sim.s_level(interface), sim.s_level(interface + 1));
+                for (a, b) in lo.iter().zip(hi) {
+                    let d = (*b as f64 - *a as f64) / 6.0;
+                    e += 0.5 * eta * d * d;
+                }
+                if interface > 0 {
+                    let lo = &sim.meta_a_field[(interface - 1) * cells..interface * cells];
+                    let hi = &sim.meta_a_field[interface * cells..(interface + 1) * cells];
+                    for (a, b) in lo.iter().zip(hi) {
+                        let d = (*b as f64 - *a as f64) / 5.0;
+                        e += 0.5 * eta * d * d;
+                    }
+                }
+            }
+            e
+        };
+        for level in 0..=layers {
+            let idx = rng.next_usize(cells);
+            let s0 = sim.s_level(level)[idx];
+            let s1 = propose_u8(&mut rng, sim.params.l_s, s0);
+            let delta = sim.delta_e_s_level(level, idx, s0, s1) as f64;
+            let e_before = energy(&sim);
+            sim.s_level_mut(level)[idx] = s1;
+            assert!((energy(&sim) - e_before - delta).abs() < 1e-4);
+        }
+        for layer in 0..layers {
+            let idx = rng.next_usize(cells);
+            let a0 = sim.meta_a_field[layer * cells + idx];
+            let a1 = propose_u16(&mut rng, sim.params.l_a, a0);
+            let delta = sim.delta_e_meta_a_couple(layer, idx, a0, a1) as f64;
+            let e_before = energy(&sim);
+            sim.meta_a_field[layer * cells + idx] = a1;
+            assert!((energy(&sim) - e_before - delta).abs() < 1e-4);
+        }
+
+        // Mixed stencils share the full-stencil stride; budgets are per interface.
+        sim.params.op_coupling_on = true;
+        sim.op_stencil_by_interface = vec![0, 1, 0];
+        sim.op_budget_by_interface = vec![8, 12, 5];
+        sim.init_op_k();
+        let stride = sim.op_r_count_internal();
+        assert_eq!(stride, OP_STENCIL_FULL.len());
+        for (i, cell) in sim.op_k.chunks(stride).enumerate() {
+            let interface = i / cells;
+            let total: u32 = cell.iter().map(|k| *k as u32).sum();
+            assert_eq!(total, sim.op_budget_at(interface) as u32);
+            if sim.op_stencil_at(interface) == 0 {
+                assert!(cell[OP_STENCIL_CROSS.len()..].iter().all(|k| *k == 0));
+            }
+        }
+    }
+
+    // Normalised values of one grid-shaped plane of a meta field (0 = a, 1 = n, 2.. = w dir).
+    fn meta_plane(sim: &Sim, field: usize, layer: usize) -> Vec<f64> {
+        let start = sim.meta_offset(layer);
+        let cells = sim.level_cells(layer + 1);
+        match field {
+            0 => sim.meta_a_field[start..start + cells]
+                .iter()
+                .map(|a| *a as f64 / sim.params.l_a as f64)
+                .collect(),
+            1 => sim.meta_n_field[start..start + cells]
+                .iter()
+                .map(|n| *n as f64 / sim.params.l_n as f64)
+                .collect(),
+            dir => {
+                let start = sim.meta_edge_offset(layer) + (dir - 2) * cells;
+                sim.meta_w_edges[start..start + cells]
+                    .iter()
+                    .map(|w| *w as f64 / sim.params.l_w as f64)
+                    .collect()
+            }
+        }
+    }
+
+    fn block_means(values: &[f64], fine: usize, coarse: usize) -> Vec<f64> {
+        let mut sums = vec![(0.0, 0.0); coarse * coarse];
+        for (c, v) in values.iter().enumerate() {
+            let bin = &mut sums[coarse_cell(c, fine, coarse)];
+            bin.0 += v;
+            bin.1 += 1.0;
+        }
+        sums.iter().map(|(sum, n)| sum / n).collect()
+    }
+
+    // S energy with block-mean couplings: mismatch (mode 0) or op-K prediction (mode 1).
+    fn downsampled_s_energy(sim: &Sim) -> f64 {
+        let mut e = sim.energy_breakdown_inner().5 as f64;
+        let l_s = sim.params.l_s as f64;
+        for interface in 0..sim.params.meta_layers as usize {
+            let norm = |level: usize| -> Vec<f64> { sim.s_level(level).iter().map(|s| *s as f64 / l_s).collect() };
+            let (fine, coarse) = (sim.level_grid(interface), sim.level_grid(interface + 1));
+            let lower = block_means(&norm(interface), fine, coarse);
+            let upper = norm(interface + 1);
+            let eta = sim.eta_at(interface) as f64;
+            for (q, u) in upper.iter().enumerate() {
+                let pred = if sim.params.s_coupling_mode == 0 {
+                    lower[q]
+                } else {
+                    let budget = sim.op_budget_at(interface) as f64;
+                    sim.op_offsets_at(interface)
+                        .iter()
+                        .enumerate()
+                        .map(|(r, (dx, dy))| {
+                            let k = sim.op_k[sim.op_k_index(interface, q, r)] as f64;
+                            k / budget * lower[Sim::offset_index(q, *dx, *dy, coarse)]
+                        })
+                        .sum()
+                };
+                e += 0.5 * eta * (u - pred).powi(2);
+            }
+        }
+        e
+    }
+
+    fn downsampled_meta_energy(sim: &Sim, field: usize) -> f64 {
+        let mut e = 0.0;
+        for layer in 1..sim.params.meta_layers as usize {
+            let (fine, coarse) = (sim.level_grid(layer), sim.level_grid(layer + 1));
+            let planes: &[usize] = if field < 2 { &[field] } else { &[2, 3] };
+            for plane in planes {
+                let lower = block_means(&meta_plane(sim, *plane, layer - 1), fine, coarse);
+                let upper = meta_plane(sim, *plane, layer);
+                let eta = sim.eta_at(layer) as f64;
+                e += lower.iter().zip(&upper).map(|(m, u)| 0.5 * eta * (u - m).powi(2)).sum::<f64>();
+            }
+        }
+        e
+    }
+
+    #[test]
+    fn test_downsampled_layers_match_block_energy() {
+        let mut sim = Sim::new(1, 3);
+        let g = 7usize;
+        let layers = 3usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = layers as u16;
+        sim.params.meta_downsample = 2;
+        sim.params.l_s = 6;
+        sim.params.l_a = 5;
+        sim.params.l_n = 4;
+        sim.params.l_w = 3;
+        sim.params.eta = 0.6;
+        sim.eta_by_interface = vec![0.9, 0.5, 0.3];
+        sim.s_field = vec![0u8; g * g];
+        sim.resize_meta_arrays();
+        // 7 -> 3 (uneven 3/2/2 blocks) -> 1 -> 1.
+        assert_eq!((0..=layers).map(|l| sim.level_grid(l)).collect::<Vec<_>>(), vec![7, 3, 1, 1]);
+        assert_eq!(sim.meta_field.len(), 11);
+        assert_eq!(sim.meta_w_edges.len(), 22);
+        let mut rng = Lcg::new(99);
+        fill_random_fields(&mut sim, &mut rng);
+
+        for _ in 0..40 {
+            let level = rng.next_usize(layers + 1);
+            let idx = rng.next_usize(sim.level_cells(level));
+            let s0 = sim.s_level(level)[idx];
+            let s1 = propose_u8(&mut rng, sim.params.l_s, s0);
+            let delta = sim.delta_e_s_level(level, idx, s0, s1) as f64;
+            let before = downsampled_s_energy(&sim);
+            sim.s_level_mut(level)[idx] = s1;
+            assert!((downsampled_s_energy(&sim) - before - delta).abs() < 1e-4);
+        }
+        for _ in 0..20 {
+            let layer = rng.next_usize(layers);
+            let cells = sim.level_cells(layer + 1);
+            let idx = rng.next_usize(cells);
+            let at = sim.meta_offset(layer) + idx;
+
+            let (a0, n0) = (sim.meta_a_field[at], sim.meta_n_field[at]);
+            let a1 = propose_u16(&mut rng, sim.params.l_a, a0);
+            let n1 = propose_i16(&mut rng, sim.params.l_n, n0);
+            let (da, dn) = (sim.delta_e_meta_a_couple(layer, idx, a0, a1), sim.delta_e_meta_n_couple(layer, idx, n0, n1));
+            let (ea, en) = (downsampled_meta_energy(&sim, 0), downsampled_meta_energy(&sim, 1));
+            sim.meta_a_field[at] = a1;
+            sim.meta_n_field[at] = n1;
+            assert!((downsampled_meta_energy(&sim, 0) - ea - da as f64).abs() < 1e-4);
+            assert!((downsampled_meta_energy(&sim, 1) - en - dn as f64).abs() < 1e-4);
+
+            let edge = rng.next_usize(2 * cells);
+            let at = sim.meta_edge_offset(layer) + edge;
+            let w0 = sim.meta_w_edges[at];
+            let w1 = propose_u8(&mut rng, sim.params.l_w, w0);
+            let dw = sim.delta_e_meta_w_couple(layer, edge, w0, w1) as f64;
+            let ew = downsampled_meta_energy(&sim, 2);
+            sim.meta_w_edges[at] = w1;
+            assert!((downsampled_meta_energy(&sim, 2) - ew - dw).abs() < 1e-4);
+        }
+
+        // op-K coupling: stencils act on the upper grid over block means of the lower one.
+        sim.params.op_coupling_on = true;
+        sim.params.s_coupling_mode = 1;
+        sim.params.op_stencil = 1;
+        sim.init_op_k();
+        assert_eq!(sim.op_k.len(), 11 * OP_STENCIL_FULL.len());
+        for _ in 0..40 {
+            let interface = rng.next_usize(layers);
+            let q = rng.next_usize(sim.level_cells(interface + 1));
+            let r_from = rng.next_usize(OP_STENCIL_FULL.len());
+            let r_to = (r_from + 1 + rng.next_usize(OP_STENCIL_FULL.len() - 1)) % OP_STENCIL_FULL.len();
+            let (from, to) = (sim.op_k_index(interface, q, r_from), sim.op_k_index(interface, q, r_to));
+            if sim.op_k[from] == 0 {
+                continue;
+            }
+            let delta = (sim.eta_at(interface) * sim.delta_raw_k_op(interface, q, r_from, r_to)) as f64;
+            let before = downsampled_s_energy(&sim);
+            sim.op_k[from] -= 1;
+            sim.op_k[to] += 1;
+            assert!((downsampled_s_energy(&sim) - before - delta).abs() < 1e-4);
+        }
+        for _ in 0..40 {
+            let level = rng.next_usize(layers + 1);
+            let idx = rng.next_usize(sim.level_cells(level));
+            let s0 = sim.s_level(level)[idx];
+            let s1 = propose_u8(&mut rng, sim.params.l_s, s0);
+            let delta = sim.delta_e_s_level(level, idx, s0, s1) as f64;
+            let before = downsampled_s_energy(&sim);
+            sim.s_level_mut(level)[idx] = s1;
+            assert!((downsampled_s_energy(&sim) - before - delta).abs() < 1e-4);
+        }
+        let lifted = sim.s_level_lifted(1).into_owned();
+        assert_eq!(lifted.len(), g * g);
+        assert_eq!(lifted[g * g - 1], sim.s_level(1)[8]);
+        assert_eq!(sim.project_from_base(&lifted, 1), sim.s_level(1));
+    }
 }
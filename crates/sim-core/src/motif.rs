@@ -0,0 +1,277 @@
+use js_sys::{Uint16Array, Uint32Array};
+use wasm_bindgen::prelude::*;
+
+use crate::Sim;
+
+// Motif modes mirror `opBinsMode` in scripts/opk-motif-basis.mjs:
+// 0 = five axis-mass bins (centre, +x, -x, +y, -y), 1 = dir9 argmax, 2 = dir9 argmax x entropy bin.
+pub(crate) const MOTIF_MODE_AXIS: u8 = 0;
+pub(crate) const MOTIF_MODE_DIR9: u8 = 1;
+
+// Number of token-only motif labels; the full class multiplies this by the 3 mismatch bins.
+pub(crate) fn motif_token_states(mode: u8) -> usize {
+    match mode {
+        MOTIF_MODE_AXIS => 243,
+        MOTIF_MODE_DIR9 => 9,
+        _ => 27,
+    }
+}
+
+fn bin_by_thresholds(value: f64, t1: f64, t2: f64) -> u16 {
+    if value <= t1 {
+        0
+    } else if value <= t2 {
+        1
+    } else {
+        2
+    }
+}
+
+fn dir9_slot(dx: i32, dy: i32) -> usize {
+    match (dx.signum(), dy.signum()) {
+        (0, 0) => 0,
+        (1, 0) => 1,
+        (-1, 0) => 2,
+        (0, -1) => 3,
+        (0, 1) => 4,
+        (1, -1) => 5,
+        (-1, -1) => 6,
+        (1, 1) => 7,
+        _ => 8,
+    }
+}
+
+// Token-only motif label of one cell's op-K vector (the stencil part of `computeMOpClasses`).
+pub(crate) fn token_motif(tokens: &[u8], offsets: &[(i32, i32)], budget: u8, mode: u8) -> u16 {
+    if mode == MOTIF_MODE_AXIS {
+        let denom = budget.max(1) as f64;
+        let mut mass = [0u32; 5];
+        for (count, (dx, dy)) in tokens.iter().zip(offsets) {
+            let slot = if *dx == 0 && *dy == 0 {
+                0
+            } else if dx.abs() >= dy.abs() {
+                if *dx >= 0 {
+                    1
+                } else {
+                    2
+                }
+            } else if *dy >= 0 {
+                3
+            } else {
+                4
+            };
+            mass[slot] += *count as u32;
+        }
+        return mass.iter().rev().fold(0u16, |acc, m| {
+            acc * 3 + bin_by_thresholds(*m as f64 / denom, 1.0 / 6.0, 2.0 / 6.0)
+        });
+    }
+    let denom = budget.max(1) as f64;
+    let mut masses = [0.0f64; 9];
+    for (count, (dx, dy)) in tokens.iter().zip(offsets) {
+        if *count > 0 {
+            masses[dir9_slot(*dx, *dy)] += *count as f64 / denom;
+        }
+    }
+    let mut argmax = 0usize;
+    for (i, m) in masses.iter().enumerate().skip(1) {
+        if *m > masses[argmax] {
+            argmax = i;
+        }
+    }
+    if mode == MOTIF_MODE_DIR9 {
+        return argmax as u16;
+    }
+    let sum: f64 = masses.iter().sum();
+    let mut h = 0.0;
+    if sum > 0.0 {
+        for m in masses {
+            let p = m / sum;
+            if p > 0.0 {
+                h -= p * p.ln();
+            }
+        }
+    }
+    let h_norm = h / 9f64.ln();
+    let h_bin = if h_norm < 0.33 {
+        0
+    } else if h_norm < 0.66 {
+        1
+    } else {
+        2
+    };
+    (argmax + 9 * h_bin) as u16
+}
+
+// Combines the token motif with the sign of (upper - lower), matching the JS class ids.
+pub(crate) fn full_motif_class(mode: u8, mismatch: u16, token: u16) -> u16 {
+    match mode {
+        MOTIF_MODE_DIR9 => mismatch * 9 + token,
+        _ => mismatch + 3 * token,
+    }
+}
+
+#[wasm_bindgen]
+impl Sim {
+    pub fn op_motif_state_count(&self) -> u32 {
+        motif_token_states(self.params.op_motif_mode) as u32
+    }
+
+    /// Token-only motif label per cell for one interface, kept current on every op-K change.
+    pub fn op_motif_labels(&self, interface: u32) -> Uint16Array {
+        let interface = interface as usize;
+        if interface >= self.interface_count() {
+            return Uint16Array::new_with_length(0);
+        }
+        let cells = self.op_cells(interface);
+        let start = self.op_offset(interface);
+        match self.op_motif.get(start..start + cells) {
+            Some(labels) => Uint16Array::from(labels),
+            None => Uint16Array::new_with_length(0),
+        }
+    }
+
+    /// Full `computeMOpClasses` ids (token motif plus lower/upper mismatch sign) for one interface.
+    pub fn op_motif_classes(&self, interface: u32) -> Uint16Array {
+        let interface = interface as usize;
+        if interface >= self.interface_count() {
+            return Uint16Array::new_with_length(0);
+        }
+        let cells = self.op_cells(interface);
+        let start = self.op_offset(interface);
+        let Some(labels) = self.op_motif.get(start..start + cells) else {
+            return Uint16Array::new_with_length(0);
+        };
+        let (lower, upper) = self.interface_levels(interface);
+        let upper_s = self.s_level(upper);
+        let classes: Vec<u16> = labels
+            .iter()
+            .enumerate()
+            .map(|(q, token)| {
+                let mismatch = match upper_s[q].cmp(&self.s_block_mean(lower, upper, q)) {
+                    std::cmp::Ordering::Less => 0,
+                    std::cmp::Ordering::Equal => 1,
+                    std::cmp::Ordering::Greater => 2,
+                };
+                full_motif_class(self.params.op_motif_mode, mismatch, *token)
+            })
+            .collect();
+        Uint16Array::from(classes.as_slice())
+    }
+
+    /// Row-major `from * states + to` counts of token-motif changes for one interface.
+    pub fn op_motif_transitions(&self, interface: u32) -> Uint32Array {
+        let states = motif_token_states(self.params.op_motif_mode);
+        let start = interface as usize * states * states;
+        match self.op_motif_trans.get(start..start + states * states) {
+            Some(counts) => Uint32Array::from(counts),
+            None => Uint32Array::new_with_length(0),
+        }
+    }
+
+    pub fn reset_op_motif_transitions(&mut self) {
+        self.op_motif_trans.iter_mut().for_each(|c| *c = 0);
+    }
+}
+
+impl Sim {
+    fn cell_token_motif(&self, interface: usize, q: usize) -> u16 {
+        let r_count = self.op_r_count_at(interface);
+        let start = self.op_k_index(interface, q, 0);
+        token_motif(
+            &self.op_k[start..start + r_count],
+            self.op_offsets_at(interface),
+            self.op_budget_at(interface),
+            self.params.op_motif_mode,
+        )
+    }
+
+    // Relabels every cell; transition counts survive unless the label space changed shape.
+    pub(crate) fn refresh_op_motifs(&mut self) {
+        let interfaces = self.interface_count();
+        if !self.params.op_motif_on || self.op_k.is_empty() || interfaces == 0 {
+            self.op_motif.clear();
+            self.op_motif_trans.clear();
+            return;
+        }
+        self.op_motif = (0..interfaces)
+            .flat_map(|interface| (0..self.op_cells(interface)).map(move |q| (interface, q)))
+            .map(|(interface, q)| self.cell_token_motif(interface, q))
+            .collect();
+        let states = motif_token_states(self.params.op_motif_mode);
+        if self.op_motif_trans.len() != interfaces * states * states {
+            self.op_motif_trans = vec![0u32; interfaces * states * states];
+        }
+    }
+
+    // Called after any change to one cell's op-K tokens.
+    pub(crate) fn update_op_motif(&mut self, interface: usize, q: usize) {
+        if self.op_motif.is_empty() {
+            return;
+        }
+        let idx = self.op_offset(interface) + q;
+        let from = self.op_motif[idx] as usize;
+        let to = self.cell_token_motif(interface, q);
+        if from == to as usize {
+            return;
+        }
+        self.op_motif[idx] = to;
+        let states = motif_token_states(self.params.op_motif_mode);
+        self.op_motif_trans[(interface * states + from) * states + to as usize] += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_motif_matches_js_classes() {
+        let cross: [(i32, i32); 5] = [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)];
+        // All mass on +x: dir9 slot 1, zero entropy.
+        let tokens = [0u8, 16, 0, 0, 0];
+        assert_eq!(token_motif(&tokens, &cross, 16, 1), 1);
+        assert_eq!(token_motif(&tokens, &cross, 16, 2), 1);
+        assert_eq!(full_motif_class(2, 2, 1), 5);
+        // Uniform over five slots: argmax stays at the centre, entropy ln5/ln9 ~ 0.73.
+        let uniform = [4u8, 3, 3, 3, 3];
+        assert_eq!(token_motif(&uniform, &cross, 16, 2), 18);
+        // Axis bins (centre, +x, -x, +y, -y) = (1, 1, 1, 1, 1) under thresholds 1/6, 2/6.
+        assert_eq!(token_motif(&uniform, &cross, 16, 0), 121);
+    }
+
+    #[test]
+    fn test_op_motif_updates_incrementally_on_opk_moves() {
+        let mut sim = Sim::new(1, 11);
+        let g = 4usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 1;
+        sim.params.op_coupling_on = true;
+        sim.params.op_motif_on = true;
+        sim.params.p_write = 0.0;
+        sim.params.p_n_write = 0.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_s_write = 1.0;
+        sim.s_field = vec![0u8; g * g];
+        sim.resize_meta_arrays();
+        sim.init_op_k();
+        sim.refresh_op_motifs();
+        let mut snapshot = sim.op_motif.clone();
+        let mut expected = vec![0u32; sim.op_motif_trans.len()];
+        let states = motif_token_states(sim.params.op_motif_mode);
+        for _ in 0..400 {
+            sim.step(1);
+            for (q, (prev, now)) in snapshot.iter().zip(&sim.op_motif).enumerate() {
+                if prev != now {
+                    expected[(q / (g * g) * states + *prev as usize) * states + *now as usize] += 1;
+                }
+            }
+            snapshot = sim.op_motif.clone();
+        }
+        let labels = sim.op_motif.clone();
+        sim.refresh_op_motifs();
+        assert_eq!(labels, sim.op_motif);
+        assert_eq!(expected, sim.op_motif_trans);
+        assert!(expected.iter().sum::<u32>() > 0);
+    }
+}
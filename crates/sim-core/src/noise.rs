@@ -0,0 +1,315 @@
+use js_sys::{Float64Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{Sim, MAX_S_LEVELS};
+
+// Model 0 (default) resets hit cells to a uniform value in 0..=l_s.
+pub(crate) const NOISE_MODEL_DRIFT: u8 = 1;
+pub(crate) const NOISE_MODEL_FLIP: u8 = 2;
+
+// Cumulative code-noise accounting, indexed by S level (0 = base, 1.. = meta layers).
+#[derive(Clone, Copy, Default)]
+pub(crate) struct CodeNoiseStats {
+    pub(crate) events: [u64; MAX_S_LEVELS],
+    pub(crate) cells_hit: [u64; MAX_S_LEVELS],
+    pub(crate) cells_changed: [u64; MAX_S_LEVELS],
+    pub(crate) abs_change: [u64; MAX_S_LEVELS],
+    pub(crate) energy_delta: f64,
+    pub(crate) opk_events: u64,
+    pub(crate) opk_moved: u64,
+}
+
+#[wasm_bindgen]
+impl Sim {
+    pub fn code_noise_stats(&self) -> Object {
+        let levels = (self.params.meta_layers as usize + 1).min(MAX_S_LEVELS);
+        let to_f64 = |v: &[u64; MAX_S_LEVELS]| -> Vec<f64> {
+            v[..levels].iter().map(|x| *x as f64).collect()
+        };
+        let stats = &self.code_noise_stats;
+        let o = Object::new();
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("events"),
+            &Float64Array::from(to_f64(&stats.events).as_slice()),
+        );
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("cellsHit"),
+            &Float64Array::from(to_f64(&stats.cells_hit).as_slice()),
+        );
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("cellsChanged"),
+            &Float64Array::from(to_f64(&stats.cells_changed).as_slice()),
+        );
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("absChange"),
+            &Float64Array::from(to_f64(&stats.abs_change).as_slice()),
+        );
+        let _ = Reflect::set(&o, &JsValue::from_str("energyDelta"), &JsValue::from_f64(stats.energy_delta));
+        let _ = Reflect::set(&o, &JsValue::from_str("opkEvents"), &JsValue::from_f64(stats.opk_events as f64));
+        let _ = Reflect::set(&o, &JsValue::from_str("opkMoved"), &JsValue::from_f64(stats.opk_moved as f64));
+        o
+    }
+}
+
+impl Sim {
+    // Noise events so far on every level and on op-K; a change means the state was hit.
+    pub(crate) fn code_noise_event_count(&self) -> u64 {
+        let stats = &self.code_noise_stats;
+        stats.events.iter().sum::<u64>() + stats.opk_events
+    }
+
+    pub(crate) fn maybe_code_noise(&mut self) {
+        let layers = self.params.meta_layers as usize;
+        if !self.code_noise_rates.is_empty() {
+            let levels = self.code_noise_rates.len().min(layers + 1);
+            for level in 0..levels {
+                let rate = self.code_noise_rates[level];
+                if rate > 0.0 && self.rand01() < rate {
+                    self.code_noise_event(level);
+                }
+            }
+        } else if self.params.code_noise_rate > 0.0
+            && layers > 0
+            && self.rand01() < self.params.code_noise_rate
+        {
+            let layer = self.params.code_noise_layer as usize;
+            if layer < layers {
+                self.code_noise_event(layer + 1);
+            }
+        }
+        if self.params.code_noise_opk_rate > 0.0
+            && !self.op_k.is_empty()
+            && self.rand01() < self.params.code_noise_opk_rate
+        {
+            self.code_noise_opk_event();
+        }
+    }
+
+    fn code_noise_event(&mut self, level: usize) {
+        if self.params.grid_size == 0 || level >= MAX_S_LEVELS {
+            return;
+        }
+        // Region cells are base-grid cells; a downsampled level is hit in the cell above them.
+        let g = self.level_grid(level);
+        let cells = g * g;
+        let batch = self.params.code_noise_batch.max(1) as usize;
+        let restricted = self.code_noise_region.is_some();
+        if restricted && self.code_noise_cells.is_empty() {
+            return;
+        }
+        let radius = self.params.code_noise_burst as i32;
+        let changed_before = self.code_noise_stats.cells_changed[level];
+        for _ in 0..batch {
+            let center = if restricted {
+                let pick = (self.rand_u32() as usize) % self.code_noise_cells.len();
+                self.ancestor_cell(self.code_noise_cells[pick] as usize, 0, level)
+            } else {
+                (self.rand_u32() as usize) % cells
+            };
+            if radius == 0 {
+                self.code_noise_hit(level, center);
+                continue;
+            }
+            // Correlated burst: a (2r+1)^2 patch around the centre, clipped to the region. When
+            // the patch is wider than the grid it wraps onto itself; each cell is hit once.
+            let mut patch: Vec<usize> = Vec::new();
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let idx = Self::offset_index(center, dx, dy, g);
+                    let inside = match &self.code_noise_region {
+                        Some(region) => region.contains_cell(idx, g),
+                        None => true,
+                    };
+                    if inside && !patch.contains(&idx) {
+                        patch.push(idx);
+                    }
+                }
+            }
+            for idx in patch {
+                self.code_noise_hit(level, idx);
+            }
+        }
+        self.code_noise_stats.events[level] += 1;
+        if self.code_noise_stats.cells_changed[level] != changed_before {
+            self.intervention_count = self.intervention_count.saturating_add(1);
+        }
+    }
+
+    fn code_noise_hit(&mut self, level: usize, idx: usize) {
+        let l_s = self.params.l_s;
+        let s0 = self.s_level(level)[idx];
+        let s1 = match self.params.code_noise_model {
+            NOISE_MODEL_DRIFT => {
+                if self.rand01() < 0.5 {
+                    s0.saturating_add(1).min(l_s)
+                } else {
+                    s0.saturating_sub(1)
+                }
+            }
+            NOISE_MODEL_FLIP => l_s - s0.min(l_s),
+            _ => (self.rand_u32() % (l_s as u32 + 1)) as u8,
+        };
+        self.code_noise_stats.cells_hit[level] += 1;
+        if s1 == s0 {
+            return;
+        }
+        let d_e = self.delta_e_s_level(level, idx, s0, s1) as f64;
+        self.s_level_mut(level)[idx] = s1;
+        if level == 0 {
+            self.sum_s += (s1 as i32) - (s0 as i32);
+        }
+        self.code_noise_stats.cells_changed[level] += 1;
+        self.code_noise_stats.abs_change[level] += ((s1 as i32) - (s0 as i32)).unsigned_abs() as u64;
+        self.code_noise_stats.energy_delta += d_e;
+        self.intervention_energy_total += d_e;
+    }
+
+    // Moves single op-K token units between stencil slots; the per-cell budget is preserved.
+    fn code_noise_opk_event(&mut self) {
+        let interfaces = self.interface_count();
+        let r_count = self.op_r_count_internal();
+        if interfaces == 0 || self.params.grid_size == 0 || r_count < 2 {
+            return;
+        }
+        let restricted = self.code_noise_region.is_some();
+        if restricted && self.code_noise_cells.is_empty() {
+            return;
+        }
+        let batch = self.params.code_noise_batch.max(1) as usize;
+        let moved_before = self.code_noise_stats.opk_moved;
+        for _ in 0..batch {
+            let interface = (self.rand_u32() as usize) % interfaces;
+            if !self.interface_has_op(interface) {
+                continue;
+            }
+            let upper = self.interface_levels(interface).1;
+            let q = if restricted {
+                let pick = (self.rand_u32() as usize) % self.code_noise_cells.len();
+                self.ancestor_cell(self.code_noise_cells[pick] as usize, 0, upper)
+            } else {
+                (self.rand_u32() as usize) % self.level_cells(upper)
+            };
+            let r_count = self.op_r_count_at(interface);
+            let r_from = (self.rand_u32() as usize) % r_count;
+            let mut r_to = (self.rand_u32() as usize) % (r_count - 1);
+            if r_to >= r_from {
+                r_to += 1;
+            }
+            let idx_from = self.op_k_index(interface, q, r_from);
+            if self.op_k[idx_from] == 0 {
+                continue;
+            }
+            let idx_to = self.op_k_index(interface, q, r_to);
+            let d_e = if self.params.s_coupling_mode == 1 {
+                (self.eta_at(interface) * self.delta_raw_k_op(interface, q, r_from, r_to)) as f64
+            } else {
+                0.0
+            };
+            self.op_k[idx_from] -= 1;
+            self.op_k[idx_to] = self.op_k[idx_to].saturating_add(1);
+            self.update_op_motif(interface, q);
+            self.code_noise_stats.opk_moved += 1;
+            self.code_noise_stats.energy_delta += d_e;
+            self.intervention_energy_total += d_e;
+        }
+        self.code_noise_stats.opk_events += 1;
+        if self.code_noise_stats.opk_moved != moved_before {
+            self.intervention_count = self.intervention_count.saturating_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_noise_counts_and_energy_match_state_change() {
+        let mut sim = Sim::new(1, 3);
+        let g = 6usize;
+        let cells = g * g;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 2;
+        sim.params.l_s = 4;
+        sim.params.eta = 0.5;
+        sim.s_field = vec![1u8; cells];
+        sim.resize_meta_arrays();
+        sim.params.code_noise_model = NOISE_MODEL_FLIP;
+        sim.params.code_noise_burst = 1;
+        sim.code_noise_rates = vec![0.0, 1.0, 0.0];
+
+        let energy = |sim: &Sim| -> f64 {
+            let mut e = sim.energy_breakdown_inner().6 as f64;
+            let denom = sim.params.l_s as f64;
+            for level in 1..=2 {
+                let (lo, hi) = (sim.s_level(level - 1), sim.s_level(level));
+                for (a, b) in lo.iter().zip(hi) {
+                    let d = (*b as f64 - *a as f64) / denom;
+                    e += 0.5 * sim.params.eta as f64 * d * d;
+                }
+            }
+            e
+        };
+        let e_before = energy(&sim);
+        sim.maybe_code_noise();
+        let e_after = energy(&sim);
+
+        let stats = sim.code_noise_stats;
+        assert_eq!(stats.events[1], 1);
+        assert_eq!(stats.cells_hit[1], 9);
+        // Meta layer 0 starts at zero, so every flipped cell moves to l_s.
+        assert_eq!(stats.cells_changed[1], 9);
+        assert_eq!(stats.abs_change[1], 9 * 4);
+        assert_eq!(sim.s_level(1).iter().filter(|s| **s == 4).count(), 9);
+        assert_eq!(stats.events[0] + stats.events[2], 0);
+        assert!((e_after - e_before - stats.energy_delta).abs() < 1e-3);
+        assert_eq!(sim.intervention_count, 1);
+
+        // A burst wider than the grid wraps onto itself but hits each cell once.
+        sim.params.code_noise_burst = 4;
+        let level = sim.s_level(1).to_vec();
+        sim.maybe_code_noise();
+        assert_eq!(sim.code_noise_stats.cells_hit[1], 9 + cells as u64);
+        assert!(sim.s_level(1).iter().zip(&level).all(|(s, s0)| *s == 4 - *s0));
+        assert_eq!(sim.intervention_count, 2);
+
+        // A drift event that cannot move anything (all cells already at 0) is not counted.
+        sim.s_field = vec![0u8; cells];
+        sim.params.code_noise_burst = 0;
+        sim.params.code_noise_model = NOISE_MODEL_DRIFT;
+        sim.code_noise_rates = vec![1.0];
+        sim.params.l_s = 0;
+        sim.maybe_code_noise();
+        assert_eq!(sim.code_noise_stats.events[0], 1);
+        assert_eq!(sim.intervention_count, 2);
+    }
+
+    #[test]
+    fn test_opk_noise_preserves_token_budget() {
+        let mut sim = Sim::new(1, 5);
+        let g = 4usize;
+        sim.params.grid_size = g as u16;
+        sim.params.meta_layers = 1;
+        sim.params.op_coupling_on = true;
+        sim.s_field = vec![0u8; g * g];
+        sim.resize_meta_arrays();
+        sim.init_op_k();
+        sim.params.code_noise_opk_rate = 1.0;
+        sim.params.code_noise_batch = 20;
+        for _ in 0..10 {
+            sim.maybe_code_noise();
+        }
+        let r_count = sim.op_r_count_internal();
+        let budget = sim.params.op_budget_k as u32;
+        for cell in sim.op_k.chunks(r_count) {
+            assert_eq!(cell.iter().map(|k| *k as u32).sum::<u32>(), budget);
+        }
+        assert_eq!(sim.code_noise_stats.opk_events, 10);
+        assert!(sim.code_noise_stats.opk_moved > 0);
+        assert!((1..=10).contains(&sim.intervention_count));
+    }
+}
@@ -0,0 +1,141 @@
+use js_sys::Uint16Array;
+use wasm_bindgen::prelude::*;
+
+use crate::Sim;
+
+// Particle-field coupling: E = -kappaField * sum_k s(cell_k) / L_s over particles k, with
+// cell_k the base cell under particle k. kappaField > 0 draws particles toward high S and
+// makes S writes cheaper where particles sit; kappaField < 0 does the opposite. An S write
+// reads the particle count of its cell from `field_counts`, kept in step on every move.
+#[wasm_bindgen]
+impl Sim {
+    /// Number of particles in each base cell (row-major, `gridSize x gridSize`).
+    pub fn particle_counts(&self) -> Uint16Array {
+        Uint16Array::from(self.count_particles().as_slice())
+    }
+}
+
+impl Sim {
+    fn count_particles(&self) -> Vec<u16> {
+        let mut counts = vec![0u16; self.level_cells(0)];
+        if self.params.grid_size == 0 {
+            return counts;
+        }
+        for k in 0..self.n {
+            let cell = self.level_cell_at(0, self.positions[2 * k], self.positions[2 * k + 1]);
+            counts[cell] = counts[cell].saturating_add(1);
+        }
+        counts
+    }
+
+    // Rebuilds the per-cell particle counts (empty while the field coupling is off).
+    pub(crate) fn recompute_field_counts(&mut self) {
+        self.field_counts = if self.params.kappa_field != 0.0 && self.params.grid_size > 0 {
+            self.count_particles()
+        } else {
+            Vec::new()
+        };
+    }
+
+    // Moves particle k's count to the cell under (x, y); call before the move is written.
+    pub(crate) fn field_count_move(&mut self, k: usize, x: f32, y: f32) {
+        if self.field_counts.is_empty() || self.field_counts.len() != self.level_cells(0) {
+            return;
+        }
+        let from = self.level_cell_at(0, self.positions[2 * k], self.positions[2 * k + 1]);
+        let to = self.level_cell_at(0, x, y);
+        self.field_counts[from] = self.field_counts[from].saturating_sub(1);
+        self.field_counts[to] = self.field_counts[to].saturating_add(1);
+    }
+
+    fn field_norm_at(&self, x: f32, y: f32) -> f32 {
+        let s = self.s_field[self.level_cell_at(0, x, y)];
+        (s as f32) / self.params.l_s.max(1) as f32
+    }
+
+    pub(crate) fn particle_field_energy(&self) -> f32 {
+        if self.params.kappa_field == 0.0 || self.params.grid_size == 0 {
+            return 0.0;
+        }
+        let mut sum = 0.0;
+        for k in 0..self.n {
+            sum += self.field_norm_at(self.positions[2 * k], self.positions[2 * k + 1]);
+        }
+        -self.params.kappa_field * sum
+    }
+
+    // Field energy of a particle sitting at (x, y).
+    pub(crate) fn particle_field_at(&self, x: f32, y: f32) -> f32 {
+        if self.params.kappa_field == 0.0 || self.params.grid_size == 0 {
+            return 0.0;
+        }
+        -self.params.kappa_field * self.field_norm_at(x, y)
+    }
+
+    // ΔE of one particle moving from (x0, y0) to (x1, y1).
+    pub(crate) fn delta_e_particle_field_move(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> f32 {
+        if self.params.kappa_field == 0.0 || self.params.grid_size == 0 {
+            return 0.0;
+        }
+        -self.params.kappa_field * (self.field_norm_at(x1, y1) - self.field_norm_at(x0, y0))
+    }
+
+    // ΔE on the particles of base cell `idx` when it moves from s0 to s1.
+    pub(crate) fn delta_e_field_particles(&self, idx: usize, s0: u8, s1: u8) -> f32 {
+        if self.params.kappa_field == 0.0 || self.params.grid_size == 0 {
+            return 0.0;
+        }
+        let count = if self.field_counts.len() == self.level_cells(0) {
+            self.field_counts[idx] as usize
+        } else {
+            (0..self.n)
+                .filter(|k| self.level_cell_at(0, self.positions[2 * k], self.positions[2 * k + 1]) == idx)
+                .count()
+        };
+        let change = ((s1 as f32) - (s0 as f32)) / self.params.l_s.max(1) as f32;
+        -self.params.kappa_field * count as f32 * change
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assert_delta, Lcg};
+
+    #[test]
+    fn test_particle_field_deltas_match_energy() {
+        let mut sim = Sim::new(20, 3);
+        let g = 5usize;
+        sim.params.grid_size = g as u16;
+        sim.params.kappa_field = 1.5;
+        sim.s_field = (0..g * g).map(|i| (i * 5 % 7) as u8).collect();
+        sim.recompute_field_counts();
+        let mut rng = Lcg::new(41);
+        let energy = |sim: &Sim| sim.particle_field_energy() as f64;
+        for _ in 0..200 {
+            let i = rng.next_usize(sim.n);
+            let (x0, y0) = (sim.positions[2 * i], sim.positions[2 * i + 1]);
+            let (x1, y1) = (rng.next_f32(), rng.next_f32());
+            let delta = sim.delta_e_particle_field_move(x0, y0, x1, y1) as f64;
+            let apply = |sim: &mut Sim| {
+                sim.field_count_move(i, x1, y1);
+                sim.positions[2 * i..2 * i + 2].copy_from_slice(&[x1, y1]);
+            };
+            assert_delta(&mut sim, energy, delta, apply, 1e-4);
+
+            let idx = rng.next_usize(g * g);
+            let (s0, s1) = (sim.s_field[idx], rng.next_u8_range(sim.params.l_s));
+            let delta = (sim.delta_e_s_level(0, idx, s0, s1) - sim.delta_e_field(0, s0, s1)) as f64;
+            assert_delta(&mut sim, energy, delta, |sim| sim.s_field[idx] = s1, 1e-4);
+        }
+        let (u_rep, u_bond, e_w, e_n, e_a, e_s, total) = sim.energy_breakdown_inner();
+        let u_field = total - (u_rep + u_bond + e_w + e_n + e_a + e_s);
+        assert!((u_field - sim.particle_field_energy()).abs() < 1e-3);
+        assert!(sim.particle_field_energy() < 0.0);
+        // The cached counts followed every move; a zero-size grid has no cells to read.
+        assert_eq!(sim.field_counts, sim.count_particles());
+        sim.params.grid_size = 0;
+        sim.recompute_field_counts();
+        assert_eq!(sim.delta_e_field_particles(0, 0, 1), 0.0);
+    }
+}
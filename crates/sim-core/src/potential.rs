@@ -0,0 +1,335 @@
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{edge_index, get_f32, get_f32_vec, get_string, repulsion_energy, torus_dist, Sim};
+
+// Radial interaction shape on the torus (distances are in box units).
+pub(crate) trait PairPotential {
+    fn energy(&self, r: f32) -> f32;
+}
+
+// Soft harmonic wall below r0: the default particle repulsion (`kappaRep`, `r0`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SoftWall {
+    pub(crate) kappa: f32,
+    pub(crate) r0: f32,
+}
+
+impl PairPotential for SoftWall {
+    fn energy(&self, r: f32) -> f32 {
+        repulsion_energy(self.kappa, self.r0, r)
+    }
+}
+
+// Harmonic spring around r_star: the default per-unit-w bond shape (`kappaBond`, `rStar`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Harmonic {
+    pub(crate) kappa: f32,
+    pub(crate) r_star: f32,
+}
+
+impl PairPotential for Harmonic {
+    fn energy(&self, r: f32) -> f32 {
+        0.5 * self.kappa * (r - self.r_star).powi(2)
+    }
+}
+
+// Lennard-Jones truncated and shifted to zero at r_cut.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LennardJones {
+    pub(crate) epsilon: f32,
+    pub(crate) sigma: f32,
+    pub(crate) r_cut: f32,
+}
+
+// Distances below sigma / 2 are read as sigma / 2, capping the core at 16128 epsilon so
+// overlapping particles stay finite.
+fn lj(epsilon: f32, sigma: f32, r: f32) -> f32 {
+    let sr6 = (sigma / r.max(0.5 * sigma)).powi(6);
+    4.0 * epsilon * (sr6 * sr6 - sr6)
+}
+
+impl PairPotential for LennardJones {
+    fn energy(&self, r: f32) -> f32 {
+        if r >= self.r_cut {
+            return 0.0;
+        }
+        lj(self.epsilon, self.sigma, r) - lj(self.epsilon, self.sigma, self.r_cut)
+    }
+}
+
+// Weeks-Chandler-Andersen: the purely repulsive LJ core, cut at 2^(1/6) sigma and shifted by epsilon.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Wca {
+    pub(crate) epsilon: f32,
+    pub(crate) sigma: f32,
+}
+
+impl PairPotential for Wca {
+    fn energy(&self, r: f32) -> f32 {
+        if r >= 2f32.powf(1.0 / 6.0) * self.sigma {
+            return 0.0;
+        }
+        lj(self.epsilon, self.sigma, r) + self.epsilon
+    }
+}
+
+// Morse well of `depth` at r_eq with stiffness `alpha`, shifted to zero at r_cut.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Morse {
+    pub(crate) depth: f32,
+    pub(crate) alpha: f32,
+    pub(crate) r_eq: f32,
+    pub(crate) r_cut: f32,
+}
+
+fn morse(depth: f32, alpha: f32, r_eq: f32, r: f32) -> f32 {
+    let e = 1.0 - (-alpha * (r - r_eq)).exp();
+    depth * (e * e - 1.0)
+}
+
+impl PairPotential for Morse {
+    fn energy(&self, r: f32) -> f32 {
+        if r >= self.r_cut {
+            return 0.0;
+        }
+        morse(self.depth, self.alpha, self.r_eq, r) - morse(self.depth, self.alpha, self.r_eq, self.r_cut)
+    }
+}
+
+// User table sampled at r = i * r_max / (len - 1), linearly interpolated; the last value
+// holds beyond r_max.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Tabulated {
+    pub(crate) r_max: f32,
+    pub(crate) values: Vec<f32>,
+}
+
+impl PairPotential for Tabulated {
+    fn energy(&self, r: f32) -> f32 {
+        let last = self.values.len() - 1;
+        let t = (r / self.r_max).clamp(0.0, 1.0) * last as f32;
+        let i = (t as usize).min(last.saturating_sub(1));
+        let frac = t - i as f32;
+        match self.values.get(i + 1) {
+            Some(next) => self.values[i] * (1.0 - frac) + next * frac,
+            None => self.values[i],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Potential {
+    SoftWall(SoftWall),
+    Harmonic(Harmonic),
+    LennardJones(LennardJones),
+    Wca(Wca),
+    Morse(Morse),
+    Tabulated(Tabulated),
+}
+
+impl PairPotential for Potential {
+    fn energy(&self, r: f32) -> f32 {
+        match self {
+            Potential::SoftWall(p) => p.energy(r),
+            Potential::Harmonic(p) => p.energy(r),
+            Potential::LennardJones(p) => p.energy(r),
+            Potential::Wca(p) => p.energy(r),
+            Potential::Morse(p) => p.energy(r),
+            Potential::Tabulated(p) => p.energy(r),
+        }
+    }
+}
+
+impl Potential {
+    // `{ kind, ... }` with kind one of "softWall" (kappa, r0), "harmonic" (kappa, rStar),
+    // "lj" (epsilon, sigma, rCut), "wca" (epsilon, sigma), "morse" (depth, alpha, rEq, rCut)
+    // or "table" (rMax, values). Anything else (e.g. "default") yields None.
+    pub(crate) fn from_js(v: &JsValue) -> Option<Potential> {
+        if !v.is_object() {
+            return None;
+        }
+        let num = |key: &str, default: f32| get_f32(v, key).filter(|x| x.is_finite()).unwrap_or(default);
+        let positive = |key: &str, default: f32| num(key, default).max(1e-4);
+        let potential = match get_string(v, "kind")?.as_str() {
+            "softWall" => Potential::SoftWall(SoftWall {
+                kappa: num("kappa", 50.0).max(0.0),
+                r0: num("r0", 0.03).clamp(0.0, 0.5),
+            }),
+            "harmonic" => Potential::Harmonic(Harmonic {
+                kappa: num("kappa", 3.0).max(0.0),
+                r_star: num("rStar", 0.18).clamp(0.0, 0.5),
+            }),
+            "lj" => {
+                let sigma = positive("sigma", 0.05);
+                Potential::LennardJones(LennardJones {
+                    epsilon: num("epsilon", 1.0).max(0.0),
+                    sigma,
+                    r_cut: num("rCut", 2.5 * sigma).clamp(sigma, 0.5),
+                })
+            }
+            "wca" => Potential::Wca(Wca {
+                epsilon: num("epsilon", 1.0).max(0.0),
+                sigma: positive("sigma", 0.05),
+            }),
+            "morse" => Potential::Morse(Morse {
+                depth: num("depth", 1.0).max(0.0),
+                alpha: positive("alpha", 30.0),
+                r_eq: num("rEq", 0.1).clamp(0.0, 0.5),
+                r_cut: num("rCut", 0.5).clamp(0.0, 0.5),
+            }),
+            "table" => {
+                let values: Vec<f32> = get_f32_vec(v, "values")?.into_iter().filter(|x| x.is_finite()).collect();
+                if values.is_empty() {
+                    return None;
+                }
+                Potential::Tabulated(Tabulated {
+                    r_max: positive("rMax", 0.5),
+                    values,
+                })
+            }
+            _ => return None,
+        };
+        Some(potential)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Potential::SoftWall(_) => "softWall",
+            Potential::Harmonic(_) => "harmonic",
+            Potential::LennardJones(_) => "lj",
+            Potential::Wca(_) => "wca",
+            Potential::Morse(_) => "morse",
+            Potential::Tabulated(_) => "table",
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Active potential kinds as `{ pair, bond }`; "softWall" and "harmonic" are the defaults
+    /// driven by `kappaRep`/`r0` and `kappaBond`/`rStar`.
+    pub fn potentials(&self) -> Object {
+        let o = Object::new();
+        let pair = self.pair_potential.as_ref().map_or("softWall", |p| p.kind());
+        let bond = self.bond_potential.as_ref().map_or("harmonic", |p| p.kind());
+        let _ = Reflect::set(&o, &JsValue::from_str("pair"), &JsValue::from_str(pair));
+        let _ = Reflect::set(&o, &JsValue::from_str("bond"), &JsValue::from_str(bond));
+        o
+    }
+}
+
+impl Sim {
+    // Pair energy of particles i and j at distance r; the default soft wall takes its
+    // parameters from the species table when one is set.
+    pub(crate) fn pair_energy(&self, i: usize, j: usize, r: f32) -> f32 {
+        match &self.pair_potential {
+            Some(p) => p.energy(r),
+            None => {
+                let (kappa, r0) = self.repulsion_params(i, j);
+                repulsion_energy(kappa, r0, r)
+            }
+        }
+    }
+
+    // Bond energy per unit of w at distance r (total bond energy is w * shape).
+    pub(crate) fn bond_shape(&self, i: usize, j: usize, r: f32) -> f32 {
+        match &self.bond_potential {
+            Some(p) => p.energy(r),
+            None => {
+                let (kappa, r_star) = self.bond_params(i, j);
+                0.5 * kappa * (r - r_star).powi(2)
+            }
+        }
+    }
+
+    pub(crate) fn pair_energy_total(&self) -> (f32, f32) {
+        let mut u_rep = 0.0f32;
+        let mut u_bond = 0.0f32;
+        for i in 0..self.n {
+            for j in (i + 1)..self.n {
+                let r = torus_dist(
+                    self.positions[2 * i],
+                    self.positions[2 * i + 1],
+                    self.positions[2 * j],
+                    self.positions[2 * j + 1],
+                );
+                u_rep += self.pair_energy(i, j, r);
+                let w = self.w[edge_index(self.n, i, j)] as f32;
+                if w > 0.0 {
+                    u_bond += w * self.bond_shape(i, j, r);
+                }
+            }
+        }
+        (u_rep, u_bond)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_potential_shapes() {
+        let sigma = 0.05;
+        let lj = LennardJones { epsilon: 1.0, sigma, r_cut: 2.5 * sigma };
+        let r_min = 2f32.powf(1.0 / 6.0) * sigma;
+        assert!(lj.energy(r_min) < lj.energy(0.98 * r_min));
+        assert!(lj.energy(r_min) < lj.energy(1.02 * r_min));
+        assert_eq!(lj.energy(0.2), 0.0);
+        let wca = Wca { epsilon: 1.0, sigma };
+        assert!(wca.energy(0.999 * r_min).abs() < 1e-3);
+        assert_eq!(wca.energy(r_min), 0.0);
+        assert!(wca.energy(0.9 * sigma) > 1.0);
+        assert_eq!(wca.energy(0.0), wca.energy(0.5 * sigma));
+        assert!(lj.energy(0.0).is_finite() && lj.energy(1e-9) == lj.energy(0.5 * sigma));
+        let morse = Morse { depth: 2.0, alpha: 30.0, r_eq: 0.1, r_cut: 0.5 };
+        let shift = morse.energy(0.1) + 2.0;
+        assert!(shift.abs() < 1e-3);
+        assert!(morse.energy(0.1) < morse.energy(0.09) && morse.energy(0.1) < morse.energy(0.11));
+        let table = Tabulated { r_max: 0.2, values: vec![4.0, 2.0, 0.0] };
+        assert!((table.energy(0.05) - 3.0).abs() < 1e-6);
+        assert!((table.energy(0.15) - 1.0).abs() < 1e-6);
+        assert_eq!(table.energy(0.4), 0.0);
+    }
+
+    #[test]
+    fn test_custom_potentials_match_energy_diff() {
+        let mut sim = Sim::new(10, 21);
+        sim.pair_potential = Some(Potential::LennardJones(LennardJones { epsilon: 0.5, sigma: 0.08, r_cut: 0.2 }));
+        sim.bond_potential = Some(Potential::Morse(Morse { depth: 1.0, alpha: 20.0, r_eq: 0.15, r_cut: 0.5 }));
+        // Particles on a coarse lattice so no pair starts deep inside the LJ core.
+        for k in 0..sim.n {
+            sim.positions[2 * k] = (k % 4) as f32 * 0.25 + 0.1;
+            sim.positions[2 * k + 1] = (k / 4) as f32 * 0.3 + 0.1;
+        }
+        for (e, w) in sim.w.iter_mut().enumerate() {
+            *w = (e % 4) as u8;
+        }
+        let energy = |sim: &Sim| {
+            let (u_rep, u_bond, e_w, ..) = sim.energy_breakdown_inner();
+            (u_rep + u_bond + e_w) as f64
+        };
+        for step in 0..50 {
+            let i = step % sim.n;
+            let (x0, y0) = (sim.positions[2 * i], sim.positions[2 * i + 1]);
+            let shift = if step % 2 == 0 { 0.06 } else { -0.05 };
+            let (x1, y1) = ((x0 + shift + 1.0) % 1.0, (y0 + 0.5 * shift + 1.0) % 1.0);
+            let delta = sim.delta_e_move_particle(i, x0, y0, x1, y1) as f64;
+            let before = energy(&sim);
+            sim.positions[2 * i] = x1;
+            sim.positions[2 * i + 1] = y1;
+            assert!((energy(&sim) - before - delta).abs() < 1e-4 * (1.0 + before.abs()));
+
+            let j = (i + 1 + step % (sim.n - 1)) % sim.n;
+            let (a, b) = if i < j { (i, j) } else { (j, i) };
+            let e = edge_index(sim.n, a, b);
+            let (w0, w1) = (sim.w[e], (sim.w[e] + 1) % 5);
+            let r = torus_dist(sim.positions[2 * a], sim.positions[2 * a + 1], sim.positions[2 * b], sim.positions[2 * b + 1]);
+            let delta = sim.delta_e_write(w0, w1, (a, b), r) as f64;
+            let before = energy(&sim);
+            sim.w[e] = w1;
+            assert!((energy(&sim) - before - delta).abs() < 1e-4 * (1.0 + before.abs()));
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use js_sys::{Float64Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{edge_index, torus_dist, Sim, MOVE_REACT_H, MOVE_REACT_L};
+
+// A <-> B interconversion of particle species (`reactionA`, `reactionB`). A conversion of
+// particle k is only possible while k has a bonded partner (w > 0) within `reactionRadius`
+// whose species matches `reactionCatalyst` (255 = any); the partner is unchanged by the
+// conversion, so the catalysed proposal stays symmetric. With P6 on, A -> B takes +mu of the
+// reservoir at the particle's position and B -> A returns it; each reservoir has its own move
+// kind (ReactH, ReactL). Species energies (`species.energy`) set the intrinsic A/B offset.
+// Conversions that would exceed the target species' counter caps or put a bond on a
+// disallowed pair are rejected, and both species must exist in the species table.
+impl Sim {
+    fn reaction_catalysed(&self, k: usize) -> bool {
+        let (xk, yk) = (self.positions[2 * k], self.positions[2 * k + 1]);
+        (0..self.n).any(|j| {
+            if j == k || self.w[edge_index(self.n, k.min(j), k.max(j))] == 0 {
+                return false;
+            }
+            let catalyst = self.params.reaction_catalyst;
+            let r = torus_dist(xk, yk, self.positions[2 * j], self.positions[2 * j + 1]);
+            r <= self.params.reaction_radius && (catalyst == u8::MAX || self.species[j] == catalyst)
+        })
+    }
+
+    fn reaction_allowed(&self, k: usize) -> bool {
+        if self.a_counter[k] > self.l_a_of(k) || self.n_counter[k].abs() > self.l_n_of(k) {
+            return false;
+        }
+        (0..self.n).all(|j| j == k || self.w[edge_index(self.n, k.min(j), k.max(j))] == 0 || self.bond_allowed(k, j))
+    }
+
+    // Returns +1 for an accepted A -> B, -1 for B -> A, 0 otherwise.
+    pub(crate) fn reaction_step(&mut self) -> i8 {
+        if self.n == 0 {
+            return 0;
+        }
+        let k = (self.rand_u32() as usize) % self.n;
+        let (a, b) = (self.params.reaction_a, self.params.reaction_b);
+        let from = self.species[k];
+        let forward = from == a;
+        let count = self.species_count() as u8;
+        if a == b || a >= count || b >= count || (!forward && from != b) || !self.reaction_catalysed(k) {
+            return 0;
+        }
+        let e0 = self.particle_energy(k);
+        self.species[k] = if forward { b } else { a };
+        if !self.reaction_allowed(k) {
+            self.species[k] = from;
+            return 0;
+        }
+        let d_e = self.particle_energy(k) - e0;
+        let (x, y) = (self.positions[2 * k], self.positions[2 * k + 1]);
+        let high = x < 0.5;
+        let work = if self.params.p6_on {
+            let mu = self.mu_at(x, y);
+            if forward {
+                mu
+            } else {
+                -mu
+            }
+        } else {
+            0.0
+        };
+        let kind = if high { MOVE_REACT_H } else { MOVE_REACT_L };
+        if self.params.p6_on && forward && self.resource_exhausted(high) {
+            self.species[k] = from;
+            return 0;
+        }
+        if self.accept_move(d_e, work, 0.0, kind) {
+            if self.params.p6_on {
+                self.draw_resource(high, forward);
+            }
+            let slot = if high { 0 } else { 2 } + if forward { 0 } else { 1 };
+            self.reaction_counts[slot] = self.reaction_counts[slot].saturating_add(1);
+            return if forward { 1 } else { -1 };
+        }
+        self.species[k] = from;
+        0
+    }
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Accepted conversions as `[A->B high, B->A high, A->B low, B->A low]`.
+    pub fn reaction_stats(&self) -> Object {
+        let counts: Vec<f64> = self.reaction_counts.iter().map(|c| *c as f64).collect();
+        let o = Object::new();
+        let _ = Reflect::set(&o, &JsValue::from_str("counts"), &Float64Array::from(counts.as_slice()));
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("epHigh"),
+            &JsValue::from_f64(self.ep_exact_by_move[MOVE_REACT_H]),
+        );
+        let _ = Reflect::set(
+            &o,
+            &JsValue::from_str("epLow"),
+            &JsValue::from_f64(self.ep_exact_by_move[MOVE_REACT_L]),
+        );
+        o
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::species::SpeciesTable;
+
+    fn reacting_sim(seed: u32) -> Sim {
+        let mut sim = Sim::new(6, seed);
+        let table = SpeciesTable {
+            count: 2,
+            kappa_rep: None,
+            r0: None,
+            kappa_bond: Some(vec![3.0, 1.0, 1.0, 5.0]),
+            r_star: None,
+            energy: Some(vec![0.0, 0.7]),
+            l_a: None,
+            l_n: None,
+            bond_allowed: None,
+        };
+        sim.set_species_table(Some((table, Some(vec![0; 6]))));
+        sim.params.reaction_radius = 0.5;
+        sim.w.iter_mut().for_each(|w| *w = 0);
+        // Particles 0 and 1 are a bonded pair; the rest are never catalysed.
+        sim.w[edge_index(sim.n, 0, 1)] = 2;
+        sim
+    }
+
+    #[test]
+    fn test_reaction_deltas_match_energy_and_need_catalyst() {
+        let mut sim = reacting_sim(3);
+        let energy = |sim: &Sim| sim.energy_breakdown_inner().6 as f64;
+        let mut converted = 0;
+        for _ in 0..400 {
+            let before = energy(&sim);
+            let species: Vec<u8> = sim.species.clone();
+            let ep_before = sim.ep_naive_total;
+            let step = sim.reaction_step();
+            assert_eq!(sim.species[2..], species[2..]);
+            if step != 0 {
+                converted += 1;
+                // Without P6 the naive EP of an accepted move is -beta * ΔE.
+                let d_ep = sim.ep_naive_total - ep_before;
+                assert!((energy(&sim) - before + d_ep / sim.params.beta as f64).abs() < 1e-4 * (1.0 + before.abs()));
+            }
+        }
+        assert!(converted > 0);
+    }
+
+    #[test]
+    fn test_reaction_reservoirs_drive_conversion() {
+        // An isolated bonded pair in the high-mu half: the drive pushes it toward B.
+        let mut sim = reacting_sim(9);
+        sim.params.p6_on = true;
+        sim.params.mu_high = 2.0;
+        sim.params.mu_low = -2.0;
+        sim.positions[..4].copy_from_slice(&[0.2, 0.5, 0.25, 0.5]);
+        let mut b_high = 0.0;
+        for _ in 0..4000 {
+            sim.reaction_step();
+            b_high += sim.species[..2].iter().filter(|s| **s == 1).count() as f64;
+        }
+        assert!(b_high / 4000.0 > 1.5);
+        assert!(sim.reaction_counts[0] > 0 && sim.ep_exact_by_move[MOVE_REACT_H] != 0.0);
+        assert_eq!(sim.reaction_counts[2] + sim.reaction_counts[3], 0);
+
+        sim.positions[..4].copy_from_slice(&[0.7, 0.5, 0.75, 0.5]);
+        let mut b_low = 0.0;
+        for _ in 0..4000 {
+            sim.reaction_step();
+            b_low += sim.species[..2].iter().filter(|s| **s == 1).count() as f64;
+        }
+        assert!(b_low / 4000.0 < 0.5);
+        assert!(sim.reaction_counts[3] > 0);
+    }
+}
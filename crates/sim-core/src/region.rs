@@ -0,0 +1,264 @@
+use js_sys::{Array, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{get_f32, get_string, get_u16, get_u8, get_u8_vec, torus_dist};
+
+// Regions select grid cells in torus coordinates [0,1)^2, so the same mask selects the same
+// cells for perturbations, repair gates, code noise and metrics. Quadrants and stripes bin a
+// cell by its lower-left corner (x / g), as perturbation regions always have; disc and rect
+// test the cell centre.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RegionMask {
+    All,
+    Quadrant(u8),
+    Stripe {
+        axis: Axis,
+        bins: u8,
+        bin: u8,
+        span: u8,
+    },
+    Disc {
+        cx: f32,
+        cy: f32,
+        r: f32,
+    },
+    Rect {
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+    },
+    Checkerboard {
+        size: u16,
+        parity: u8,
+    },
+    Bitmap(Vec<u8>),
+    Union(Vec<RegionMask>),
+    Intersection(Vec<RegionMask>),
+    Not(Box<RegionMask>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Axis {
+    X,
+    Y,
+}
+
+impl RegionMask {
+    pub(crate) fn contains_cell(&self, idx: usize, g: usize) -> bool {
+        if g == 0 {
+            return false;
+        }
+        let cx = idx % g;
+        let cy = idx / g;
+        match self {
+            RegionMask::All => true,
+            RegionMask::Quadrant(q) => {
+                let qx = if 2 * cx < g { 0 } else { 1 };
+                let qy = if 2 * cy < g { 0 } else { 1 };
+                qy * 2 + qx == *q
+            }
+            RegionMask::Stripe {
+                axis,
+                bins,
+                bin,
+                span,
+            } => {
+                let bins = (*bins).max(1) as usize;
+                let coord = match axis {
+                    Axis::X => cx,
+                    Axis::Y => cy,
+                };
+                let stripe = (coord * bins / g).min(bins - 1);
+                let span = ((*span).max(1) as usize).min(bins);
+                (0..span).any(|i| (*bin as usize + i) % bins == stripe)
+            }
+            RegionMask::Disc { cx: dx, cy: dy, r } => {
+                let (x, y) = cell_center(cx, cy, g);
+                torus_dist(x, y, *dx, *dy) <= *r
+            }
+            RegionMask::Rect { x0, y0, x1, y1 } => {
+                let (x, y) = cell_center(cx, cy, g);
+                wrapped_range_contains(*x0, *x1, x) && wrapped_range_contains(*y0, *y1, y)
+            }
+            RegionMask::Checkerboard { size, parity } => {
+                let size = (*size).max(1) as usize;
+                ((cx / size + cy / size) % 2) as u8 == (*parity % 2)
+            }
+            RegionMask::Bitmap(bits) => bits.get(idx).is_some_and(|b| *b != 0),
+            RegionMask::Union(parts) => parts.iter().any(|p| p.contains_cell(idx, g)),
+            RegionMask::Intersection(parts) => parts.iter().all(|p| p.contains_cell(idx, g)),
+            RegionMask::Not(inner) => !inner.contains_cell(idx, g),
+        }
+    }
+
+    pub(crate) fn to_mask(&self, g: usize) -> Vec<u8> {
+        (0..g * g).map(|idx| self.contains_cell(idx, g) as u8).collect()
+    }
+
+    pub(crate) fn cells(&self, g: usize) -> Vec<u32> {
+        (0..g * g)
+            .filter(|idx| self.contains_cell(*idx, g))
+            .map(|idx| idx as u32)
+            .collect()
+    }
+
+    // Parses `obj[key]`, accepting either a region object or the legacy string form
+    // ("all" | "quadrant" | "stripe") whose fields sit alongside `key` on `obj`.
+    pub(crate) fn from_params(obj: &JsValue, key: &str, default_bins: u8) -> Option<RegionMask> {
+        let v = Reflect::get(obj, &JsValue::from_str(key)).ok()?;
+        if v.is_undefined() || v.is_null() {
+            return None;
+        }
+        if let Some(kind) = v.as_string() {
+            return Self::from_kind(obj, &kind, default_bins);
+        }
+        Self::from_js(&v, default_bins)
+    }
+
+    pub(crate) fn from_js(v: &JsValue, default_bins: u8) -> Option<RegionMask> {
+        if !v.is_object() {
+            return None;
+        }
+        let kind = get_string(v, "type")?;
+        Self::from_kind(v, &kind, default_bins)
+    }
+
+    fn from_kind(obj: &JsValue, kind: &str, default_bins: u8) -> Option<RegionMask> {
+        let region = match kind {
+            "all" => RegionMask::All,
+            "quadrant" => RegionMask::Quadrant(get_u8(obj, "quadrant").unwrap_or(0).min(3)),
+            "stripe" => RegionMask::Stripe {
+                axis: if get_string(obj, "axis").as_deref() == Some("y") {
+                    Axis::Y
+                } else {
+                    Axis::X
+                },
+                bins: get_u8(obj, "bins").unwrap_or(default_bins).max(1),
+                bin: get_u8(obj, "bin").unwrap_or(0),
+                span: get_u8(obj, "span").unwrap_or(1).max(1),
+            },
+            "disc" => RegionMask::Disc {
+                cx: get_f32(obj, "cx").unwrap_or(0.5),
+                cy: get_f32(obj, "cy").unwrap_or(0.5),
+                r: get_f32(obj, "r").unwrap_or(0.25).max(0.0),
+            },
+            "rect" => RegionMask::Rect {
+                x0: get_f32(obj, "x0").unwrap_or(0.0),
+                y0: get_f32(obj, "y0").unwrap_or(0.0),
+                x1: get_f32(obj, "x1").unwrap_or(1.0),
+                y1: get_f32(obj, "y1").unwrap_or(1.0),
+            },
+            "checkerboard" => RegionMask::Checkerboard {
+                size: get_u16(obj, "size").unwrap_or(1).max(1),
+                parity: get_u8(obj, "parity").unwrap_or(0) % 2,
+            },
+            "bitmap" => RegionMask::Bitmap(get_u8_vec(obj, "mask")?),
+            "union" | "intersection" => {
+                let list = Reflect::get(obj, &JsValue::from_str("regions")).ok()?;
+                if !Array::is_array(&list) {
+                    return None;
+                }
+                let mut parts = Vec::new();
+                for item in Array::from(&list).iter() {
+                    parts.push(Self::from_js(&item, default_bins)?);
+                }
+                if kind == "union" {
+                    RegionMask::Union(parts)
+                } else {
+                    RegionMask::Intersection(parts)
+                }
+            }
+            "not" => {
+                let inner = Reflect::get(obj, &JsValue::from_str("region")).ok()?;
+                RegionMask::Not(Box::new(Self::from_js(&inner, default_bins)?))
+            }
+            _ => return None,
+        };
+        Some(region)
+    }
+}
+
+fn cell_center(cx: usize, cy: usize, g: usize) -> (f32, f32) {
+    let gf = g as f32;
+    ((cx as f32 + 0.5) / gf, (cy as f32 + 0.5) / gf)
+}
+
+// Half-open [lo, hi) on the unit circle; hi < lo wraps through 0.
+fn wrapped_range_contains(lo: f32, hi: f32, v: f32) -> bool {
+    if hi - lo >= 1.0 {
+        true
+    } else if lo <= hi {
+        v >= lo && v < hi
+    } else {
+        v >= lo || v < hi
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(region: &RegionMask, g: usize) -> usize {
+        region.cells(g).len()
+    }
+
+    #[test]
+    fn test_region_shapes_partition_and_wrap() {
+        let g = 8usize;
+        let quads: usize = (0..4).map(|q| count(&RegionMask::Quadrant(q), g)).sum();
+        assert_eq!(quads, g * g);
+        assert!(RegionMask::Quadrant(1).contains_cell(g - 1, g));
+
+        let stripe_y = RegionMask::Stripe {
+            axis: Axis::Y,
+            bins: 4,
+            bin: 3,
+            span: 2,
+        };
+        // Span wraps from the last bin back to bin 0.
+        assert!(stripe_y.contains_cell(0, g));
+        assert!(stripe_y.contains_cell((g - 1) * g, g));
+        assert!(!stripe_y.contains_cell(2 * g, g));
+        assert_eq!(count(&stripe_y, g), g * g / 2);
+
+        let disc = RegionMask::Disc {
+            cx: 0.0,
+            cy: 0.0,
+            r: 0.1,
+        };
+        // Torus-aware: all four corner cells are within reach of the origin.
+        for idx in [0, g - 1, (g - 1) * g, g * g - 1] {
+            assert!(disc.contains_cell(idx, g));
+        }
+
+        let rect = RegionMask::Rect {
+            x0: 0.75,
+            y0: 0.0,
+            x1: 0.25,
+            y1: 1.0,
+        };
+        assert_eq!(count(&rect, g), g * g / 2);
+
+        let board = RegionMask::Checkerboard { size: 2, parity: 0 };
+        assert_eq!(count(&board, g), g * g / 2);
+        let both = RegionMask::Union(vec![board.clone(), RegionMask::Not(Box::new(board.clone()))]);
+        assert_eq!(count(&both, g), g * g);
+        let neither = RegionMask::Intersection(vec![board.clone(), RegionMask::Not(Box::new(board))]);
+        assert_eq!(count(&neither, g), 0);
+
+        let bitmap = RegionMask::Bitmap(vec![1, 0, 1]);
+        assert_eq!(bitmap.cells(g), vec![0, 2]);
+
+        // Bins follow the cell's left edge, like the scripts' quadrantIndex and stripeIndex.
+        let g = 5usize;
+        assert_eq!(RegionMask::Quadrant(0).cells(g), vec![0, 1, 2, 5, 6, 7, 10, 11, 12]);
+        let stripe = RegionMask::Stripe {
+            axis: Axis::X,
+            bins: 3,
+            bin: 1,
+            span: 1,
+        };
+        assert_eq!(stripe.cells(g).iter().filter(|idx| **idx < 5).collect::<Vec<_>>(), vec![&2, &3]);
+    }
+}
@@ -0,0 +1,254 @@
+use js_sys::{Float32Array, Float64Array, Object, Reflect, Uint32Array};
+use wasm_bindgen::prelude::*;
+
+use crate::coarse_ep::Xorshift;
+use crate::Sim;
+
+// Parallel tempering: one Sim per rung of a beta ladder, all sharing the same parameters.
+// Every `swapEvery` steps, neighbouring rungs propose to exchange configurations (even pairs
+// and odd pairs alternate) with acceptance min(1, exp((beta_a - beta_b)(H_a - H_b))), where H
+// is the full internal energy (breakdown total plus inter-level and op-K coupling), minus
+// muParticle * N while grand-canonical exchange is on. A swap exchanges the betas of the two
+// Sims, so `slot_of_rung` maps each rung to the replica holding it. Swaps are exact for any
+// equilibrium ensemble; P6 drive breaks detailed balance and makes them a heuristic.
+const TARGET_ENERGIES_MAX: usize = 100_000;
+
+#[wasm_bindgen]
+pub struct ReplicaSet {
+    replicas: Vec<Sim>,
+    betas: Vec<f32>,
+    slot_of_rung: Vec<usize>,
+    swap_every: u32,
+    // Steps since the last swap round, carried across `step` calls.
+    since_swap: u32,
+    target_rung: usize,
+    rng: Xorshift,
+    round: u64,
+    attempts: Vec<u32>,
+    accepts: Vec<u32>,
+    target_energies: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl ReplicaSet {
+    /// One replica per entry of `betas` (rungs keep the given order); the rung closest to
+    /// beta = 1 is the target.
+    #[wasm_bindgen(constructor)]
+    pub fn new(n: usize, seed: u32, betas: Vec<f32>) -> ReplicaSet {
+        let betas: Vec<f32> = betas.into_iter().filter(|b| b.is_finite() && *b > 0.0).collect();
+        let betas = if betas.is_empty() { vec![1.0] } else { betas };
+        let replicas = (0..betas.len())
+            .map(|k| {
+                let mut sim = Sim::new(n, seed.wrapping_add((k as u32).wrapping_mul(0x9E37_79B9)));
+                sim.params.beta = betas[k];
+                sim
+            })
+            .collect();
+        let target_rung = (0..betas.len())
+            .min_by(|a, b| (betas[*a] - 1.0).abs().total_cmp(&(betas[*b] - 1.0).abs()))
+            .unwrap_or(0);
+        let pairs = betas.len() - 1;
+        ReplicaSet {
+            replicas,
+            slot_of_rung: (0..betas.len()).collect(),
+            betas,
+            swap_every: 100,
+            since_swap: 0,
+            target_rung,
+            rng: Xorshift(if seed == 0 { 1 } else { seed ^ 0x5bd1_e995 }),
+            round: 0,
+            attempts: vec![0; pairs],
+            accepts: vec![0; pairs],
+            target_energies: Vec::new(),
+        }
+    }
+
+    /// Applies `params` to every replica (each keeps its rung's beta). `swapEvery` and
+    /// `targetRung` configure the set itself.
+    pub fn set_params(&mut self, params: JsValue) {
+        if let Some(v) = crate::get_u32(&params, "swapEvery") {
+            self.swap_every = v.max(1);
+        }
+        if let Some(v) = crate::get_u32(&params, "targetRung") {
+            self.target_rung = (v as usize).min(self.betas.len() - 1);
+        }
+        for rung in 0..self.betas.len() {
+            let sim = &mut self.replicas[self.slot_of_rung[rung]];
+            sim.set_params(params.clone());
+            sim.params.beta = self.betas[rung];
+        }
+    }
+
+    /// Advances every replica by `steps`, proposing swaps every `swapEvery` steps counted
+    /// across calls. The target rung's full energy is recorded after each swap round (the
+    /// oldest half is dropped once the record is full).
+    pub fn step(&mut self, steps: u32) {
+        let mut left = steps;
+        while left > 0 {
+            let chunk = left.min(self.swap_every.saturating_sub(self.since_swap).max(1));
+            for sim in &mut self.replicas {
+                sim.step(chunk);
+            }
+            left -= chunk;
+            self.since_swap += chunk;
+            if self.since_swap >= self.swap_every {
+                self.since_swap = 0;
+                self.swap_round();
+            }
+        }
+    }
+
+    pub fn rungs(&self) -> usize {
+        self.betas.len()
+    }
+
+    pub fn betas(&self) -> Float32Array {
+        Float32Array::from(self.betas.as_slice())
+    }
+
+    /// Replica index currently holding each rung.
+    pub fn slot_of_rung(&self) -> Uint32Array {
+        let slots: Vec<u32> = self.slot_of_rung.iter().map(|s| *s as u32).collect();
+        Uint32Array::from(slots.as_slice())
+    }
+
+    /// Swap statistics per neighbouring rung pair: `attempts`, `accepts` and `rate`.
+    pub fn swap_stats(&self) -> Object {
+        let rate: Vec<f64> = self
+            .attempts
+            .iter()
+            .zip(&self.accepts)
+            .map(|(a, s)| if *a > 0 { *s as f64 / *a as f64 } else { 0.0 })
+            .collect();
+        let o = Object::new();
+        let _ = Reflect::set(&o, &JsValue::from_str("attempts"), &Uint32Array::from(self.attempts.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("accepts"), &Uint32Array::from(self.accepts.as_slice()));
+        let _ = Reflect::set(&o, &JsValue::from_str("rate"), &Float64Array::from(rate.as_slice()));
+        o
+    }
+
+    /// Full energies of the target rung, one per swap round.
+    pub fn target_energies(&self) -> Float64Array {
+        Float64Array::from(self.target_energies.as_slice())
+    }
+
+    /// Clears the recorded target energies and the swap statistics.
+    pub fn reset_stats(&mut self) {
+        self.target_energies.clear();
+        self.attempts.iter_mut().for_each(|a| *a = 0);
+        self.accepts.iter_mut().for_each(|a| *a = 0);
+    }
+
+    /// A copy of the Sim currently at the target rung.
+    pub fn target_sim(&self) -> Sim {
+        self.replicas[self.slot_of_rung[self.target_rung]].clone()
+    }
+}
+
+impl ReplicaSet {
+    fn rand01(&mut self) -> f64 {
+        ((self.rng.next_u32() >> 8) as f64) / ((1u32 << 24) as f64)
+    }
+
+    // ln of the swap acceptance ratio between rungs a and b.
+    fn swap_log_ratio(&self, a: usize, b: usize) -> f64 {
+        let e_a = swap_energy(&self.replicas[self.slot_of_rung[a]]);
+        let e_b = swap_energy(&self.replicas[self.slot_of_rung[b]]);
+        (self.betas[a] as f64 - self.betas[b] as f64) * (e_a - e_b)
+    }
+
+    fn swap_round(&mut self) {
+        let parity = (self.round % 2) as usize;
+        self.round += 1;
+        for a in (parity..self.betas.len().saturating_sub(1)).step_by(2) {
+            let b = a + 1;
+            self.attempts[a] += 1;
+            let log_ratio = self.swap_log_ratio(a, b);
+            if log_ratio >= 0.0 || self.rand01() < log_ratio.exp() {
+                self.accepts[a] += 1;
+                self.slot_of_rung.swap(a, b);
+                self.replicas[self.slot_of_rung[a]].params.beta = self.betas[a];
+                self.replicas[self.slot_of_rung[b]].params.beta = self.betas[b];
+            }
+        }
+        let target = &self.replicas[self.slot_of_rung[self.target_rung]];
+        let energy = target.internal_energy();
+        if self.target_energies.len() >= TARGET_ENERGIES_MAX {
+            self.target_energies.drain(..TARGET_ENERGIES_MAX / 2);
+        }
+        self.target_energies.push(energy);
+    }
+}
+
+// The energy the swap rule weighs: U, or U - mu * N in the grand-canonical ensemble.
+fn swap_energy(sim: &Sim) -> f64 {
+    let e = sim.internal_energy();
+    if sim.params.gc_on {
+        e - sim.params.mu_particle as f64 * sim.n as f64
+    } else {
+        e
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swaps_keep_rungs_consistent() {
+        let mut set = ReplicaSet::new(8, 3, vec![1.0, 0.7, 0.45, 0.3]);
+        assert_eq!(set.target_rung, 0);
+        set.swap_every = 50;
+        set.step(5000);
+        assert_eq!(set.target_energies.len(), 100);
+        let mut slots = set.slot_of_rung.clone();
+        slots.sort();
+        assert_eq!(slots, vec![0, 1, 2, 3]);
+        for rung in 0..4 {
+            assert_eq!(set.replicas[set.slot_of_rung[rung]].params.beta, set.betas[rung]);
+        }
+        assert!(set.accepts.iter().sum::<u32>() > 0);
+        assert_eq!(set.attempts.iter().sum::<u32>(), 100 + 50);
+        let target = set.target_sim();
+        assert_eq!(target.params.beta, 1.0);
+        assert!((target.internal_energy() - set.target_energies[99]).abs() < 1e-9);
+
+        // Short calls still add up to swap rounds.
+        set.reset_stats();
+        for _ in 0..10 {
+            set.step(20);
+        }
+        assert_eq!(set.target_energies.len(), 4);
+        assert_eq!(set.since_swap, 0);
+    }
+
+    #[test]
+    fn test_swap_ratio_uses_internal_energy() {
+        let mut set = ReplicaSet::new(6, 11, vec![1.0, 0.5]);
+        for sim in &mut set.replicas {
+            sim.params.meta_layers = 1;
+            sim.resize_meta_arrays();
+            sim.params.eta = 0.9;
+            sim.s_field.iter_mut().enumerate().for_each(|(i, s)| *s = (i % 5) as u8);
+        }
+        set.step(500);
+        let (e0, e1) = (set.replicas[set.slot_of_rung[0]].internal_energy(), set.replicas[set.slot_of_rung[1]].internal_energy());
+        assert!((set.swap_log_ratio(0, 1) - 0.5 * (e0 - e1)).abs() < 1e-9);
+        let couple: f64 = set.replicas[0].coupling_energy_by_interface_inner().iter().sum();
+        assert!(couple != 0.0);
+    }
+
+    #[test]
+    fn test_swap_ratio_subtracts_mu_n_under_gc() {
+        let mut set = ReplicaSet::new(6, 5, vec![1.0, 0.5]);
+        for sim in &mut set.replicas {
+            sim.params.gc_on = true;
+            sim.params.mu_particle = -0.8;
+        }
+        set.step(400);
+        let (r0, r1) = (&set.replicas[set.slot_of_rung[0]], &set.replicas[set.slot_of_rung[1]]);
+        assert!(r0.n != r1.n);
+        let h = |s: &Sim| s.internal_energy() + 0.8 * s.n as f64;
+        assert!((set.swap_log_ratio(0, 1) - 0.5 * (h(r0) - h(r1))).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,207 @@
+use js_sys::{Float32Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::Sim;
+
+// Finite P6 reservoirs. reservoirMode 0 keeps the infinite reservoirs at muHigh/muLow; 1 feeds
+// both halves from one global stock; 2 gives the high (x < 0.5) and low halves their own
+// stock. The regions are fixed to these two halves, the same split `mu_at` uses for the P6
+// drive; `RegionMask` is not consulted, so other regions are not supported. Each accepted P6-driven increment draws one
+// unit from its reservoir (a decrement returns one; a unit returned to a full stock spills
+// and is counted in `spilled`), an increment is refused while the stock is below one unit,
+// and every step adds `reservoirRefill` up to `reservoirCapacity` (in kMC mode, per unit of
+// time). The effective chemical potential is mu + ln(stock / capacity) / beta, so it falls as
+// the stock runs down.
+pub(crate) const RESERVOIR_HIGH: usize = 0;
+pub(crate) const RESERVOIR_LOW: usize = 1;
+
+#[wasm_bindgen]
+impl Sim {
+    /// Reservoir state: `mode`, `stocks` ([high, low]; both read the same global stock in
+    /// mode 1), the effective `muHigh`/`muLow`, and the totals `drawn`, `refilled` and
+    /// `spilled` (units returned to a full stock).
+    pub fn reservoirs(&self) -> Object {
+        let stocks = [
+            self.reservoir_stock[self.reservoir_slot(true)],
+            self.reservoir_stock[self.reservoir_slot(false)],
+        ];
+        let o = Object::new();
+        let set = |key: &str, value: f64| {
+            let _ = Reflect::set(&o, &JsValue::from_str(key), &JsValue::from_f64(value));
+        };
+        set("mode", self.params.reservoir_mode as f64);
+        set("muHigh", self.reservoir_mu(true) as f64);
+        set("muLow", self.reservoir_mu(false) as f64);
+        set("drawn", self.reservoir_drawn);
+        set("refilled", self.reservoir_refilled);
+        set("spilled", self.reservoir_spilled);
+        let _ = Reflect::set(&o, &JsValue::from_str("stocks"), &Float32Array::from(&stocks[..]));
+        o
+    }
+}
+
+impl Sim {
+    // The reservoir keys of `set_params`. The capacity is applied before the mode, so a call
+    // that switches finite reservoirs on fills the stocks to the capacity it also sets; saved
+    // stocks are restored last.
+    pub(crate) fn configure_reservoirs(
+        &mut self,
+        mode: Option<u8>,
+        capacity: Option<f32>,
+        refill: Option<f32>,
+        stocks: Option<Vec<f32>>,
+    ) {
+        if let Some(v) = capacity.filter(|v| v.is_finite()) {
+            self.params.reservoir_capacity = v.clamp(1.0, 1e9);
+            self.clamp_reservoirs();
+        }
+        if let Some(v) = mode {
+            let mode = v.min(2);
+            if mode != 0 && self.params.reservoir_mode == 0 {
+                self.reservoir_stock = [self.params.reservoir_capacity; 2];
+            }
+            self.params.reservoir_mode = mode;
+        }
+        if let Some(v) = refill.filter(|v| v.is_finite()) {
+            self.params.reservoir_refill = v.max(0.0);
+        }
+        if let Some(v) = stocks {
+            for (stock, value) in self.reservoir_stock.iter_mut().zip(v) {
+                if value.is_finite() {
+                    *stock = value;
+                }
+            }
+            self.clamp_reservoirs();
+        }
+    }
+
+    fn reservoir_slot(&self, high: bool) -> usize {
+        if self.params.reservoir_mode == 2 && !high {
+            RESERVOIR_LOW
+        } else {
+            RESERVOIR_HIGH
+        }
+    }
+
+    // Effective chemical potential of the high or low reservoir.
+    pub(crate) fn reservoir_mu(&self, high: bool) -> f32 {
+        let mu = if high { self.params.mu_high } else { self.params.mu_low };
+        if self.params.reservoir_mode == 0 {
+            return mu;
+        }
+        let stock = self.reservoir_stock[self.reservoir_slot(high)].max(0.5);
+        let capacity = self.params.reservoir_capacity;
+        mu + (stock / capacity).ln() / self.params.beta.max(1e-6)
+    }
+
+    pub(crate) fn resource_exhausted(&self, high: bool) -> bool {
+        self.params.reservoir_mode != 0 && self.reservoir_stock[self.reservoir_slot(high)] < 1.0
+    }
+
+    // Books one accepted P6-driven change: an increment draws a unit, a decrement returns it
+    // (spilling whatever the capacity cannot hold).
+    pub(crate) fn draw_resource(&mut self, high: bool, up: bool) {
+        if self.params.reservoir_mode == 0 {
+            return;
+        }
+        let slot = self.reservoir_slot(high);
+        if up {
+            self.reservoir_stock[slot] -= 1.0;
+            self.reservoir_drawn += 1.0;
+        } else {
+            let kept = (self.params.reservoir_capacity - self.reservoir_stock[slot]).clamp(0.0, 1.0);
+            self.reservoir_stock[slot] += kept;
+            self.reservoir_spilled += (1.0 - kept) as f64;
+            self.reservoir_drawn -= 1.0;
+        }
+    }
+
+    pub(crate) fn refill_reservoirs(&mut self, dt: f32) {
+        if self.params.reservoir_mode == 0 {
+            return;
+        }
+        let stocks = if self.params.reservoir_mode == 2 { 2 } else { 1 };
+        let capacity = self.params.reservoir_capacity;
+        for stock in &mut self.reservoir_stock[..stocks] {
+            let added = (self.params.reservoir_refill * dt).min(capacity - *stock).max(0.0);
+            *stock += added;
+            self.reservoir_refilled += added as f64;
+        }
+    }
+
+    // Keeps the stocks inside [0, capacity] after a mode or capacity change.
+    pub(crate) fn clamp_reservoirs(&mut self) {
+        let capacity = self.params.reservoir_capacity;
+        self.reservoir_stock.iter_mut().for_each(|s| *s = s.clamp(0.0, capacity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reservoir_depletes_and_refills() {
+        let mut sim = Sim::new(8, 19);
+        sim.params.p6_on = true;
+        sim.params.mu_high = 3.0;
+        sim.params.mu_low = 3.0;
+        sim.params.lambda_n = 0.01;
+        sim.params.l_n = 50;
+        sim.params.p_write = 0.0;
+        sim.params.p_n_write = 1.0;
+        sim.params.p_a_write = 0.0;
+        sim.params.p_s_write = 0.0;
+        sim.params.clock_on = false;
+        sim.params.reservoir_mode = 1;
+        sim.params.reservoir_capacity = 20.0;
+        sim.params.reservoir_refill = 0.0;
+        sim.reservoir_stock = [20.0, 20.0];
+        let total_n = |sim: &Sim| sim.n_counter.iter().map(|n| *n as i32).sum::<i32>();
+        let start = total_n(&sim);
+        sim.step(5000);
+        // A strong drive runs the stock down; the net counter gain is exactly what was drawn.
+        let stock = sim.reservoir_stock[RESERVOIR_HIGH];
+        assert!((0.0..5.0).contains(&stock));
+        assert_eq!((total_n(&sim) - start) as f32, 20.0 - stock);
+        assert!(sim.reservoir_mu(true) < 2.0);
+
+        sim.params.reservoir_refill = 0.5;
+        sim.params.p_n_write = 0.0;
+        sim.step(100);
+        assert!((sim.reservoir_stock[RESERVOIR_HIGH] - 20.0).abs() < 1e-3);
+        assert!((sim.reservoir_mu(true) - 3.0).abs() < 1e-5);
+        assert!((sim.reservoir_refilled - (20.0 - stock) as f64).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_regional_stocks_are_separate() {
+        let mut sim = Sim::new(2, 7);
+        sim.params.reservoir_mode = 2;
+        sim.params.reservoir_capacity = 10.0;
+        sim.reservoir_stock = [10.0, 10.0];
+        sim.draw_resource(true, true);
+        sim.draw_resource(true, true);
+        sim.draw_resource(false, false);
+        // The low stock is full: the returned unit spills instead of overfilling it.
+        assert_eq!(sim.reservoir_stock, [8.0, 10.0]);
+        assert_eq!(sim.reservoir_spilled, 1.0);
+        assert!(!sim.resource_exhausted(true) && !sim.resource_exhausted(false));
+        sim.params.reservoir_mode = 1;
+        assert_eq!(sim.reservoir_slot(false), RESERVOIR_HIGH);
+        assert!((sim.reservoir_mu(false) - (sim.params.mu_low + 0.8f32.ln())).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mode_and_capacity_in_one_call_fill_to_new_capacity() {
+        let mut sim = Sim::new(2, 3);
+        sim.configure_reservoirs(Some(1), Some(500.0), None, None);
+        assert_eq!(sim.reservoir_stock, [500.0, 500.0]);
+        assert!((sim.reservoir_mu(true) - sim.params.mu_high).abs() < 1e-6);
+        // Saved stocks win over the fill, and a later capacity cut clamps them.
+        sim.configure_reservoirs(Some(2), None, None, Some(vec![120.0, 40.0]));
+        assert_eq!(sim.reservoir_stock, [120.0, 40.0]);
+        sim.configure_reservoirs(None, Some(80.0), None, None);
+        assert_eq!(sim.reservoir_stock, [80.0, 40.0]);
+    }
+}
@@ -0,0 +1,330 @@
+use js_sys::{Float64Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::{edge_index, get_f32_vec, get_u8, get_u8_vec, Sim};
+
+const MAX_SPECIES: usize = 16;
+
+// Species-dependent parameters. Pair matrices are row-major `count x count` and read from the
+// upper triangle (a <= b), so they are symmetric by construction; a missing matrix or limit
+// falls back to the global parameter. `lA`/`lN` cap a species' counters below the global
+// limits, which still set the energy normalisation; counters clamped by new caps are booked
+// as an intervention. With `bondAllowed`, P1 only proposes pairs whose entry is non-zero, so
+// the bonds of other pairs keep their value between initialisations (`initRandom` zeroes
+// them). `energy` is a per-species intrinsic energy added to the total for every particle of
+// that species. The pair matrices only parameterise the default soft wall and harmonic bond:
+// a custom `pairPotential` or `bondPotential` is dropped while the table carries the matching
+// matrices.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SpeciesTable {
+    pub(crate) count: usize,
+    pub(crate) kappa_rep: Option<Vec<f32>>,
+    pub(crate) r0: Option<Vec<f32>>,
+    pub(crate) kappa_bond: Option<Vec<f32>>,
+    pub(crate) r_star: Option<Vec<f32>>,
+    pub(crate) energy: Option<Vec<f32>>,
+    pub(crate) l_a: Option<Vec<u16>>,
+    pub(crate) l_n: Option<Vec<i16>>,
+    pub(crate) bond_allowed: Option<Vec<bool>>,
+}
+
+impl SpeciesTable {
+    // `species`: `{ count, labels?, kappaRep?, r0?, kappaBond?, rStar?, energy?, lA?, lN?,
+    // bondAllowed? }`.
+    // Anything that is not an object (e.g. null) clears the table. Returns the table and the
+    // labels, if any were given.
+    pub(crate) fn from_js(v: &JsValue) -> Option<(SpeciesTable, Option<Vec<u8>>)> {
+        if !v.is_object() {
+            return None;
+        }
+        let count = (get_u8(v, "count")? as usize).clamp(1, MAX_SPECIES);
+        let matrix = |key: &str, lo: f32, hi: f32| {
+            get_f32_vec(v, key)
+                .filter(|m| m.len() == count * count && m.iter().all(|x| x.is_finite()))
+                .map(|m| m.into_iter().map(|x| x.clamp(lo, hi)).collect::<Vec<f32>>())
+        };
+        let per_species = |key: &str| get_f32_vec(v, key).filter(|m| m.len() == count && m.iter().all(|x| x.is_finite()));
+        let table = SpeciesTable {
+            count,
+            kappa_rep: matrix("kappaRep", 0.0, 1000.0),
+            r0: matrix("r0", 0.0, 0.5),
+            kappa_bond: matrix("kappaBond", 0.0, 1000.0),
+            r_star: matrix("rStar", 0.0, 0.5),
+            energy: per_species("energy").map(|m| m.iter().map(|x| x.clamp(-100.0, 100.0)).collect()),
+            l_a: per_species("lA").map(|m| m.iter().map(|x| x.round().clamp(1.0, 65535.0) as u16).collect()),
+            l_n: per_species("lN").map(|m| m.iter().map(|x| x.round().clamp(1.0, 32767.0) as i16).collect()),
+            bond_allowed: get_u8_vec(v, "bondAllowed")
+                .filter(|m| m.len() == count * count)
+                .map(|m| m.iter().map(|x| *x != 0).collect()),
+        };
+        let labels = get_u8_vec(v, "labels").map(|l| l.iter().map(|s| (*s as usize).min(count - 1) as u8).collect());
+        Some((table, labels))
+    }
+
+    fn pair_slot(&self, a: u8, b: u8) -> usize {
+        let (a, b) = (a.min(b) as usize, a.max(b) as usize);
+        a * self.count + b
+    }
+}
+
+#[wasm_bindgen]
+impl Sim {
+    /// Species label of each particle (all 0 without a species table).
+    pub fn species_labels(&self) -> Uint8Array {
+        Uint8Array::from(self.species.as_slice())
+    }
+
+    /// Species-resolved diagnostics: `count`, per-species `counts`, `meanA` and `meanN`, and
+    /// row-major `count x count` matrices `bondW` (summed w) and `bonds` (pairs with w > 0).
+    pub fn species_stats(&self) -> Object {
+        let count = self.species_count();
+        let mut counts = vec![0f64; count];
+        let mut sum_a = vec![0f64; count];
+        let mut sum_n = vec![0f64; count];
+        for k in 0..self.n {
+            let s = self.species[k] as usize;
+            counts[s] += 1.0;
+            sum_a[s] += self.a_counter[k] as f64;
+            sum_n[s] += self.n_counter[k] as f64;
+        }
+        let mut bond_w = vec![0f64; count * count];
+        let mut bonds = vec![0f64; count * count];
+        for i in 0..self.n {
+            for j in (i + 1)..self.n {
+                let w = self.w[edge_index(self.n, i, j)];
+                if w == 0 {
+                    continue;
+                }
+                let (a, b) = (self.species[i] as usize, self.species[j] as usize);
+                let slots = if a == b { vec![a * count + a] } else { vec![a * count + b, b * count + a] };
+                for slot in slots {
+                    bond_w[slot] += w as f64;
+                    bonds[slot] += 1.0;
+                }
+            }
+        }
+        let mean = |sum: &[f64]| -> Vec<f64> {
+            sum.iter().zip(&counts).map(|(s, c)| if *c > 0.0 { s / c } else { 0.0 }).collect()
+        };
+        let o = Object::new();
+        let set = |key: &str, value: &[f64]| {
+            let _ = Reflect::set(&o, &JsValue::from_str(key), &Float64Array::from(value));
+        };
+        let _ = Reflect::set(&o, &JsValue::from_str("count"), &JsValue::from_f64(count as f64));
+        set("counts", &counts);
+        set("meanA", &mean(&sum_a));
+        set("meanN", &mean(&sum_n));
+        set("bondW", &bond_w);
+        set("bonds", &bonds);
+        o
+    }
+}
+
+impl Sim {
+    pub(crate) fn species_count(&self) -> usize {
+        self.species_table.as_ref().map_or(1, |t| t.count)
+    }
+
+    pub(crate) fn set_species_table(&mut self, table: Option<(SpeciesTable, Option<Vec<u8>>)>) {
+        match table {
+            Some((table, labels)) => {
+                if let Some(labels) = labels.filter(|l| l.len() == self.n) {
+                    self.species = labels;
+                }
+                let top = (table.count - 1) as u8;
+                self.species.iter_mut().for_each(|s| *s = (*s).min(top));
+                self.species_table = Some(table);
+            }
+            None => {
+                self.species_table = None;
+                self.species.iter_mut().for_each(|s| *s = 0);
+            }
+        }
+        let over = |sim: &Sim, k: usize| {
+            sim.a_counter[k] > sim.l_a_of(k) || sim.n_counter[k].abs() > sim.l_n_of(k)
+        };
+        if (0..self.n).any(|k| over(self, k)) {
+            let e0 = self.internal_energy();
+            for k in 0..self.n {
+                self.a_counter[k] = self.a_counter[k].min(self.l_a_of(k));
+                self.n_counter[k] = self.n_counter[k].clamp(-self.l_n_of(k), self.l_n_of(k));
+            }
+            self.intervention_energy_total += self.internal_energy() - e0;
+        }
+    }
+
+    // Drops a custom pair or bond potential that the species matrices would otherwise bypass.
+    pub(crate) fn reject_species_potentials(&mut self) {
+        if let Some(table) = &self.species_table {
+            if table.kappa_rep.is_some() || table.r0.is_some() {
+                self.pair_potential = None;
+            }
+            if table.kappa_bond.is_some() || table.r_star.is_some() {
+                self.bond_potential = None;
+            }
+        }
+    }
+
+    fn species_pair_value(&self, i: usize, j: usize, pick: fn(&SpeciesTable) -> &Option<Vec<f32>>) -> Option<f32> {
+        let table = self.species_table.as_ref()?;
+        let values = pick(table).as_ref()?;
+        Some(values[table.pair_slot(self.species[i], self.species[j])])
+    }
+
+    // (kappaRep, r0) between particles i and j.
+    pub(crate) fn repulsion_params(&self, i: usize, j: usize) -> (f32, f32) {
+        (
+            self.species_pair_value(i, j, |t| &t.kappa_rep).unwrap_or(self.params.kappa_rep),
+            self.species_pair_value(i, j, |t| &t.r0).unwrap_or(self.params.r0),
+        )
+    }
+
+    // (kappaBond, rStar) between particles i and j.
+    pub(crate) fn bond_params(&self, i: usize, j: usize) -> (f32, f32) {
+        (
+            self.species_pair_value(i, j, |t| &t.kappa_bond).unwrap_or(self.params.kappa_bond),
+            self.species_pair_value(i, j, |t| &t.r_star).unwrap_or(self.params.r_star),
+        )
+    }
+
+    pub(crate) fn bond_allowed(&self, i: usize, j: usize) -> bool {
+        match &self.species_table {
+            Some(table) => table
+                .bond_allowed
+                .as_ref()
+                .is_none_or(|allowed| allowed[table.pair_slot(self.species[i], self.species[j])]),
+            None => true,
+        }
+    }
+
+    // Intrinsic energy of one particle of `species`.
+    pub(crate) fn species_energy(&self, species: u8) -> f32 {
+        match &self.species_table {
+            Some(SpeciesTable { energy: Some(energy), .. }) => energy[species as usize],
+            _ => 0.0,
+        }
+    }
+
+    pub(crate) fn species_energy_total(&self) -> f32 {
+        self.species.iter().map(|s| self.species_energy(*s)).sum()
+    }
+
+    pub(crate) fn l_a_for(&self, species: u8) -> u16 {
+        match &self.species_table {
+            Some(SpeciesTable { l_a: Some(l_a), .. }) => l_a[species as usize].min(self.params.l_a),
+            _ => self.params.l_a,
+        }
+    }
+
+    pub(crate) fn l_n_for(&self, species: u8) -> i16 {
+        match &self.species_table {
+            Some(SpeciesTable { l_n: Some(l_n), .. }) => l_n[species as usize].min(self.params.l_n),
+            _ => self.params.l_n,
+        }
+    }
+
+    pub(crate) fn l_a_of(&self, k: usize) -> u16 {
+        self.l_a_for(self.species[k])
+    }
+
+    pub(crate) fn l_n_of(&self, k: usize) -> i16 {
+        self.l_n_for(self.species[k])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::potential::{Potential, Wca};
+    use crate::torus_dist;
+
+    fn two_species(sim: &mut Sim) {
+        let table = SpeciesTable {
+            count: 2,
+            kappa_rep: Some(vec![10.0, 80.0, 80.0, 40.0]),
+            r0: Some(vec![0.05, 0.08, 0.08, 0.03]),
+            kappa_bond: Some(vec![1.0, 6.0, 6.0, 2.0]),
+            r_star: None,
+            energy: None,
+            l_a: Some(vec![2, 6]),
+            l_n: Some(vec![6, 1]),
+            bond_allowed: Some(vec![false, true, true, true]),
+        };
+        let labels = (0..sim.n).map(|k| (k % 2) as u8).collect();
+        sim.set_species_table(Some((table, Some(labels))));
+    }
+
+    #[test]
+    fn test_species_pair_params_match_energy_diff() {
+        let mut sim = Sim::new(12, 17);
+        for (e, w) in sim.w.iter_mut().enumerate() {
+            *w = (e % 3) as u8;
+        }
+        two_species(&mut sim);
+        assert_eq!(sim.repulsion_params(0, 1), (80.0, 0.08));
+        assert_eq!(sim.repulsion_params(1, 3), (40.0, 0.03));
+        assert_eq!(sim.bond_params(0, 2), (1.0, sim.params.r_star));
+        assert!(!sim.bond_allowed(0, 2) && sim.bond_allowed(1, 2));
+        let energy = |sim: &Sim| {
+            let (u_rep, u_bond, e_w, ..) = sim.energy_breakdown_inner();
+            (u_rep + u_bond + e_w) as f64
+        };
+        for step in 0..40 {
+            let i = step % sim.n;
+            let (x0, y0) = (sim.positions[2 * i], sim.positions[2 * i + 1]);
+            let (x1, y1) = ((x0 + 0.031) % 1.0, (y0 + 0.017 * (step % 3) as f32) % 1.0);
+            let delta = sim.delta_e_move_particle(i, x0, y0, x1, y1) as f64;
+            let before = energy(&sim);
+            sim.positions[2 * i] = x1;
+            sim.positions[2 * i + 1] = y1;
+            assert!((energy(&sim) - before - delta).abs() < 1e-4 * (1.0 + before.abs()));
+
+            let j = (i + 1) % sim.n;
+            let (a, b) = (i.min(j), i.max(j));
+            let e = edge_index(sim.n, a, b);
+            let (w0, w1) = (sim.w[e], (sim.w[e] + 1) % 4);
+            let r = torus_dist(sim.positions[2 * a], sim.positions[2 * a + 1], sim.positions[2 * b], sim.positions[2 * b + 1]);
+            let delta = sim.delta_e_write(w0, w1, (a, b), r) as f64;
+            let before = energy(&sim);
+            sim.w[e] = w1;
+            assert!((energy(&sim) - before - delta).abs() < 1e-4 * (1.0 + before.abs()));
+        }
+    }
+
+    #[test]
+    fn test_species_limits_and_restricted_p1() {
+        let mut sim = Sim::new(10, 5);
+        sim.a_counter.iter_mut().for_each(|a| *a = 5);
+        sim.n_counter.iter_mut().for_each(|n| *n = -5);
+        let frozen: Vec<u8> = sim.w.clone();
+        two_species(&mut sim);
+        let mut clamped = 0.0;
+        for k in 0..sim.n {
+            let (l_a, l_n) = if k % 2 == 0 { (2, 5) } else { (5, 1) };
+            assert_eq!((sim.a_counter[k], sim.n_counter[k]), (l_a, -l_n));
+            clamped += (sim.delta_e_apparatus(5, l_a) + sim.delta_e_counter(-5, -l_n)) as f64;
+        }
+        // The clamp is an intervention, not protocol work.
+        assert!(clamped < 0.0);
+        assert!((sim.intervention_energy_total - clamped).abs() < 1e-3);
+        sim.pair_potential = Some(Potential::Wca(Wca { epsilon: 1.0, sigma: 0.05 }));
+        sim.reject_species_potentials();
+        assert!(sim.pair_potential.is_none());
+        sim.params.r_propose = 0.5;
+        for _ in 0..2000 {
+            sim.p1_write_step();
+            sim.p2_write_step();
+            sim.p4_write_step();
+        }
+        for k in 0..sim.n {
+            assert!(sim.a_counter[k] <= sim.l_a_of(k));
+            assert!(sim.n_counter[k].abs() <= sim.l_n_of(k));
+        }
+        for i in (0..sim.n).step_by(2) {
+            for j in ((i + 2)..sim.n).step_by(2) {
+                let e = edge_index(sim.n, i, j);
+                assert_eq!(sim.w[e], frozen[e]);
+            }
+        }
+    }
+}
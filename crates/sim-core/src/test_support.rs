@@ -0,0 +1,89 @@
+// Shared test fixtures: a seeded LCG, random field fills and the incremental-ΔE check.
+use crate::Sim;
+
+pub(crate) struct Lcg {
+    state: u32,
+}
+
+impl Lcg {
+    pub(crate) fn new(seed: u32) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_mul(1664525).wrapping_add(1013904223);
+        self.state
+    }
+
+    pub(crate) fn next_usize(&mut self, max: usize) -> usize {
+        if max == 0 {
+            0
+        } else {
+            (self.next_u32() as usize) % max
+        }
+    }
+
+    pub(crate) fn next_u8_range(&mut self, max_inclusive: u8) -> u8 {
+        if max_inclusive == 0 {
+            0
+        } else {
+            (self.next_u32() % (max_inclusive as u32 + 1)) as u8
+        }
+    }
+
+    pub(crate) fn next_u16_range(&mut self, max_inclusive: u16) -> u16 {
+        if max_inclusive == 0 {
+            0
+        } else {
+            (self.next_u32() % (max_inclusive as u32 + 1)) as u16
+        }
+    }
+
+    pub(crate) fn next_i16_range(&mut self, min_inclusive: i16, max_inclusive: i16) -> i16 {
+        if min_inclusive >= max_inclusive {
+            return min_inclusive;
+        }
+        let span = (max_inclusive as i32 - min_inclusive as i32 + 1) as u32;
+        min_inclusive + (self.next_u32() % span) as i16
+    }
+
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        ((self.next_u32() >> 8) as f32) / ((1u32 << 24) as f32)
+    }
+}
+
+pub(crate) fn fill_random_fields(sim: &mut Sim, rng: &mut Lcg) {
+    let l_s = sim.params.l_s;
+    let l_w = sim.params.l_w;
+    let l_a = sim.params.l_a;
+    let l_n = sim.params.l_n;
+    for s in &mut sim.s_field {
+        *s = rng.next_u8_range(l_s);
+    }
+    for s in &mut sim.meta_field {
+        *s = rng.next_u8_range(l_s);
+    }
+    for w in &mut sim.meta_w_edges {
+        *w = rng.next_u8_range(l_w);
+    }
+    for a in &mut sim.meta_a_field {
+        *a = rng.next_u16_range(l_a);
+    }
+    for n in &mut sim.meta_n_field {
+        *n = rng.next_i16_range(-l_n, l_n);
+    }
+}
+
+// Checks that `delta` (an incremental ΔE) equals the change of `energy` across `edit`.
+pub(crate) fn assert_delta(
+    sim: &mut Sim,
+    energy: impl Fn(&Sim) -> f64,
+    delta: f64,
+    edit: impl FnOnce(&mut Sim),
+    tol: f64,
+) {
+    let before = energy(sim);
+    edit(sim);
+    let after = energy(sim);
+    assert!((after - before - delta).abs() < tol, "ΔE {delta} vs {}", after - before);
+}
@@ -50,148 +50,30 @@ export function parseSeedList(seedArg) {
     .filter((v) => Number.isFinite(v) && v > 0);
 }
 
-export function quadrantIndex(idx, g) {
-  const x = idx % g;
-  const y = Math.floor(idx / g);
-  const qx = x < g / 2 ? 0 : 1;
-  const qy = y < g / 2 ? 0 : 1;
-  return qy * 2 + qx;
-}
-
-export function quadrantMeans(field, g, mask) {
-  const sums = [0, 0, 0, 0];
-  const counts = [0, 0, 0, 0];
-  for (let i = 0; i < field.length; i += 1) {
-    if (mask && !mask[i]) continue;
-    const q = quadrantIndex(i, g);
-    sums[q] += field[i];
-    counts[q] += 1;
-  }
-  return sums.map((sum, i) => (counts[i] > 0 ? sum / counts[i] : 0));
-}
-
-export function logicalBitsFromField(field, g, lS, mask) {
-  const sums = [0, 0, 0, 0];
-  const counts = [0, 0, 0, 0];
-  for (let i = 0; i < field.length; i += 1) {
-    if (mask && !mask[i]) continue;
-    const q = quadrantIndex(i, g);
-    sums[q] += field[i];
-    counts[q] += 1;
-  }
-  const threshold = lS / 2;
-  return sums.map((sum, i) => {
-    const meanVal = counts[i] > 0 ? sum / counts[i] : 0;
-    return meanVal >= threshold ? 1 : 0;
-  });
-}
-
-export function errorRate(bitsA, bitsB) {
-  let mismatches = 0;
-  for (let i = 0; i < bitsA.length; i += 1) {
-    if (bitsA[i] !== bitsB[i]) mismatches += 1;
-  }
-  return mismatches / bitsA.length;
-}
-
-export function errRegionBits(baseField, metaField, g, lS, regionMask) {
-  const baseBits = logicalBitsFromField(baseField, g, lS, regionMask);
-  const metaBits = logicalBitsFromField(metaField, g, lS, regionMask);
-  return errorRate(metaBits, baseBits);
-}
-
-export function errQuadrantMean(baseField, metaField, g, lS, regionMask) {
-  const denom = lS > 0 ? lS : 1;
-  const baseMeans = quadrantMeans(baseField, g, regionMask);
-  const metaMeans = quadrantMeans(metaField, g, regionMask);
-  let acc = 0;
-  for (let i = 0; i < 4; i += 1) {
-    acc += Math.abs(baseMeans[i] - metaMeans[i]);
-  }
-  return acc / (4 * denom);
-}
-
-export function makeMask(seed, size, frac) {
-  let x = seed >>> 0;
-  if (x === 0) x = 1;
-  const mask = new Array(size);
-  for (let i = 0; i < size; i += 1) {
-    x ^= x << 13;
-    x ^= x >>> 17;
-    x ^= x << 5;
-    const r = (x >>> 8) / (1 << 24);
-    mask[i] = r < frac;
-  }
-  return mask;
-}
-
-export function errF05(baseBits, metaField, g, lS, seed) {
-  const trials = 20;
-  let acc = 0;
-  for (let t = 0; t < trials; t += 1) {
-    const mask = makeMask(seed + t * 101, metaField.length, 0.5);
-    const bits = logicalBitsFromField(metaField, g, lS, mask);
-    acc += errorRate(bits, baseBits);
-  }
-  return acc / trials;
-}
-
-export function errF05Region(baseField, metaField, g, lS, seed, regionMask) {
-  const trials = 20;
-  let acc = 0;
-  for (let t = 0; t < trials; t += 1) {
-    const mask = makeMask(seed + t * 101, metaField.length, 0.5);
-    if (regionMask) {
-      for (let i = 0; i < mask.length; i += 1) {
-        mask[i] = mask[i] && regionMask[i];
-      }
-    }
-    const baseBits = logicalBitsFromField(baseField, g, lS, mask);
-    const metaBits = logicalBitsFromField(metaField, g, lS, mask);
-    acc += errorRate(metaBits, baseBits);
+// RegionMask spec (see Sim.region_mask) for a quadrant or a span of clock-bin stripes.
+export function regionSpec(regionType, regionIndex, span, bins) {
+  if (regionType === "stripe") {
+    return { type: "stripe", bins, bin: regionIndex, span: Math.max(1, span) };
   }
-  return acc / trials;
+  return { type: "quadrant", quadrant: regionIndex };
 }
 
-function stripeIndex(idx, g, bins) {
-  const x = idx % g;
-  const fx = x / g;
-  return Math.min(bins - 1, Math.floor(fx * bins));
+// Bitmap RegionMask spec for a boolean or 0/1 cell mask.
+export function bitmapRegion(mask) {
+  return { type: "bitmap", mask: Array.from(mask, (v) => (v ? 1 : 0)) };
 }
 
-function regionMask(g, regionType, regionIndex, span, bins) {
-  const cells = g * g;
-  const mask = new Array(cells);
-  if (regionType === "stripe") {
-    const s = Math.max(1, span);
-    for (let i = 0; i < cells; i += 1) {
-      const stripe = stripeIndex(i, g, bins);
-      let ok = false;
-      for (let k = 0; k < s; k += 1) {
-        if ((regionIndex + k) % bins === stripe) {
-          ok = true;
-          break;
-        }
-      }
-      mask[i] = ok;
-    }
-  } else {
-    for (let i = 0; i < cells; i += 1) {
-      mask[i] = quadrantIndex(i, g) === regionIndex;
-    }
-  }
-  return mask;
+// Logical-bit error and mean |base - meta0| over `region`, from the core's code_metrics.
+export function regionCodeMetrics(sim, region) {
+  const metrics = sim.code_metrics({ region, trials: 0 });
+  return { err: metrics.bitErr[0] ?? 0, sdiff: metrics.sdiff[0] ?? 0 };
 }
 
-export function meanAbsDiffRegion(a, b, mask) {
-  let sum = 0;
-  let count = 0;
-  for (let i = 0; i < a.length; i += 1) {
-    if (!mask[i]) continue;
-    sum += Math.abs(a[i] - b[i]);
-    count += 1;
-  }
-  return count === 0 ? 0 : sum / count;
+// Reconstructibility of meta layer 0: logical-bit error read through 20 random cell subsets
+// keeping each cell with probability `frac` (the core's code_metrics `reconErr`).
+export function reconErr(sim, frac, seed) {
+  const metrics = sim.code_metrics({ frac, trials: 20, seed });
+  return metrics.reconErr[0] ?? 0;
 }
 
 function gateAllowsRegion(params, active, regionType, regionIndex, gateSpan, bins) {
@@ -297,7 +179,7 @@ export async function runDeadlineEvents({
 
   const bins = params.clockK ?? 8;
   const effectiveSpan = gateSpan ?? params.repairGateSpan ?? 1;
-  const regionMaskArr = regionMask(params.gridSize, regionType, regionIndex, effectiveSpan, bins);
+  const region = regionSpec(regionType, regionIndex, effectiveSpan, bins);
   const tailWindowSteps = tailWindow ?? 200_000;
   const graceWindow = Math.max(0, Math.floor(0.2 * deadline));
 
@@ -367,13 +249,7 @@ export async function runDeadlineEvents({
         }
       }
 
-      const baseS = sim.base_s_field();
-      const metaS = sim.meta_field();
-      const cells = baseS.length;
-      const meta0 = metaS.subarray(0, cells);
-      const sdiff = meanAbsDiffRegion(baseS, meta0, regionMaskArr);
-      const lS = params.lS ?? 1;
-      const errSample = errRegionBits(baseS, meta0, params.gridSize, lS, regionMaskArr);
+      const { err: errSample, sdiff } = regionCodeMetrics(sim, region);
       if (t < eventEvery) {
         baselineSamples.push(errSample);
       } else if (errFloor === null) {
@@ -439,14 +315,7 @@ export async function runDeadlineEvents({
     }
 
 
-    const baseS = sim.base_s_field();
-    const metaS = sim.meta_field();
-    const cells = baseS.length;
-    const meta0 = metaS.subarray(0, cells);
-    const lS = params.lS ?? 1;
-    const baseBits = logicalBitsFromField(baseS, params.gridSize, lS, regionMaskArr);
-    const errEnd = errorRate(baseBits, logicalBitsFromField(meta0, params.gridSize, lS, regionMaskArr));
-    const sdiffEnd = meanAbsDiffRegion(baseS, meta0, regionMaskArr);
+    const { err: errEnd, sdiff: sdiffEnd } = regionCodeMetrics(sim, region);
 
     const tailStart = Math.max(0, steps - tailWindowSteps);
     const tailSamples = sampleRecords.filter(
@@ -2,6 +2,7 @@
 import fs from "node:fs";
 import path from "node:path";
 import { fileURLToPath, pathToFileURL } from "node:url";
+import { reconErr } from "./deadline-event-utils.mjs";
 
 const __filename = fileURLToPath(import.meta.url);
 const __dirname = path.dirname(__filename);
@@ -50,17 +51,6 @@ function percentile(values, p) {
   return nums[idx];
 }
 
-function makeRng(seed) {
-  let x = seed >>> 0;
-  if (x === 0) x = 1;
-  return () => {
-    x ^= x << 13;
-    x ^= x >>> 17;
-    x ^= x << 5;
-    return (x >>> 8) / (1 << 24);
-  };
-}
-
 function meanAbsDiff(a, b) {
   let sum = 0;
   for (let i = 0; i < a.length; i += 1) {
@@ -69,53 +59,10 @@ function meanAbsDiff(a, b) {
   return a.length === 0 ? 0 : sum / a.length;
 }
 
-function quadrantIndex(idx, g) {
-  const x = idx % g;
-  const y = Math.floor(idx / g);
-  const qx = x < g / 2 ? 0 : 1;
-  const qy = y < g / 2 ? 0 : 1;
-  return qy * 2 + qx;
-}
-
-function logicalBitsFromField(field, g, lS, mask) {
-  const sums = [0, 0, 0, 0];
-  const counts = [0, 0, 0, 0];
-  for (let i = 0; i < field.length; i += 1) {
-    if (mask && !mask[i]) continue;
-    const q = quadrantIndex(i, g);
-    sums[q] += field[i];
-    counts[q] += 1;
-  }
-  const threshold = lS / 2;
-  return sums.map((sum, i) => {
-    const meanVal = counts[i] > 0 ? sum / counts[i] : 0;
-    return meanVal >= threshold ? 1 : 0;
-  });
-}
-
-function errorRate(bitsA, bitsB) {
-  let mismatches = 0;
-  for (let i = 0; i < bitsA.length; i += 1) {
-    if (bitsA[i] !== bitsB[i]) mismatches += 1;
-  }
-  return mismatches / bitsA.length;
-}
-
-function reconstructibilityErrors(baseBits, metaField, g, lS, seed) {
-  const rng = makeRng(seed);
+function reconstructibilityErrors(sim, seed) {
   const errors = {};
   for (const frac of fractions) {
-    const trials = 20;
-    let acc = 0;
-    for (let t = 0; t < trials; t += 1) {
-      const mask = new Array(metaField.length);
-      for (let i = 0; i < metaField.length; i += 1) {
-        mask[i] = rng() < frac;
-      }
-      const bits = logicalBitsFromField(metaField, g, lS, mask);
-      acc += errorRate(bits, baseBits);
-    }
-    errors[frac] = acc / trials;
+    errors[frac] = reconErr(sim, frac, seed);
   }
   return errors;
 }
@@ -174,9 +121,7 @@ function runCondition(id, params) {
     const cells = baseS.length;
     const meta0 = metaS.subarray(0, cells);
     const meta1 = metaS.subarray(cells, 2 * cells);
-    const lS = params.lS ?? 1;
-    const baseBits = logicalBitsFromField(baseS, params.gridSize, lS);
-    const errCurve = reconstructibilityErrors(baseBits, meta0, params.gridSize, lS, seed + 5000);
+    const errCurve = reconstructibilityErrors(sim, seed + 5000);
     const finalSdiffBase = meanAbsDiff(baseS, meta0);
     const finalSdiffMeta = meanAbsDiff(meta0, meta1);
     const epTotal = sim.ep_exact_total();
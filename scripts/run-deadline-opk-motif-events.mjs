@@ -7,8 +7,8 @@ import {
   std,
   percentile,
   readJson,
-  errRegionBits,
-  meanAbsDiffRegion,
+  regionSpec,
+  regionCodeMetrics,
 } from "./deadline-event-utils.mjs";
 import { parseOpOffsets } from "./opk-metrics.mjs";
 import {
@@ -1178,11 +1178,8 @@ async function runOnce(args) {
     }
 
     if (t === nextReport) {
-      const baseS = sim.base_s_field();
-      const metaS = sim.meta_field();
-      const meta0 = metaS.subarray(0, cells);
-      const sdiff = meanAbsDiffRegion(baseS, meta0, regionMask);
-      const errSample = errRegionBits(baseS, meta0, gridSize, lS, regionMask);
+      const region = regionSpec(regionType, regionIndex, gateSpan, bins);
+      const { err: errSample, sdiff } = regionCodeMetrics(sim, region);
       const errAdj = errSample;
       const good = sdiff <= args.sdiffGood && errAdj <= args.errGood;
       const sinceEvent = lastEventTime === null ? Number.POSITIVE_INFINITY : t - lastEventTime;
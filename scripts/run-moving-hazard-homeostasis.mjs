@@ -8,8 +8,8 @@ import {
   std,
   percentile,
   parseSeedList,
-  meanAbsDiffRegion,
-  errRegionBits,
+  bitmapRegion,
+  regionCodeMetrics,
 } from "./deadline-event-utils.mjs";
 import { parseOpOffsets } from "./opk-metrics.mjs";
 
@@ -687,19 +687,13 @@ export async function runMovingHazardHomeostasis({
           const event = events[currentEventIdx];
           if (!event.recovered && !event.miss) {
             const hazardMask = hazardMasks.get(event.hazardIndex);
-            const baseS = sim.base_s_field();
-            const metaS = sim.meta_field();
-            const cells = baseS.length;
-            const meta0 = metaS.subarray(0, cells);
-            const lS = params.lS ?? 1;
-            const errSample = errRegionBits(baseS, meta0, gridSize, lS, hazardMask);
+            const { err: errSample, sdiff } = regionCodeMetrics(sim, bitmapRegion(hazardMask));
             if (t < eventEveryVal) {
               baselineErr.push(errSample);
             } else if (errFloor === null) {
               errFloor = baselineErr.length ? mean(baselineErr) : 0;
             }
             const errAdj = errFloor === null ? 0 : Math.max(0, errSample - errFloor);
-            const sdiff = meanAbsDiffRegion(baseS, meta0, hazardMask);
             const good = sdiff <= sdiffGood && errAdj <= errGood;
             event.samples.push({ t, err: errAdj, sdiff, good });
             updateEventOutcome(event, t, good);
@@ -3,6 +3,7 @@ import assert from "node:assert/strict";
 import fs from "node:fs";
 import path from "node:path";
 import { fileURLToPath, pathToFileURL } from "node:url";
+import { reconErr, regionCodeMetrics } from "./deadline-event-utils.mjs";
 
 const __filename = fileURLToPath(import.meta.url);
 const __dirname = path.dirname(__filename);
@@ -36,87 +37,6 @@ function percentile(values, p) {
   return nums[idx];
 }
 
-function meanAbsDiff(a, b) {
-  let sum = 0;
-  for (let i = 0; i < a.length; i += 1) {
-    sum += Math.abs(a[i] - b[i]);
-  }
-  return a.length === 0 ? 0 : sum / a.length;
-}
-
-function meanAbsDiffQuadrant(a, b, g, quadrant) {
-  let sum = 0;
-  let count = 0;
-  for (let i = 0; i < a.length; i += 1) {
-    const x = i % g;
-    const y = Math.floor(i / g);
-    const qx = x < g / 2 ? 0 : 1;
-    const qy = y < g / 2 ? 0 : 1;
-    const q = qy * 2 + qx;
-    if (q !== quadrant) continue;
-    sum += Math.abs(a[i] - b[i]);
-    count += 1;
-  }
-  return count === 0 ? 0 : sum / count;
-}
-
-function quadrantIndex(idx, g) {
-  const x = idx % g;
-  const y = Math.floor(idx / g);
-  const qx = x < g / 2 ? 0 : 1;
-  const qy = y < g / 2 ? 0 : 1;
-  return qy * 2 + qx;
-}
-
-function logicalBitsFromField(field, g, lS, mask) {
-  const sums = [0, 0, 0, 0];
-  const counts = [0, 0, 0, 0];
-  for (let i = 0; i < field.length; i += 1) {
-    if (mask && !mask[i]) continue;
-    const q = quadrantIndex(i, g);
-    sums[q] += field[i];
-    counts[q] += 1;
-  }
-  const threshold = lS / 2;
-  return sums.map((sum, i) => {
-    const meanVal = counts[i] > 0 ? sum / counts[i] : 0;
-    return meanVal >= threshold ? 1 : 0;
-  });
-}
-
-function errorRate(bitsA, bitsB) {
-  let mismatches = 0;
-  for (let i = 0; i < bitsA.length; i += 1) {
-    if (bitsA[i] !== bitsB[i]) mismatches += 1;
-  }
-  return mismatches / bitsA.length;
-}
-
-function makeMask(seed, size, frac) {
-  let x = seed >>> 0;
-  if (x === 0) x = 1;
-  const mask = new Array(size);
-  for (let i = 0; i < size; i += 1) {
-    x ^= x << 13;
-    x ^= x >>> 17;
-    x ^= x << 5;
-    const r = (x >>> 8) / (1 << 24);
-    mask[i] = r < frac;
-  }
-  return mask;
-}
-
-function errF05(baseBits, metaField, g, lS, seed) {
-  const trials = 20;
-  let acc = 0;
-  for (let t = 0; t < trials; t += 1) {
-    const mask = makeMask(seed + t * 101, metaField.length, 0.5);
-    const bits = logicalBitsFromField(metaField, g, lS, mask);
-    acc += errorRate(bits, baseBits);
-  }
-  return acc / trials;
-}
-
 const baseParams = {
   beta: 2.0,
   stepSize: 0.01,
@@ -168,11 +88,7 @@ function runPreset(id, overrides) {
 
     for (let step = reportEvery; step <= steps; step += reportEvery) {
       sim.step(reportEvery);
-      const baseS = sim.base_s_field();
-      const metaS = sim.meta_field();
-      const cells = baseS.length;
-      const meta0 = metaS.subarray(0, cells);
-      const sdiffBase = meanAbsDiffQuadrant(baseS, meta0, params.gridSize, 2);
+      const sdiffBase = regionCodeMetrics(sim, { type: "quadrant", quadrant: 2 }).sdiff;
       if (!perturbApplied) {
         if (step >= perturbStep) {
           baselineTarget = 4.0;
@@ -194,14 +110,8 @@ function runPreset(id, overrides) {
       }
     }
 
-    const baseS = sim.base_s_field();
-    const metaS = sim.meta_field();
-    const cells = baseS.length;
-    const meta0 = metaS.subarray(0, cells);
-    const lS = params.lS ?? 1;
-    const baseBits = logicalBitsFromField(baseS, params.gridSize, lS);
-    const err = errF05(baseBits, meta0, params.gridSize, lS, seed + 9000);
-    const sdiffBase = meanAbsDiffQuadrant(baseS, meta0, params.gridSize, 2);
+    const err = reconErr(sim, 0.5, seed + 9000);
+    const sdiffBase = regionCodeMetrics(sim, { type: "quadrant", quadrant: 2 }).sdiff;
     const missDeadline = recoverySteps === null || recoverySteps > deadline;
 
     runs.push({
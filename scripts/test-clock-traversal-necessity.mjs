@@ -3,6 +3,7 @@ import assert from "node:assert/strict";
 import fs from "node:fs";
 import path from "node:path";
 import { fileURLToPath, pathToFileURL } from "node:url";
+import { reconErr } from "./deadline-event-utils.mjs";
 
 const __filename = fileURLToPath(import.meta.url);
 const __dirname = path.dirname(__filename);
@@ -47,63 +48,6 @@ function meanAbsDiff(a, b) {
   return a.length === 0 ? 0 : sum / a.length;
 }
 
-function quadrantIndex(idx, g) {
-  const x = idx % g;
-  const y = Math.floor(idx / g);
-  const qx = x < g / 2 ? 0 : 1;
-  const qy = y < g / 2 ? 0 : 1;
-  return qy * 2 + qx;
-}
-
-function logicalBitsFromField(field, g, lS, mask) {
-  const sums = [0, 0, 0, 0];
-  const counts = [0, 0, 0, 0];
-  for (let i = 0; i < field.length; i += 1) {
-    if (mask && !mask[i]) continue;
-    const q = quadrantIndex(i, g);
-    sums[q] += field[i];
-    counts[q] += 1;
-  }
-  const threshold = lS / 2;
-  return sums.map((sum, i) => {
-    const meanVal = counts[i] > 0 ? sum / counts[i] : 0;
-    return meanVal >= threshold ? 1 : 0;
-  });
-}
-
-function errorRate(bitsA, bitsB) {
-  let mismatches = 0;
-  for (let i = 0; i < bitsA.length; i += 1) {
-    if (bitsA[i] !== bitsB[i]) mismatches += 1;
-  }
-  return mismatches / bitsA.length;
-}
-
-function makeMask(seed, size, frac) {
-  let x = seed >>> 0;
-  if (x === 0) x = 1;
-  const mask = new Array(size);
-  for (let i = 0; i < size; i += 1) {
-    x ^= x << 13;
-    x ^= x >>> 17;
-    x ^= x << 5;
-    const r = (x >>> 8) / (1 << 24);
-    mask[i] = r < frac;
-  }
-  return mask;
-}
-
-function errF05(baseBits, metaField, g, lS, seed) {
-  const trials = 20;
-  let acc = 0;
-  for (let t = 0; t < trials; t += 1) {
-    const mask = makeMask(seed + t * 101, metaField.length, 0.5);
-    const bits = logicalBitsFromField(metaField, g, lS, mask);
-    acc += errorRate(bits, baseBits);
-  }
-  return acc / trials;
-}
-
 function runPreset(id, paramsPath) {
   const params = readJson(path.resolve(rootDir, paramsPath));
   const runs = [];
@@ -148,9 +92,7 @@ function runPreset(id, paramsPath) {
     const metaS = sim.meta_field();
     const cells = baseS.length;
     const meta0 = metaS.subarray(0, cells);
-    const lS = params.lS ?? 1;
-    const baseBits = logicalBitsFromField(baseS, params.gridSize, lS);
-    const err = errF05(baseBits, meta0, params.gridSize, lS, seed + 4000);
+    const err = reconErr(sim, 0.5, seed + 4000);
     const sdiffBase = meanAbsDiff(baseS, meta0);
 
     runs.push({